@@ -0,0 +1,59 @@
+//! Throughput of the in-memory cache under concurrent access, for a growing
+//! number of shards and tasks. Run with `cargo bench --bench cache_shards`.
+
+use rinha::modules::cache::redis::{CachePolicy, RedisCache};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+const KEYS: usize = 10_000;
+const OPS_PER_TASK: usize = 200_000;
+/// One write for every this many operations; the rest are reads.
+const WRITE_EVERY: usize = 5;
+const TTL: Option<Duration> = Some(Duration::from_secs(60));
+
+async fn run(shards: usize, tasks: usize) -> f64 {
+    let cache = Arc::new(RedisCache::with_shards(64, CachePolicy::AllKeysLRU, shards));
+    for i in 0..KEYS {
+        cache.set_raw(&format!("key-{}", i), "x".repeat(64), TTL).await.unwrap();
+    }
+
+    let started = Instant::now();
+    let handles: Vec<_> = (0..tasks)
+        .map(|task| {
+            let cache = Arc::clone(&cache);
+            tokio::spawn(async move {
+                for op in 0..OPS_PER_TASK {
+                    let key = format!("key-{}", (op * 7919 + task * 104_729) % KEYS);
+                    if op % WRITE_EVERY == 0 {
+                        cache.set_raw(&key, "y".repeat(64), TTL).await.unwrap();
+                    } else {
+                        cache.get_raw(&key).await.unwrap();
+                    }
+                }
+            })
+        })
+        .collect();
+    for handle in handles {
+        handle.await.unwrap();
+    }
+
+    (tasks * OPS_PER_TASK) as f64 / started.elapsed().as_secs_f64()
+}
+
+fn main() {
+    let threads = std::thread::available_parallelism().map_or(4, |threads| threads.get());
+    let runtime = tokio::runtime::Builder::new_multi_thread()
+        .worker_threads(threads)
+        .enable_all()
+        .build()
+        .unwrap();
+
+    println!("{} worker threads, {} ops per task, 1 write in {}", threads, OPS_PER_TASK, WRITE_EVERY);
+    println!("{:>6} {:>6} {:>14}", "shards", "tasks", "ops/sec");
+    for shards in [1, 4, 16, 64] {
+        for tasks in [1, 2, 4, 8, 16] {
+            let throughput = runtime.block_on(run(shards, tasks));
+            println!("{:>6} {:>6} {:>14.0}", shards, tasks, throughput);
+        }
+    }
+}
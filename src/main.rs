@@ -1,13 +1,17 @@
-mod modules;
-
+use std::sync::Arc;
 use axum::{
     routing::{get, post},
     Router,
     http::StatusCode,
 };
 use serde_json::json;
-use modules::config::Config;
-use modules::payment::create_payment;
+use rinha::modules::config::Config;
+use rinha::modules::payment::create_payment;
+use rinha::modules::summary::get_payments_summary;
+use rinha::modules::ApplicationServices;
+use rinha::modules::config::IntakeMode;
+use rinha::modules::queue::get_queue_metrics;
+use rinha::modules::cache::get_cache_info;
 
 #[tokio::main]
 async fn main() {
@@ -15,15 +19,92 @@ async fn main() {
     
     env_logger::init();
 
+    let mut services = ApplicationServices::with_config(&config);
+    if let Some(path) = &config.cache_snapshot_path {
+        match services.cache_manager.load_snapshot(path).await {
+            Ok(restored) => log::info!("Restored {} cache entries from {}", restored, path.display()),
+            Err(e) => log::warn!("Could not load cache snapshot: {}", e),
+        }
+        let interval = config.cache_snapshot_interval;
+        if !interval.is_zero() && services.cache_manager.start_snapshots(path.clone(), interval) {
+            log::info!("Cache snapshots scheduled (every {:?})", interval);
+        }
+    }
+    if config.intake_mode == IntakeMode::Async {
+        services.start_payment_queue(&config);
+        log::info!(
+            "Async payment intake enabled ({} workers, capacity {})",
+            config.queue_workers,
+            config.queue_capacity
+        );
+    }
+    services.health_manager.start_poller();
+    log::info!("Health poller started (every {:?})", config.health_poll_interval);
+    if services.cache_manager.start_expiry_sweeper(config.cache_sweep_interval) {
+        log::info!("Cache expiry sweeper started (every {:?})", config.cache_sweep_interval);
+    }
+    if services.cache_manager.start_invalidation_listener() {
+        log::info!("Cache L1 listening for invalidations on {}", config.cache_tiers.channel);
+    }
+    let services = Arc::new(services);
+
     let app = Router::new()
         .route("/", get(root))
         .route("/health", get(health))
-        .route("/payments", post(create_payment));
+        .route("/payments", post(create_payment))
+        .route("/payments-summary", get(get_payments_summary))
+        .with_state(Arc::clone(&services));
+
+    if let Some(metrics_addr) = config.metrics_addr {
+        let metrics = Router::new()
+            .route("/metrics/queue", get(get_queue_metrics))
+            .route("/metrics/cache", get(get_cache_info))
+            .with_state(Arc::clone(&services));
+        let listener = tokio::net::TcpListener::bind(metrics_addr).await.unwrap();
+        log::info!("Serving metrics on {}", metrics_addr);
+        tokio::spawn(async move {
+            if let Err(e) = axum::serve(listener, metrics).await {
+                log::error!("Metrics listener failed: {}", e);
+            }
+        });
+    }
 
     log::info!("Starting server on {}", config.server_addr());
 
     let listener = tokio::net::TcpListener::bind(config.server_addr()).await.unwrap();
-    axum::serve(listener, app).await.unwrap();
+    axum::serve(listener, app).with_graceful_shutdown(shutdown_signal()).await.unwrap();
+
+    if let Some(path) = &config.cache_snapshot_path {
+        match services.cache_manager.save_snapshot(path).await {
+            Ok(Some(saved)) => log::info!("Saved {} cache entries to {}", saved, path.display()),
+            Ok(None) => {}
+            Err(e) => log::error!("Could not save cache snapshot: {}", e),
+        }
+    }
+}
+
+/// Resolves on Ctrl+C or SIGTERM, which is how Docker stops a container.
+async fn shutdown_signal() {
+    let ctrl_c = async {
+        let _ = tokio::signal::ctrl_c().await;
+    };
+    #[cfg(unix)]
+    let terminate = async {
+        match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+            Ok(mut signal) => {
+                signal.recv().await;
+            }
+            Err(_) => std::future::pending().await,
+        }
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => {}
+        _ = terminate => {}
+    }
+    log::info!("Shutting down");
 }
 
 async fn root() -> &'static str {
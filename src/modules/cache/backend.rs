@@ -0,0 +1,246 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::time::Duration;
+use axum::async_trait;
+use serde::Serialize;
+use crate::modules::clock::SharedClock;
+use crate::modules::error::PaymentError;
+use super::redis::RedisCache;
+use super::snapshot::CacheSnapshot;
+use super::stats::CacheStats;
+
+/// Answer to a TTL query, mirroring the Redis `PTTL` replies.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EntryTtl {
+    /// No such key (`-2`).
+    Missing,
+    /// The key exists and never expires (`-1`).
+    Persistent,
+    /// Time left before the key expires.
+    Expires(Duration),
+}
+
+/// Memory held by a backend, in bytes.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct MemoryStats {
+    pub used_bytes: u64,
+    pub limit_bytes: u64,
+    /// Bytes held by entries with a TTL, the ones volatile policies may
+    /// evict; 0 when the backend cannot tell.
+    pub volatile_bytes: u64,
+    pub entry_count: usize,
+}
+
+/// Storage behind `CacheManager`. Values cross this boundary already
+/// serialized, which keeps the trait object-safe; `CacheManager` does the
+/// typed (de)serialization.
+#[async_trait]
+pub trait CacheBackend: Send + Sync {
+    /// Short name for logs, e.g. `memory` or `redis`.
+    fn name(&self) -> &'static str;
+
+    /// Stores `value` under `key`; with no `ttl` the entry never expires.
+    async fn set_raw(&self, key: &str, value: String, ttl: Option<Duration>) -> Result<(), PaymentError>;
+
+    async fn get_raw(&self, key: &str) -> Result<Option<String>, PaymentError>;
+
+    async fn remove(&self, key: &str) -> Result<bool, PaymentError>;
+
+    async fn clear(&self) -> Result<(), PaymentError>;
+
+    /// Takes or renews a lease on `key` for `holder`. Succeeds when the key
+    /// is free, expired, or already held by `holder`.
+    async fn acquire_lease(&self, key: &str, holder: &str, ttl: Duration) -> Result<bool, PaymentError>;
+
+    /// Adds `delta` to the integer at `key`, creating it at 0 and keeping
+    /// its TTL. Fails with `PaymentError::Serialization` if the value is not
+    /// an integer or the sum overflows.
+    async fn incr_by(&self, key: &str, delta: i64) -> Result<i64, PaymentError>;
+
+    async fn incr_by_float(&self, key: &str, delta: f64) -> Result<f64, PaymentError>;
+
+    /// Stores `value` unless `key` exists. Returns whether it was stored.
+    async fn set_nx(&self, key: &str, value: String, ttl: Option<Duration>) -> Result<bool, PaymentError>;
+
+    /// Stores `value` with `ttl` only if `key` holds exactly `expected`.
+    async fn compare_and_swap(
+        &self,
+        key: &str,
+        expected: &str,
+        value: String,
+        ttl: Option<Duration>,
+    ) -> Result<bool, PaymentError>;
+
+    /// Adds `delta` to an integer field of the hash at `key`.
+    async fn hincr_by(&self, key: &str, field: &str, delta: i64) -> Result<i64, PaymentError>;
+
+    async fn hincr_by_float(&self, key: &str, field: &str, delta: f64) -> Result<f64, PaymentError>;
+
+    async fn hget_all(&self, key: &str) -> Result<HashMap<String, String>, PaymentError>;
+
+    async fn ttl(&self, key: &str) -> Result<EntryTtl, PaymentError>;
+
+    /// Sets a new TTL on an existing key. Returns false if there is no such key.
+    async fn expire(&self, key: &str, ttl: Duration) -> Result<bool, PaymentError>;
+
+    /// Removes the TTL of `key`. Returns false if the key is missing or
+    /// already persistent.
+    async fn persist(&self, key: &str) -> Result<bool, PaymentError>;
+
+    async fn get_memory_usage_mb(&self) -> u64;
+
+    async fn memory_stats(&self) -> MemoryStats;
+
+    /// Hit, miss, expiry, eviction and write counters plus memory.
+    async fn stats(&self) -> CacheStats;
+
+    async fn get_entry_count(&self) -> usize;
+
+    fn get_memory_limit_mb(&self) -> u64;
+
+    /// Backends that keep their own expiry clock take this one instead.
+    fn set_clock(&mut self, _clock: SharedClock) {}
+
+    /// Starts removing expired entries in the background. Backends that
+    /// expire on their own, like a Redis server, return false.
+    fn start_sweeper(&self, _interval: Duration) -> bool {
+        false
+    }
+
+    /// Starts applying invalidations from other replicas to a local tier.
+    /// Backends without one return false.
+    fn start_invalidation_listener(&self) -> bool {
+        false
+    }
+
+    /// Live entries to warm up a restarted process with. Backends whose
+    /// data outlives the process, like a Redis server, return `None`.
+    async fn snapshot(&self) -> Option<CacheSnapshot> {
+        None
+    }
+
+    /// Loads what is still live in `snapshot` and returns how many entries
+    /// that was.
+    async fn restore(&self, _snapshot: &CacheSnapshot) -> usize {
+        0
+    }
+
+    /// Starts writing a snapshot to `path` every `interval`. Backends that
+    /// keep no snapshots return false.
+    fn start_snapshots(&self, _path: PathBuf, _interval: Duration) -> bool {
+        false
+    }
+}
+
+#[async_trait]
+impl CacheBackend for RedisCache {
+    fn name(&self) -> &'static str {
+        "memory"
+    }
+
+    async fn set_raw(&self, key: &str, value: String, ttl: Option<Duration>) -> Result<(), PaymentError> {
+        RedisCache::set_raw(self, key, value, ttl).await
+    }
+
+    async fn get_raw(&self, key: &str) -> Result<Option<String>, PaymentError> {
+        RedisCache::get_raw(self, key).await
+    }
+
+    async fn remove(&self, key: &str) -> Result<bool, PaymentError> {
+        RedisCache::remove(self, key).await
+    }
+
+    async fn clear(&self) -> Result<(), PaymentError> {
+        RedisCache::clear(self).await
+    }
+
+    async fn acquire_lease(&self, key: &str, holder: &str, ttl: Duration) -> Result<bool, PaymentError> {
+        RedisCache::acquire_lease(self, key, holder, ttl).await
+    }
+
+    async fn incr_by(&self, key: &str, delta: i64) -> Result<i64, PaymentError> {
+        RedisCache::incr_by(self, key, delta).await
+    }
+
+    async fn incr_by_float(&self, key: &str, delta: f64) -> Result<f64, PaymentError> {
+        RedisCache::incr_by_float(self, key, delta).await
+    }
+
+    async fn set_nx(&self, key: &str, value: String, ttl: Option<Duration>) -> Result<bool, PaymentError> {
+        RedisCache::set_nx(self, key, value, ttl).await
+    }
+
+    async fn compare_and_swap(
+        &self,
+        key: &str,
+        expected: &str,
+        value: String,
+        ttl: Option<Duration>,
+    ) -> Result<bool, PaymentError> {
+        RedisCache::compare_and_swap(self, key, expected, value, ttl).await
+    }
+
+    async fn hincr_by(&self, key: &str, field: &str, delta: i64) -> Result<i64, PaymentError> {
+        RedisCache::hincr_by(self, key, field, delta).await
+    }
+
+    async fn hincr_by_float(&self, key: &str, field: &str, delta: f64) -> Result<f64, PaymentError> {
+        RedisCache::hincr_by_float(self, key, field, delta).await
+    }
+
+    async fn hget_all(&self, key: &str) -> Result<HashMap<String, String>, PaymentError> {
+        RedisCache::hget_all(self, key).await
+    }
+
+    async fn ttl(&self, key: &str) -> Result<EntryTtl, PaymentError> {
+        RedisCache::ttl(self, key).await
+    }
+
+    async fn expire(&self, key: &str, ttl: Duration) -> Result<bool, PaymentError> {
+        RedisCache::expire(self, key, ttl).await
+    }
+
+    async fn persist(&self, key: &str) -> Result<bool, PaymentError> {
+        RedisCache::persist(self, key).await
+    }
+
+    async fn get_memory_usage_mb(&self) -> u64 {
+        RedisCache::get_memory_usage_mb(self).await
+    }
+
+    async fn memory_stats(&self) -> MemoryStats {
+        RedisCache::memory_stats(self).await
+    }
+
+    async fn stats(&self) -> CacheStats {
+        RedisCache::stats(self).await
+    }
+
+    async fn get_entry_count(&self) -> usize {
+        RedisCache::get_entry_count(self).await
+    }
+
+    fn get_memory_limit_mb(&self) -> u64 {
+        RedisCache::get_memory_limit_mb(self)
+    }
+
+    fn set_clock(&mut self, clock: SharedClock) {
+        RedisCache::set_clock(self, clock);
+    }
+
+    fn start_sweeper(&self, interval: Duration) -> bool {
+        RedisCache::start_sweeper(self, interval)
+    }
+
+    async fn snapshot(&self) -> Option<CacheSnapshot> {
+        Some(RedisCache::snapshot(self).await)
+    }
+
+    async fn restore(&self, snapshot: &CacheSnapshot) -> usize {
+        RedisCache::restore(self, snapshot).await
+    }
+
+    fn start_snapshots(&self, path: PathBuf, interval: Duration) -> bool {
+        RedisCache::start_snapshots(self, path, interval)
+    }
+}
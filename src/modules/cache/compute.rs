@@ -0,0 +1,201 @@
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use tokio::sync::OnceCell;
+use crate::modules::error::PaymentError;
+use super::CacheManager;
+
+/// How `CacheManager::get_or_compute_with` caches what it computes.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ComputePolicy {
+    /// How long a computed value is served as fresh.
+    pub ttl: Duration,
+    /// Once `ttl` has passed, how much longer the old value is served while
+    /// a single background task computes the new one.
+    pub stale_while_revalidate: Option<Duration>,
+    /// Caches a failed computation for this long. Errors are not cached by
+    /// default, so the next caller simply tries again.
+    pub error_ttl: Option<Duration>,
+}
+
+impl ComputePolicy {
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            stale_while_revalidate: None,
+            error_ttl: None,
+        }
+    }
+}
+
+/// What a computed key holds in the backend. Keys filled by
+/// `get_or_compute` should only be read through it.
+#[derive(Serialize, Deserialize)]
+struct Computed {
+    /// The value as JSON, or the error it failed with.
+    result: Result<String, PaymentError>,
+    /// Clock milliseconds until which the result counts as fresh.
+    fresh_until: u64,
+}
+
+type ComputeResult = Result<String, PaymentError>;
+type Call = Arc<OnceCell<ComputeResult>>;
+
+/// Computations currently running, by key. Every caller for a key waits on
+/// the same cell; if the caller running it is cancelled, the next waiter
+/// takes over.
+#[derive(Default)]
+pub(super) struct InFlight {
+    calls: Mutex<HashMap<String, Call>>,
+}
+
+impl InFlight {
+    fn join(&self, key: &str) -> Call {
+        let mut calls = self.calls.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        Arc::clone(calls.entry(key.to_string()).or_default())
+    }
+
+    fn is_running(&self, key: &str) -> bool {
+        let calls = self.calls.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        calls.contains_key(key)
+    }
+
+    fn finish(&self, key: &str, call: &Call) {
+        let mut calls = self.calls.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        if calls.get(key).is_some_and(|running| Arc::ptr_eq(running, call)) {
+            calls.remove(key);
+        }
+    }
+}
+
+impl CacheManager {
+    /// Returns the cached value for `key`, or computes and caches it for
+    /// `ttl`. Concurrent callers that miss the same key share one
+    /// computation and all receive its result. Errors are not cached.
+    pub async fn get_or_compute<T, F, Fut>(&self, key: &str, ttl: Duration, compute: F) -> Result<T, PaymentError>
+    where
+        T: Serialize + DeserializeOwned,
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<T, PaymentError>>,
+    {
+        if let Some(computed) = self.read_computed(key).await {
+            if self.is_fresh(&computed) {
+                return decode(computed.result);
+            }
+        }
+        decode(self.compute_shared(key, &ComputePolicy::new(ttl), compute).await)
+    }
+
+    /// `get_or_compute` with a full `ComputePolicy`. Within the
+    /// stale-while-revalidate window the old value is returned at once and
+    /// the refresh runs on a spawned task, hence the `'static` bounds.
+    pub async fn get_or_compute_with<T, F, Fut>(
+        self: &Arc<Self>,
+        key: &str,
+        policy: &ComputePolicy,
+        compute: F,
+    ) -> Result<T, PaymentError>
+    where
+        T: Serialize + DeserializeOwned + Send + 'static,
+        F: FnOnce() -> Fut + Send + 'static,
+        Fut: Future<Output = Result<T, PaymentError>> + Send + 'static,
+    {
+        if let Some(computed) = self.read_computed(key).await {
+            if self.is_fresh(&computed) {
+                return decode(computed.result);
+            }
+            if let Some(window) = policy.stale_while_revalidate {
+                let stale_until = computed.fresh_until.saturating_add(window.as_millis() as u64);
+                if computed.result.is_ok() && self.clock.now_millis() <= stale_until {
+                    self.spawn_refresh(key, policy, compute);
+                    return decode(computed.result);
+                }
+            }
+        }
+        decode(self.compute_shared(key, policy, compute).await)
+    }
+
+    fn spawn_refresh<T, F, Fut>(self: &Arc<Self>, key: &str, policy: &ComputePolicy, compute: F)
+    where
+        T: Serialize + Send + 'static,
+        F: FnOnce() -> Fut + Send + 'static,
+        Fut: Future<Output = Result<T, PaymentError>> + Send + 'static,
+    {
+        if self.in_flight.is_running(key) {
+            return;
+        }
+        let cache = Arc::clone(self);
+        let key = key.to_string();
+        let policy = policy.clone();
+        tokio::spawn(async move {
+            if let Err(e) = cache.compute_shared(&key, &policy, compute).await {
+                log::warn!("Background refresh of {} failed: {}", key, e);
+            }
+        });
+    }
+
+    /// Runs `compute` unless a computation for `key` is already running, in
+    /// which case it waits for that one instead.
+    async fn compute_shared<T, F, Fut>(&self, key: &str, policy: &ComputePolicy, compute: F) -> ComputeResult
+    where
+        T: Serialize,
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<T, PaymentError>>,
+    {
+        let call = self.in_flight.join(key);
+        let result = call
+            .get_or_init(|| async {
+                // Another caller may have finished between our miss and joining
+                if let Some(computed) = self.read_computed(key).await {
+                    if self.is_fresh(&computed) {
+                        return computed.result;
+                    }
+                }
+                let result = compute()
+                    .await
+                    .and_then(|value| Ok(serde_json::to_string(&value)?));
+                self.store_computed(key, policy, &result).await;
+                result
+            })
+            .await
+            .clone();
+        self.in_flight.finish(key, &call);
+        result
+    }
+
+    async fn read_computed(&self, key: &str) -> Option<Computed> {
+        match self.get::<Computed>(key).await {
+            Ok(computed) => computed,
+            Err(e) => {
+                log::debug!("Treating {} as a miss: {}", key, e);
+                None
+            }
+        }
+    }
+
+    async fn store_computed(&self, key: &str, policy: &ComputePolicy, result: &ComputeResult) {
+        let (fresh_for, keep_for) = match (result, policy.error_ttl) {
+            (Ok(_), _) => (policy.ttl, policy.ttl + policy.stale_while_revalidate.unwrap_or_default()),
+            (Err(_), Some(error_ttl)) => (error_ttl, error_ttl),
+            (Err(_), None) => return,
+        };
+        let computed = Computed {
+            result: result.clone(),
+            fresh_until: self.clock.now_millis().saturating_add(fresh_for.as_millis() as u64),
+        };
+        if let Err(e) = self.set(key, &computed, keep_for).await {
+            log::warn!("Could not cache computed {}: {}", key, e);
+        }
+    }
+
+    fn is_fresh(&self, computed: &Computed) -> bool {
+        self.clock.now_millis() < computed.fresh_until
+    }
+}
+
+fn decode<T: DeserializeOwned>(result: ComputeResult) -> Result<T, PaymentError> {
+    Ok(serde_json::from_str(&result?)?)
+}
@@ -0,0 +1,194 @@
+use std::sync::Mutex;
+use std::time::Duration;
+use axum::async_trait;
+use futures_util::StreamExt;
+use redis::aio::ConnectionManager;
+use serde::{Deserialize, Serialize};
+use tokio::sync::{broadcast, OnceCell};
+use crate::modules::error::PaymentError;
+use super::redis_server::RedisSettings;
+
+/// Invalidations a subscriber can fall behind by before it has to drop its
+/// whole L1 instead.
+const BUS_CAPACITY: usize = 1024;
+/// Wait before resubscribing after the Redis connection drops.
+const RESUBSCRIBE_DELAY: Duration = Duration::from_secs(1);
+
+/// Tells replicas to drop L1 copies after a write to the shared cache.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Invalidation {
+    /// Replica that made the write; it has already dropped its own copy.
+    pub origin: String,
+    /// The key written, or `None` for every key.
+    pub key: Option<String>,
+}
+
+impl Invalidation {
+    pub fn key(origin: &str, key: &str) -> Self {
+        Self {
+            origin: origin.to_string(),
+            key: Some(key.to_string()),
+        }
+    }
+
+    pub fn all(origin: &str) -> Self {
+        Self {
+            origin: origin.to_string(),
+            key: None,
+        }
+    }
+}
+
+/// Carries invalidations between the replicas sharing an L2.
+#[async_trait]
+pub trait InvalidationBus: Send + Sync {
+    async fn publish(&self, invalidation: &Invalidation) -> Result<(), PaymentError>;
+
+    /// Invalidations published by any replica, this one included.
+    fn subscribe(&self) -> broadcast::Receiver<Invalidation>;
+}
+
+/// Bus within one process, for a single replica or for tests that run
+/// several caches side by side.
+#[derive(Clone)]
+pub struct LocalInvalidationBus {
+    sender: broadcast::Sender<Invalidation>,
+}
+
+impl Default for LocalInvalidationBus {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl LocalInvalidationBus {
+    pub fn new() -> Self {
+        Self {
+            sender: broadcast::channel(BUS_CAPACITY).0,
+        }
+    }
+}
+
+#[async_trait]
+impl InvalidationBus for LocalInvalidationBus {
+    async fn publish(&self, invalidation: &Invalidation) -> Result<(), PaymentError> {
+        // Nobody listening is not an error
+        let _ = self.sender.send(invalidation.clone());
+        Ok(())
+    }
+
+    fn subscribe(&self) -> broadcast::Receiver<Invalidation> {
+        self.sender.subscribe()
+    }
+}
+
+/// Bus over Redis pub/sub. The subscription starts with the first
+/// `subscribe` and is reopened if the connection drops; since messages sent
+/// meanwhile are lost, subscribers are then told to drop everything.
+pub struct RedisInvalidationBus {
+    client: redis::Client,
+    channel: String,
+    connect_timeout: Duration,
+    command_timeout: Duration,
+    publisher: OnceCell<ConnectionManager>,
+    sender: broadcast::Sender<Invalidation>,
+    listener: Mutex<Option<tokio::task::JoinHandle<()>>>,
+}
+
+impl RedisInvalidationBus {
+    /// Publishes on `channel` of the server in `settings`; connections are
+    /// only opened on first use.
+    pub fn new(settings: &RedisSettings, channel: impl Into<String>) -> Result<Self, PaymentError> {
+        let client = redis::Client::open(settings.url.as_str()).map_err(|e| PaymentError::Cache(e.to_string()))?;
+        Ok(Self {
+            client,
+            channel: channel.into(),
+            connect_timeout: settings.connect_timeout,
+            command_timeout: settings.command_timeout,
+            publisher: OnceCell::new(),
+            sender: broadcast::channel(BUS_CAPACITY).0,
+            listener: Mutex::new(None),
+        })
+    }
+
+    fn start_listener(&self) {
+        let Ok(mut listener) = self.listener.lock() else {
+            return;
+        };
+        if listener.as_ref().is_some_and(|handle| !handle.is_finished()) {
+            return;
+        }
+        let client = self.client.clone();
+        let channel = self.channel.clone();
+        let sender = self.sender.clone();
+        *listener = Some(tokio::spawn(async move {
+            loop {
+                if let Err(e) = listen(&client, &channel, &sender).await {
+                    log::warn!("Cache invalidation subscription to {} lost: {}", channel, e);
+                }
+                let _ = sender.send(Invalidation::all(""));
+                tokio::time::sleep(RESUBSCRIBE_DELAY).await;
+            }
+        }));
+    }
+}
+
+/// Forwards messages from `channel` until the connection fails.
+async fn listen(
+    client: &redis::Client,
+    channel: &str,
+    sender: &broadcast::Sender<Invalidation>,
+) -> redis::RedisResult<()> {
+    let mut pubsub = client.get_async_connection().await?.into_pubsub();
+    pubsub.subscribe(channel).await?;
+    let mut messages = pubsub.on_message();
+    while let Some(message) = messages.next().await {
+        let payload: String = message.get_payload()?;
+        match serde_json::from_str(&payload) {
+            Ok(invalidation) => {
+                let _ = sender.send(invalidation);
+            }
+            Err(e) => log::warn!("Ignoring malformed cache invalidation {:?}: {}", payload, e),
+        }
+    }
+    Ok(())
+}
+
+#[async_trait]
+impl InvalidationBus for RedisInvalidationBus {
+    async fn publish(&self, invalidation: &Invalidation) -> Result<(), PaymentError> {
+        let payload = serde_json::to_string(invalidation)?;
+        let connection = self
+            .publisher
+            .get_or_try_init(|| async {
+                tokio::time::timeout(self.connect_timeout, self.client.get_tokio_connection_manager())
+                    .await
+                    .map_err(|_| PaymentError::Cache("connect timed out".to_string()))?
+                    .map_err(|e| PaymentError::Cache(e.to_string()))
+            })
+            .await?;
+        let mut connection = connection.clone();
+        tokio::time::timeout(
+            self.command_timeout,
+            redis::cmd("PUBLISH").arg(&self.channel).arg(payload).query_async::<_, i64>(&mut connection),
+        )
+        .await
+        .map_err(|_| PaymentError::Cache("command timed out".to_string()))?
+        .map_err(|e| PaymentError::Cache(e.to_string()))?;
+        Ok(())
+    }
+
+    fn subscribe(&self) -> broadcast::Receiver<Invalidation> {
+        let receiver = self.sender.subscribe();
+        self.start_listener();
+        receiver
+    }
+}
+
+impl Drop for RedisInvalidationBus {
+    fn drop(&mut self) {
+        if let Some(handle) = self.listener.lock().ok().and_then(|mut listener| listener.take()) {
+            handle.abort();
+        }
+    }
+}
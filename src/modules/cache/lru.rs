@@ -0,0 +1,208 @@
+use std::collections::HashMap;
+use rand::seq::IteratorRandom;
+use rand::Rng;
+
+const NIL: usize = usize::MAX;
+
+struct Node<V> {
+    key: String,
+    value: V,
+    prev: usize,
+    next: usize,
+}
+
+/// Hash map that also keeps its keys in recency order. Entries live in a
+/// slab linked into a doubly linked list, so lookups, moving an entry to
+/// the front and popping the least recently used one are all O(1).
+pub struct LruMap<V> {
+    index: HashMap<String, usize>,
+    slots: Vec<Option<Node<V>>>,
+    free: Vec<usize>,
+    /// Most recently used.
+    head: usize,
+    /// Least recently used.
+    tail: usize,
+}
+
+impl<V> Default for LruMap<V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<V> LruMap<V> {
+    pub fn new() -> Self {
+        Self {
+            index: HashMap::new(),
+            slots: Vec::new(),
+            free: Vec::new(),
+            head: NIL,
+            tail: NIL,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.index.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.index.is_empty()
+    }
+
+    pub fn contains_key(&self, key: &str) -> bool {
+        self.index.contains_key(key)
+    }
+
+    /// Looks up `key` without changing its recency.
+    pub fn peek(&self, key: &str) -> Option<&V> {
+        self.index.get(key).map(|&slot| &self.node(slot).value)
+    }
+
+    /// Mutable lookup that does not change recency.
+    pub fn peek_mut(&mut self, key: &str) -> Option<&mut V> {
+        let slot = *self.index.get(key)?;
+        Some(&mut self.node_mut(slot).value)
+    }
+
+    /// Looks up `key` and marks it as the most recently used.
+    pub fn get(&mut self, key: &str) -> Option<&V> {
+        let slot = *self.index.get(key)?;
+        self.detach(slot);
+        self.attach_front(slot);
+        Some(&self.node(slot).value)
+    }
+
+    /// Inserts or replaces `key` as the most recently used entry, returning
+    /// the replaced value.
+    pub fn insert(&mut self, key: String, value: V) -> Option<V> {
+        if let Some(&slot) = self.index.get(&key) {
+            self.detach(slot);
+            self.attach_front(slot);
+            return Some(std::mem::replace(&mut self.node_mut(slot).value, value));
+        }
+
+        let node = Node { key: key.clone(), value, prev: NIL, next: NIL };
+        let slot = match self.free.pop() {
+            Some(slot) => {
+                self.slots[slot] = Some(node);
+                slot
+            }
+            None => {
+                self.slots.push(Some(node));
+                self.slots.len() - 1
+            }
+        };
+        self.index.insert(key, slot);
+        self.attach_front(slot);
+        None
+    }
+
+    pub fn remove(&mut self, key: &str) -> Option<V> {
+        let slot = self.index.remove(key)?;
+        Some(self.release(slot).1)
+    }
+
+    /// Least recently used entry, without removing it.
+    pub fn peek_lru(&self) -> Option<(&str, &V)> {
+        (self.tail != NIL).then(|| {
+            let node = self.node(self.tail);
+            (node.key.as_str(), &node.value)
+        })
+    }
+
+    /// Removes and returns the least recently used entry.
+    pub fn pop_lru(&mut self) -> Option<(String, V)> {
+        if self.tail == NIL {
+            return None;
+        }
+        let (key, value) = self.release(self.tail);
+        self.index.remove(&key);
+        Some((key, value))
+    }
+
+    /// Picks a uniformly random key among the entries matching `filter`.
+    /// Probes random slab slots first, which is O(1) while the slab is dense
+    /// and matches are common, then falls back to a full scan.
+    pub fn random_key<R: Rng>(&self, rng: &mut R, filter: impl Fn(&V) -> bool) -> Option<&str> {
+        const PROBES: usize = 16;
+        if self.is_empty() {
+            return None;
+        }
+        for _ in 0..PROBES {
+            if let Some(node) = &self.slots[rng.gen_range(0..self.slots.len())] {
+                if filter(&node.value) {
+                    return Some(&node.key);
+                }
+            }
+        }
+        self.iter()
+            .filter(|(_, value)| filter(value))
+            .map(|(key, _)| key)
+            .choose(rng)
+    }
+
+    /// Entries from least to most recently used.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &V)> {
+        let mut slot = self.tail;
+        std::iter::from_fn(move || {
+            if slot == NIL {
+                return None;
+            }
+            let node = self.node(slot);
+            slot = node.prev;
+            Some((node.key.as_str(), &node.value))
+        })
+    }
+
+    pub fn clear(&mut self) {
+        self.index.clear();
+        self.slots.clear();
+        self.free.clear();
+        self.head = NIL;
+        self.tail = NIL;
+    }
+
+    fn node(&self, slot: usize) -> &Node<V> {
+        self.slots[slot].as_ref().expect("LRU slot is linked but empty")
+    }
+
+    fn node_mut(&mut self, slot: usize) -> &mut Node<V> {
+        self.slots[slot].as_mut().expect("LRU slot is linked but empty")
+    }
+
+    fn release(&mut self, slot: usize) -> (String, V) {
+        self.detach(slot);
+        self.free.push(slot);
+        let node = self.slots[slot].take().expect("LRU slot is linked but empty");
+        (node.key, node.value)
+    }
+
+    fn detach(&mut self, slot: usize) {
+        let (prev, next) = {
+            let node = self.node(slot);
+            (node.prev, node.next)
+        };
+        match prev {
+            NIL => self.head = next,
+            prev => self.node_mut(prev).next = next,
+        }
+        match next {
+            NIL => self.tail = prev,
+            next => self.node_mut(next).prev = prev,
+        }
+    }
+
+    fn attach_front(&mut self, slot: usize) {
+        let head = self.head;
+        {
+            let node = self.node_mut(slot);
+            node.prev = NIL;
+            node.next = head;
+        }
+        match head {
+            NIL => self.tail = slot,
+            head => self.node_mut(head).prev = slot,
+        }
+        self.head = slot;
+    }
+}
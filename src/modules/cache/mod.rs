@@ -1,48 +1,271 @@
+pub mod backend;
+pub mod compute;
+pub mod invalidation;
+pub mod lru;
 pub mod redis;
-use redis::RedisCache;
+pub mod redis_server;
+mod shard;
+pub mod snapshot;
+pub mod stats;
+pub mod tiered;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+use axum::{
+    extract::{Query, State},
+    http::{header, StatusCode},
+    response::IntoResponse,
+};
+use serde::Deserialize;
+use backend::{CacheBackend, EntryTtl, MemoryStats};
+use compute::InFlight;
+use invalidation::{InvalidationBus, RedisInvalidationBus};
+use redis::{CachePolicy, RedisCache};
+use redis_server::RedisServerCache;
+use snapshot::CacheSnapshot;
+use stats::{CacheStats, InfoSection};
+use tiered::{TieredCache, TierSettings};
+use crate::modules::clock::{monotonic_clock, SharedClock};
+use crate::modules::config::{CacheBackendKind, Config};
+use crate::modules::error::PaymentError;
+use crate::modules::SharedServices;
 
 pub struct CacheManager {
-    redis_cache: RedisCache,
+    backend: Box<dyn CacheBackend>,
+    /// Decides freshness for `get_or_compute`.
+    clock: SharedClock,
+    in_flight: InFlight,
+}
+
+impl Default for CacheManager {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl CacheManager {
     pub fn new() -> Self {
-        Self {
-            redis_cache: RedisCache::new(),
-        }
+        Self::with_backend(Box::new(RedisCache::new()))
     }
 
     pub fn with_memory_limit(memory_limit_mb: u64) -> Self {
+        Self::with_backend(Box::new(RedisCache::with_memory_limit(memory_limit_mb)))
+    }
+
+    /// In-memory cache evicting with `policy` once `memory_limit_mb` is reached.
+    pub fn with_policy(memory_limit_mb: u64, policy: CachePolicy) -> Self {
+        Self::with_backend(Box::new(RedisCache::with_policy(memory_limit_mb, policy)))
+    }
+
+    pub fn with_backend(backend: Box<dyn CacheBackend>) -> Self {
         Self {
-            redis_cache: RedisCache::with_memory_limit(memory_limit_mb),
+            backend,
+            clock: monotonic_clock(),
+            in_flight: InFlight::default(),
+        }
+    }
+
+    /// In-process L1 over the shared `l2`, kept coherent with the other
+    /// replicas through `bus`; see `TieredCache`.
+    pub fn with_tiers(l2: Arc<dyn CacheBackend>, bus: Arc<dyn InvalidationBus>, settings: TierSettings) -> Self {
+        Self::with_backend(Box::new(TieredCache::new(l2, bus, settings)))
+    }
+
+    /// Picks the backend named by `config.cache_backend`, with an L1 in front
+    /// of Redis unless `config.cache_tiers` turns it off. An unusable Redis
+    /// URL falls back to the in-memory cache so the server still starts.
+    pub fn from_config(config: &Config) -> Self {
+        match config.cache_backend {
+            CacheBackendKind::Memory => Self::with_policy(config.redis.memory_limit_mb, config.cache_policy),
+            CacheBackendKind::Redis => match RedisServerCache::new(config.redis.clone()) {
+                Ok(cache) if config.cache_tiers.l1_ttl.is_zero() => Self::with_backend(Box::new(cache)),
+                Ok(cache) => match RedisInvalidationBus::new(&config.redis, config.cache_tiers.channel.clone()) {
+                    Ok(bus) => Self::with_tiers(Arc::new(cache), Arc::new(bus), config.cache_tiers.clone()),
+                    Err(e) => {
+                        log::error!("Cannot broadcast cache invalidations, running without L1: {}", e);
+                        Self::with_backend(Box::new(cache))
+                    }
+                },
+                Err(e) => {
+                    log::error!("Invalid Redis settings, using the in-memory cache: {}", e);
+                    Self::with_policy(config.redis.memory_limit_mb, config.cache_policy)
+                }
+            },
+        }
+    }
+
+    /// Name of the active backend, e.g. `memory` or `redis`.
+    pub fn backend_name(&self) -> &'static str {
+        self.backend.name()
+    }
+
+    pub fn set_clock(&mut self, clock: SharedClock) {
+        self.backend.set_clock(clock.clone());
+        self.clock = clock;
+    }
+
+    pub async fn set<T: serde::Serialize>(&self, key: &str, value: &T, ttl: Duration) -> Result<(), PaymentError> {
+        self.backend.set_raw(key, serde_json::to_string(value)?, Some(ttl)).await
+    }
+
+    /// Stores a value that never expires.
+    pub async fn set_persistent<T: serde::Serialize>(&self, key: &str, value: &T) -> Result<(), PaymentError> {
+        self.backend.set_raw(key, serde_json::to_string(value)?, None).await
+    }
+
+    pub async fn get<T: for<'de> serde::Deserialize<'de>>(&self, key: &str) -> Result<Option<T>, PaymentError> {
+        match self.backend.get_raw(key).await? {
+            Some(value) => Ok(Some(serde_json::from_str(&value)?)),
+            None => Ok(None),
         }
     }
 
-    pub async fn set<T: serde::Serialize>(&self, key: &str, value: &T, ttl: std::time::Duration) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-        self.redis_cache.set(key, value, ttl).await
+    /// Takes or renews a lease on `key` for `holder`; see `CacheBackend::acquire_lease`.
+    pub async fn acquire_lease(&self, key: &str, holder: &str, ttl: Duration) -> Result<bool, PaymentError> {
+        self.backend.acquire_lease(key, holder, ttl).await
+    }
+
+    /// Atomically adds `delta` to the counter at `key` and returns the new
+    /// total; see `CacheBackend::incr_by`. Read it back with `get::<i64>`.
+    pub async fn incr_by(&self, key: &str, delta: i64) -> Result<i64, PaymentError> {
+        self.backend.incr_by(key, delta).await
+    }
+
+    pub async fn incr_by_float(&self, key: &str, delta: f64) -> Result<f64, PaymentError> {
+        self.backend.incr_by_float(key, delta).await
+    }
+
+    /// Stores `value` for `ttl` unless `key` already exists. Returns whether
+    /// it was stored.
+    pub async fn set_nx<T: serde::Serialize>(&self, key: &str, value: &T, ttl: Duration) -> Result<bool, PaymentError> {
+        self.backend.set_nx(key, serde_json::to_string(value)?, Some(ttl)).await
     }
 
-    pub async fn get<T: for<'de> serde::Deserialize<'de>>(&self, key: &str) -> Result<Option<T>, Box<dyn std::error::Error + Send + Sync>> {
-        self.redis_cache.get(key).await
+    /// Replaces the value at `key` with `new`, stored for `ttl`, only if it
+    /// still equals `expected`. Returns whether it was replaced.
+    pub async fn compare_and_swap<T: serde::Serialize>(
+        &self,
+        key: &str,
+        expected: &T,
+        new: &T,
+        ttl: Duration,
+    ) -> Result<bool, PaymentError> {
+        let expected = serde_json::to_string(expected)?;
+        self.backend
+            .compare_and_swap(key, &expected, serde_json::to_string(new)?, Some(ttl))
+            .await
     }
 
-    pub async fn remove(&self, key: &str) -> Result<bool, Box<dyn std::error::Error + Send + Sync>> {
-        self.redis_cache.remove(key).await
+    /// Atomically adds `delta` to `field` of the hash at `key`.
+    pub async fn hincr_by(&self, key: &str, field: &str, delta: i64) -> Result<i64, PaymentError> {
+        self.backend.hincr_by(key, field, delta).await
     }
 
-    pub async fn clear(&self) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-        self.redis_cache.clear().await
+    pub async fn hincr_by_float(&self, key: &str, field: &str, delta: f64) -> Result<f64, PaymentError> {
+        self.backend.hincr_by_float(key, field, delta).await
+    }
+
+    /// Every field of the hash at `key`, as stored strings.
+    pub async fn hget_all(&self, key: &str) -> Result<HashMap<String, String>, PaymentError> {
+        self.backend.hget_all(key).await
+    }
+
+    /// Time left on `key`; see `CacheBackend::ttl`.
+    pub async fn ttl(&self, key: &str) -> Result<EntryTtl, PaymentError> {
+        self.backend.ttl(key).await
+    }
+
+    pub async fn expire(&self, key: &str, ttl: Duration) -> Result<bool, PaymentError> {
+        self.backend.expire(key, ttl).await
+    }
+
+    pub async fn persist(&self, key: &str) -> Result<bool, PaymentError> {
+        self.backend.persist(key).await
+    }
+
+    /// Starts the backend's background expiry, if it needs one.
+    pub fn start_expiry_sweeper(&self, interval: Duration) -> bool {
+        self.backend.start_sweeper(interval)
+    }
+
+    /// Writes the backend's live entries to `path`. Returns how many were
+    /// written, or `None` for backends that keep no snapshots.
+    pub async fn save_snapshot(&self, path: &Path) -> Result<Option<usize>, PaymentError> {
+        let Some(snapshot) = self.backend.snapshot().await else {
+            return Ok(None);
+        };
+        snapshot.write_to(path).await?;
+        Ok(Some(snapshot.entries.len()))
+    }
+
+    /// Loads the snapshot at `path`, if one was written, and returns how
+    /// many of its entries were still live.
+    pub async fn load_snapshot(&self, path: &Path) -> Result<usize, PaymentError> {
+        match CacheSnapshot::read_from(path).await? {
+            Some(snapshot) => Ok(self.backend.restore(&snapshot).await),
+            None => Ok(0),
+        }
+    }
+
+    /// Starts saving a snapshot to `path` every `interval`, if the backend
+    /// keeps them.
+    pub fn start_snapshots(&self, path: PathBuf, interval: Duration) -> bool {
+        self.backend.start_snapshots(path, interval)
+    }
+
+    /// Starts applying other replicas' invalidations to the local tier, if
+    /// the backend has one.
+    pub fn start_invalidation_listener(&self) -> bool {
+        self.backend.start_invalidation_listener()
+    }
+
+    pub async fn remove(&self, key: &str) -> Result<bool, PaymentError> {
+        self.backend.remove(key).await
+    }
+
+    pub async fn clear(&self) -> Result<(), PaymentError> {
+        self.backend.clear().await
     }
 
     pub async fn get_memory_usage_mb(&self) -> u64 {
-        self.redis_cache.get_memory_usage_mb().await
+        self.backend.get_memory_usage_mb().await
+    }
+
+    /// Byte-level view of the backend's memory use.
+    pub async fn memory_stats(&self) -> MemoryStats {
+        self.backend.memory_stats().await
+    }
+
+    /// Snapshot of the backend's hit, miss, expiry and eviction counters.
+    pub async fn stats(&self) -> CacheStats {
+        self.backend.stats().await
     }
 
     pub async fn get_entry_count(&self) -> usize {
-        self.redis_cache.get_entry_count().await
+        self.backend.get_entry_count().await
     }
 
     pub fn get_memory_limit_mb(&self) -> u64 {
-        self.redis_cache.get_memory_limit_mb()
+        self.backend.get_memory_limit_mb()
     }
-} 
\ No newline at end of file
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CacheInfoQuery {
+    pub section: Option<String>,
+}
+
+/// Cache statistics as plain text, laid out like Redis `INFO memory` and
+/// `INFO stats`; `?section=` picks one of them.
+pub async fn get_cache_info(
+    State(services): State<SharedServices>,
+    Query(query): Query<CacheInfoQuery>,
+) -> impl IntoResponse {
+    let section: InfoSection = match query.section.as_deref().unwrap_or_default().parse() {
+        Ok(section) => section,
+        Err(_) => return StatusCode::BAD_REQUEST.into_response(),
+    };
+    let info = services.cache_manager.stats().await.to_info(section);
+    (StatusCode::OK, [(header::CONTENT_TYPE, "text/plain; charset=utf-8")], info).into_response()
+}
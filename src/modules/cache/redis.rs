@@ -1,25 +1,63 @@
-use std::collections::HashMap;
-use std::sync::Arc;
+use std::collections::{BTreeMap, HashMap};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
 use std::time::Duration;
-use tokio::sync::RwLock;
+use rand::Rng;
+use tokio::task::JoinHandle;
 use serde::{Serialize, Deserialize};
+use crate::modules::clock::{monotonic_clock, SharedClock};
+use crate::modules::error::{CacheRejection, PaymentError};
+use super::backend::{EntryTtl, MemoryStats};
+use super::shard::{entry_bytes, Shards};
+use super::snapshot::{CacheSnapshot, SnapshotEntry};
+use super::stats::CacheStats;
+
+/// Redis type of an entry; string commands on a hash, and hash commands
+/// on a string, fail with WRONGTYPE.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EntryKind {
+    #[default]
+    String,
+    /// A JSON object of string fields.
+    Hash,
+}
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CacheEntry<T> {
     pub value: T,
+    #[serde(default)]
+    pub kind: EntryKind,
+    /// Clock milliseconds when the entry was written.
     pub timestamp: u64,
-    pub ttl: u64,
+    /// Clock milliseconds after which the entry is gone; `None` never expires.
+    pub expires_at: Option<u64>,
 }
 
-#[allow(dead_code)]
+/// In-process cache that mimics Redis semantics (TTLs, memory limit,
+/// eviction policies). Used when no Redis server is configured. Keys are
+/// spread over shards with their own locks; the memory limit applies to
+/// all of them together.
 pub struct RedisCache {
     memory_limit_mb: u64,
     policy: CachePolicy,
-    cache: Arc<RwLock<HashMap<String, CacheEntry<String>>>>,
-    memory_usage: Arc<RwLock<u64>>,
+    shards: Arc<Shards>,
+    clock: SharedClock,
+    sweeper: Mutex<Option<JoinHandle<()>>>,
+    snapshotter: Mutex<Option<JoinHandle<()>>>,
 }
 
-#[allow(dead_code)]
+/// Bytes charged per entry on top of its key and value, covering the LRU
+/// node, the index slot and the expiry bookkeeping.
+pub const ENTRY_OVERHEAD_BYTES: u64 = 96;
+
+/// Shards used unless `RedisCache::with_shards` says otherwise.
+pub const DEFAULT_SHARD_COUNT: usize = 16;
+
+/// Which entry to evict when the memory limit is reached, named after the
+/// Redis `maxmemory-policy` values. The volatile policies only consider
+/// entries with a TTL; a write they cannot make room for is rejected.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum CachePolicy {
     AllKeysLRU,
     VolatileLRU,
@@ -28,189 +66,738 @@ pub enum CachePolicy {
     VolatileTTL,
 }
 
+impl CachePolicy {
+    /// Whether entries without a TTL may be evicted too.
+    pub fn is_allkeys(&self) -> bool {
+        matches!(self, CachePolicy::AllKeysLRU | CachePolicy::AllKeysRandom)
+    }
+
+    /// The Redis `maxmemory-policy` name, e.g. `allkeys-lru`.
+    pub fn name(&self) -> &'static str {
+        match self {
+            CachePolicy::AllKeysLRU => "allkeys-lru",
+            CachePolicy::VolatileLRU => "volatile-lru",
+            CachePolicy::AllKeysRandom => "allkeys-random",
+            CachePolicy::VolatileRandom => "volatile-random",
+            CachePolicy::VolatileTTL => "volatile-ttl",
+        }
+    }
+}
+
+impl std::str::FromStr for CachePolicy {
+    type Err = String;
+
+    /// Parses the Redis names, e.g. `allkeys-lru` or `volatile-ttl`.
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "allkeys-lru" => Ok(CachePolicy::AllKeysLRU),
+            "volatile-lru" => Ok(CachePolicy::VolatileLRU),
+            "allkeys-random" => Ok(CachePolicy::AllKeysRandom),
+            "volatile-random" => Ok(CachePolicy::VolatileRandom),
+            "volatile-ttl" => Ok(CachePolicy::VolatileTTL),
+            other => Err(format!("unknown cache policy: {}", other)),
+        }
+    }
+}
+
+impl Default for RedisCache {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl RedisCache {
     pub fn new() -> Self {
-        Self {
-            memory_limit_mb: 50,
-            policy: CachePolicy::AllKeysLRU,
-            cache: Arc::new(RwLock::new(HashMap::new())),
-            memory_usage: Arc::new(RwLock::new(0)),
-        }
+        Self::with_policy(50, CachePolicy::AllKeysLRU)
     }
 
     pub fn with_memory_limit(memory_limit_mb: u64) -> Self {
+        Self::with_policy(memory_limit_mb, CachePolicy::AllKeysLRU)
+    }
+
+    pub fn with_policy(memory_limit_mb: u64, policy: CachePolicy) -> Self {
+        Self::with_shards(memory_limit_mb, policy, DEFAULT_SHARD_COUNT)
+    }
+
+    /// Cache split into `shard_count` independently locked shards; 1 gives
+    /// a single global lock and exact LRU order.
+    pub fn with_shards(memory_limit_mb: u64, policy: CachePolicy, shard_count: usize) -> Self {
         Self {
             memory_limit_mb,
-            policy: CachePolicy::AllKeysLRU,
-            cache: Arc::new(RwLock::new(HashMap::new())),
-            memory_usage: Arc::new(RwLock::new(0)),
+            policy,
+            shards: Arc::new(Shards::new(shard_count)),
+            clock: monotonic_clock(),
+            sweeper: Mutex::new(None),
+            snapshotter: Mutex::new(None),
         }
     }
 
-    pub async fn set<T: Serialize>(&self, key: &str, value: &T, ttl: Duration) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-        let serialized_value = serde_json::to_string(value)?;
-        let entry_size = serialized_value.len() as u64;
-        
-        // Check memory limit
-        if !self.can_store_entry(entry_size).await {
-            self.evict_entries().await;
-        }
-        
+    pub fn get_policy(&self) -> CachePolicy {
+        self.policy
+    }
+
+    pub fn shard_count(&self) -> usize {
+        self.shards.len()
+    }
+
+    pub fn set_clock(&mut self, clock: SharedClock) {
+        self.clock = clock;
+    }
+
+    pub async fn set<T: Serialize>(&self, key: &str, value: &T, ttl: Duration) -> Result<(), PaymentError> {
+        self.set_raw(key, serde_json::to_string(value)?, Some(ttl)).await
+    }
+
+    /// Stores a value that never expires, like Redis `SET` without `EX`.
+    pub async fn set_persistent<T: Serialize>(&self, key: &str, value: &T) -> Result<(), PaymentError> {
+        self.set_raw(key, serde_json::to_string(value)?, None).await
+    }
+
+    /// Stores an already serialized value, evicting as many entries as it
+    /// takes to fit it under the memory limit. `None` never expires. Fails
+    /// with `PaymentError::CacheRejected`, leaving the cache untouched, if
+    /// the policy cannot free enough memory.
+    pub async fn set_raw(&self, key: &str, serialized_value: String, ttl: Option<Duration>) -> Result<(), PaymentError> {
+        self.insert_raw(key, serialized_value, EntryKind::String, ttl)
+    }
+
+    fn insert_raw(&self, key: &str, value: String, kind: EntryKind, ttl: Option<Duration>) -> Result<(), PaymentError> {
+        let now = self.clock.now_millis();
         let entry = CacheEntry {
-            value: serialized_value,
-            timestamp: std::time::SystemTime::now()
-                .duration_since(std::time::UNIX_EPOCH)
-                .unwrap()
-                .as_secs(),
-            ttl: ttl.as_secs(),
+            value,
+            kind,
+            timestamp: now,
+            expires_at: ttl.map(|ttl| expiry(now, ttl)),
         };
-        
-        let mut cache = self.cache.write().await;
-        let old_size = cache.get(key).map(|e| e.value.len() as u64).unwrap_or(0);
-        cache.insert(key.to_string(), entry);
-        
-        // Update memory usage
-        let mut memory_usage = self.memory_usage.write().await;
-        *memory_usage = *memory_usage - old_size + entry_size;
-        
+
+        let entry_size = entry_bytes(key, &entry.value);
+        self.reserve(key, entry_size)?;
+        {
+            let mut store = self.shards.lock_key(key);
+            store.insert(key, entry, self.shards.tick());
+            store.counters.bytes_written += entry_size;
+        }
+        self.shards.release(entry_size);
+
         Ok(())
     }
 
-    pub async fn get<T: for<'de> Deserialize<'de>>(&self, key: &str) -> Result<Option<T>, Box<dyn std::error::Error + Send + Sync>> {
-        let cache = self.cache.read().await;
-        
-        if let Some(entry) = cache.get(key) {
-            // Check if entry has expired
-            let current_time = std::time::SystemTime::now()
-                .duration_since(std::time::UNIX_EPOCH)
-                .unwrap()
-                .as_secs();
-            
-            if current_time - entry.timestamp > entry.ttl {
-                // Entry has expired, remove it
-                drop(cache);
-                let _ = self.remove(key).await;
-                return Ok(None);
+    /// Takes or renews a lease on `key`. Succeeds when the key is free,
+    /// expired, or already held by `holder`; the check and the write happen
+    /// under one shard lock, like Redis `SET key holder NX PX ttl`.
+    pub async fn acquire_lease(&self, key: &str, holder: &str, ttl: Duration) -> Result<bool, PaymentError> {
+        let serialized_holder = serde_json::to_string(holder)?;
+        self.update(key, |current, now| {
+            if string_value(current)?.is_some_and(|entry| entry.value != serialized_holder) {
+                return Ok(Change::Keep(false));
             }
-            
-            let value: T = serde_json::from_str(&entry.value)?;
-            Ok(Some(value))
-        } else {
-            Ok(None)
+            Ok(Change::Write {
+                value: serialized_holder.clone(),
+                kind: EntryKind::String,
+                expires_at: Some(expiry(now, ttl)),
+                result: true,
+            })
+        })
+    }
+
+    /// Adds `delta` to the integer stored at `key`, like Redis `INCRBY`. A
+    /// missing key counts as 0 and the TTL of an existing one is kept.
+    pub async fn incr_by(&self, key: &str, delta: i64) -> Result<i64, PaymentError> {
+        self.update(key, |current, _| {
+            let total = parse_int(string_value(current)?.map(|entry| entry.value.as_str()), NOT_AN_INTEGER)?
+                .checked_add(delta)
+                .ok_or_else(|| value_error(OVERFLOW))?;
+            Ok(Change::Write {
+                value: total.to_string(),
+                kind: EntryKind::String,
+                expires_at: current.and_then(|entry| entry.expires_at),
+                result: total,
+            })
+        })
+    }
+
+    /// Float version of `incr_by`, like Redis `INCRBYFLOAT`.
+    pub async fn incr_by_float(&self, key: &str, delta: f64) -> Result<f64, PaymentError> {
+        self.update(key, |current, _| {
+            let (value, total) = add_float(string_value(current)?.map(|entry| entry.value.as_str()), delta, NOT_A_FLOAT)?;
+            Ok(Change::Write {
+                value,
+                kind: EntryKind::String,
+                expires_at: current.and_then(|entry| entry.expires_at),
+                result: total,
+            })
+        })
+    }
+
+    /// Stores `value` only if `key` holds no live entry, like Redis
+    /// `SET NX`. Returns whether it was stored.
+    pub async fn set_nx(&self, key: &str, value: String, ttl: Option<Duration>) -> Result<bool, PaymentError> {
+        self.update(key, |current, now| {
+            if current.is_some() {
+                return Ok(Change::Keep(false));
+            }
+            Ok(Change::Write {
+                value: value.clone(),
+                kind: EntryKind::String,
+                expires_at: ttl.map(|ttl| expiry(now, ttl)),
+                result: true,
+            })
+        })
+    }
+
+    /// Replaces the value at `key` with `value` only if it currently holds
+    /// exactly `expected`. The new entry gets `ttl`, or none. Returns
+    /// whether it was replaced.
+    pub async fn compare_and_swap(
+        &self,
+        key: &str,
+        expected: &str,
+        value: String,
+        ttl: Option<Duration>,
+    ) -> Result<bool, PaymentError> {
+        self.update(key, |current, now| {
+            if string_value(current)?.map_or(true, |entry| entry.value != expected) {
+                return Ok(Change::Keep(false));
+            }
+            Ok(Change::Write {
+                value: value.clone(),
+                kind: EntryKind::String,
+                expires_at: ttl.map(|ttl| expiry(now, ttl)),
+                result: true,
+            })
+        })
+    }
+
+    /// Adds `delta` to `field` of the hash at `key`, like Redis `HINCRBY`.
+    pub async fn hincr_by(&self, key: &str, field: &str, delta: i64) -> Result<i64, PaymentError> {
+        self.update_field(key, field, |current| {
+            let total = parse_int(current, HASH_NOT_AN_INTEGER)?
+                .checked_add(delta)
+                .ok_or_else(|| value_error(OVERFLOW))?;
+            Ok((total.to_string(), total))
+        })
+    }
+
+    /// Float version of `hincr_by`, like Redis `HINCRBYFLOAT`.
+    pub async fn hincr_by_float(&self, key: &str, field: &str, delta: f64) -> Result<f64, PaymentError> {
+        self.update_field(key, field, |current| {
+            add_float(current, delta, HASH_NOT_A_FLOAT)
+        })
+    }
+
+    /// Every field of the hash at `key`; empty when there is no such key.
+    pub async fn hget_all(&self, key: &str) -> Result<HashMap<String, String>, PaymentError> {
+        let now = self.clock.now_millis();
+        let mut store = self.shards.lock_key(key);
+        match store.live(key, now) {
+            Some(entry) => parse_hash(entry),
+            None => Ok(HashMap::new()),
         }
     }
 
-    pub async fn remove(&self, key: &str) -> Result<bool, Box<dyn std::error::Error + Send + Sync>> {
-        let mut cache = self.cache.write().await;
-        let mut memory_usage = self.memory_usage.write().await;
-        
-        if let Some(entry) = cache.remove(key) {
-            *memory_usage -= entry.value.len() as u64;
-            Ok(true)
-        } else {
-            Ok(false)
+    pub async fn get<T: for<'de> Deserialize<'de>>(&self, key: &str) -> Result<Option<T>, PaymentError> {
+        match self.get_raw(key).await? {
+            Some(value) => Ok(Some(serde_json::from_str(&value)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Reads a value without deserializing it. A hit marks the entry as
+    /// recently used, so this locks the key's shard. Fails with WRONGTYPE
+    /// on a hash, as `GET` does.
+    pub async fn get_raw(&self, key: &str) -> Result<Option<String>, PaymentError> {
+        let now = self.clock.now_millis();
+        let tick = self.shards.tick();
+        let mut store = self.shards.lock_key(key);
+
+        match store.live(key, now) {
+            None => {
+                store.counters.misses += 1;
+                return Ok(None);
+            }
+            Some(entry) if entry.kind != EntryKind::String => return Err(value_error(WRONG_TYPE)),
+            Some(_) => {}
         }
+        store.counters.hits += 1;
+        Ok(store.get(key, tick).map(|entry| entry.value.clone()))
+    }
+
+    /// Time left on `key`, like Redis `PTTL`.
+    pub async fn ttl(&self, key: &str) -> Result<EntryTtl, PaymentError> {
+        let now = self.clock.now_millis();
+        let mut store = self.shards.lock_key(key);
+
+        Ok(match store.live(key, now) {
+            None => EntryTtl::Missing,
+            Some(entry) => match entry.expires_at {
+                None => EntryTtl::Persistent,
+                Some(expires_at) => EntryTtl::Expires(Duration::from_millis(expires_at.saturating_sub(now))),
+            },
+        })
+    }
+
+    /// Sets a new TTL on an existing key. Returns false if there is no such key.
+    pub async fn expire(&self, key: &str, ttl: Duration) -> Result<bool, PaymentError> {
+        let now = self.clock.now_millis();
+        let mut store = self.shards.lock_key(key);
+
+        Ok(store.set_expiry(key, Some(expiry(now, ttl)), now).is_some())
+    }
+
+    /// Drops the TTL of `key` so it never expires. Returns false if the key
+    /// is missing or already persistent.
+    pub async fn persist(&self, key: &str) -> Result<bool, PaymentError> {
+        let now = self.clock.now_millis();
+        let mut store = self.shards.lock_key(key);
+
+        Ok(store.set_expiry(key, None, now).flatten().is_some())
     }
 
-    pub async fn clear(&self) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-        let mut cache = self.cache.write().await;
-        let mut memory_usage = self.memory_usage.write().await;
-        
-        cache.clear();
-        *memory_usage = 0;
-        
+    pub async fn remove(&self, key: &str) -> Result<bool, PaymentError> {
+        let mut store = self.shards.lock_key(key);
+        Ok(store.remove(key).is_some())
+    }
+
+    pub async fn clear(&self) -> Result<(), PaymentError> {
+        for index in 0..self.shards.len() {
+            self.shards.lock(index).clear();
+        }
         Ok(())
     }
 
+    /// Runs one active-expiry pass and returns how many entries it removed.
+    pub async fn sweep_expired(&self) -> usize {
+        self.shards.sweep_expired(self.clock.now_millis())
+    }
+
+    /// Spawns a task on the current runtime that runs `sweep_expired` every
+    /// `interval`, so expired entries stop counting toward memory even if
+    /// nobody reads them. Returns false if it is already running.
+    pub fn start_sweeper(&self, interval: Duration) -> bool {
+        let Ok(mut sweeper) = self.sweeper.lock() else {
+            return false;
+        };
+        if sweeper.as_ref().is_some_and(|handle| !handle.is_finished()) {
+            return false;
+        }
+
+        let shards = Arc::clone(&self.shards);
+        let clock = Arc::clone(&self.clock);
+        *sweeper = Some(tokio::spawn(async move {
+            loop {
+                tokio::time::sleep(interval).await;
+                shards.sweep_expired(clock.now_millis());
+            }
+        }));
+        true
+    }
+
+    /// Stops the sweeper. Returns false if it was not running.
+    pub fn stop_sweeper(&self) -> bool {
+        let handle = self.sweeper.lock().ok().and_then(|mut sweeper| sweeper.take());
+        match handle {
+            Some(handle) => {
+                handle.abort();
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Live entries with the TTL each has left. Shards are read one at a
+    /// time, so writes made meanwhile may or may not be included.
+    pub async fn snapshot(&self) -> CacheSnapshot {
+        take_snapshot(&self.shards, self.clock.now_millis())
+    }
+
+    /// Loads the entries of `snapshot`, less the time since it was taken
+    /// off each TTL; entries whose TTL ran out meanwhile are dropped, as
+    /// are any the memory limit rejects. Returns how many were loaded.
+    pub async fn restore(&self, snapshot: &CacheSnapshot) -> usize {
+        let elapsed = self.clock.now_millis().saturating_sub(snapshot.taken_at);
+        let mut restored = 0;
+        for entry in &snapshot.entries {
+            let ttl = match entry.ttl_millis {
+                Some(ttl) if ttl <= elapsed => continue,
+                Some(ttl) => Some(Duration::from_millis(ttl - elapsed)),
+                None => None,
+            };
+            if self.insert_raw(&entry.key, entry.value.clone(), entry.kind, ttl).is_ok() {
+                restored += 1;
+            }
+        }
+        restored
+    }
+
+    /// Spawns a task on the current runtime that writes a snapshot to
+    /// `path` every `interval`. Returns false if it is already running.
+    pub fn start_snapshots(&self, path: PathBuf, interval: Duration) -> bool {
+        let Ok(mut snapshotter) = self.snapshotter.lock() else {
+            return false;
+        };
+        if snapshotter.as_ref().is_some_and(|handle| !handle.is_finished()) {
+            return false;
+        }
+
+        let shards = Arc::clone(&self.shards);
+        let clock = Arc::clone(&self.clock);
+        *snapshotter = Some(tokio::spawn(async move {
+            loop {
+                tokio::time::sleep(interval).await;
+                let snapshot = take_snapshot(&shards, clock.now_millis());
+                if let Err(e) = snapshot.write_to(&path).await {
+                    log::warn!("Could not write cache snapshot: {}", e);
+                }
+            }
+        }));
+        true
+    }
+
     pub async fn get_memory_usage_mb(&self) -> u64 {
-        let memory_usage = self.memory_usage.read().await;
-        *memory_usage / (1024 * 1024)
+        self.get_memory_usage_bytes().await / (1024 * 1024)
+    }
+
+    /// Bytes charged for all entries: keys, values and `ENTRY_OVERHEAD_BYTES` each.
+    pub async fn get_memory_usage_bytes(&self) -> u64 {
+        self.shards.used_bytes()
+    }
+
+    pub async fn memory_stats(&self) -> MemoryStats {
+        MemoryStats {
+            used_bytes: self.shards.used_bytes(),
+            limit_bytes: self.memory_limit_bytes(),
+            volatile_bytes: self.shards.volatile_bytes(),
+            entry_count: self.shards.entry_count(),
+        }
+    }
+
+    /// Counters since the cache was created, plus current memory.
+    pub async fn stats(&self) -> CacheStats {
+        let mut stats = CacheStats {
+            backend: "memory",
+            memory: self.memory_stats().await,
+            ..CacheStats::default()
+        };
+        let mut evicted = 0;
+        for index in 0..self.shards.len() {
+            let store = self.shards.lock(index);
+            let counters = &store.counters;
+            stats.hits += counters.hits;
+            stats.misses += counters.misses;
+            stats.expired_keys += counters.expired;
+            stats.rejected_writes += counters.rejected_writes;
+            stats.bytes_written += counters.bytes_written;
+            evicted += counters.evicted;
+        }
+        stats.evictions = BTreeMap::from([(self.policy.name().to_string(), evicted)]);
+        stats
     }
 
     pub async fn get_entry_count(&self) -> usize {
-        let cache = self.cache.read().await;
-        cache.len()
+        self.shards.entry_count()
     }
 
     pub fn get_memory_limit_mb(&self) -> u64 {
         self.memory_limit_mb
     }
 
-    async fn can_store_entry(&self, entry_size: u64) -> bool {
-        let memory_usage = self.memory_usage.read().await;
-        let current_usage_mb = *memory_usage / (1024 * 1024);
-        current_usage_mb + (entry_size / (1024 * 1024)) <= self.memory_limit_mb
+    fn memory_limit_bytes(&self) -> u64 {
+        self.memory_limit_mb * 1024 * 1024
     }
 
-    async fn evict_entries(&self) {
-        match self.policy {
-            CachePolicy::AllKeysLRU => self.evict_lru_entries().await,
-            CachePolicy::VolatileLRU => self.evict_volatile_lru_entries().await,
-            CachePolicy::AllKeysRandom => self.evict_random_entries().await,
-            CachePolicy::VolatileRandom => self.evict_volatile_random_entries().await,
-            CachePolicy::VolatileTTL => self.evict_volatile_ttl_entries().await,
-        }
+    /// Read-modify-write of the entry under `key`, done under its shard
+    /// lock so concurrent updates cannot interleave. `apply` sees the live
+    /// entry, if any, and the current time. When the new value needs room
+    /// that only eviction can free, the lock is dropped to evict and
+    /// `apply` runs again on whatever the entry holds by then.
+    fn update<R>(
+        &self,
+        key: &str,
+        mut apply: impl FnMut(Option<&CacheEntry<String>>, u64) -> Result<Change<R>, PaymentError>,
+    ) -> Result<R, PaymentError> {
+        // Bytes held against the limit on behalf of this write
+        let mut reserved = 0;
+        let outcome = loop {
+            let now = self.clock.now_millis();
+            let mut store = self.shards.lock_key(key);
+            let current = store.live(key, now).map(|entry| &*entry);
+            let old_size = current.map_or(0, |entry| entry_bytes(key, &entry.value));
+            let (value, kind, expires_at, result) = match apply(current, now) {
+                Ok(Change::Write { value, kind, expires_at, result }) => (value, kind, expires_at, result),
+                Ok(Change::Keep(result)) => break Ok(result),
+                Err(e) => break Err(e),
+            };
+
+            let entry_size = entry_bytes(key, &value);
+            let growth = entry_size.saturating_sub(old_size);
+            let fits = entry_size <= reserved
+                || growth == 0
+                || self.shards.reserve(growth) <= self.memory_limit_bytes();
+            if fits {
+                if entry_size > reserved {
+                    reserved += growth;
+                }
+                store.insert(key, CacheEntry { value, kind, timestamp: now, expires_at }, self.shards.tick());
+                store.counters.bytes_written += entry_size;
+                break Ok(result);
+            }
+
+            // Evicting may need this shard, so give the lock back first
+            self.shards.release(growth);
+            drop(store);
+            self.shards.release(reserved);
+            self.reserve(key, entry_size)?;
+            reserved = entry_size;
+        };
+        self.shards.release(reserved);
+        outcome
     }
 
-    async fn evict_lru_entries(&self) {
-        let mut cache = self.cache.write().await;
-        let mut memory_usage = self.memory_usage.write().await;
-        
-        // Find the oldest entry
-        let oldest_key = cache.iter()
-            .min_by_key(|(_, entry)| entry.timestamp)
-            .map(|(key, _)| key.clone());
-        
-        if let Some(key) = oldest_key {
-            if let Some(entry) = cache.remove(&key) {
-                *memory_usage -= entry.value.len() as u64;
+    /// `update` of one field of the hash stored at `key`; `apply` gets the
+    /// field's current value and returns its new one.
+    fn update_field<R>(
+        &self,
+        key: &str,
+        field: &str,
+        mut apply: impl FnMut(Option<&str>) -> Result<(String, R), PaymentError>,
+    ) -> Result<R, PaymentError> {
+        self.update(key, |current, _| {
+            let mut hash = match current {
+                Some(entry) => parse_hash(entry)?,
+                None => HashMap::new(),
+            };
+            let (value, result) = apply(hash.get(field).map(String::as_str))?;
+            hash.insert(field.to_string(), value);
+            Ok(Change::Write {
+                value: serde_json::to_string(&hash)?,
+                kind: EntryKind::Hash,
+                expires_at: current.and_then(|entry| entry.expires_at),
+                result,
+            })
+        })
+    }
+
+    /// Reserves `entry_size` bytes for a write to `key`, evicting from any
+    /// shard until the total fits the limit. Decides up front whether the
+    /// policy can free enough, so a rejected write evicts nothing. The
+    /// caller releases the reservation once the entry is in its shard.
+    fn reserve(&self, key: &str, entry_size: u64) -> Result<(), PaymentError> {
+        self.make_room(key, entry_size).map_err(|rejection| {
+            self.shards.lock_key(key).counters.rejected_writes += 1;
+            PaymentError::CacheRejected(rejection)
+        })
+    }
+
+    fn make_room(&self, key: &str, entry_size: u64) -> Result<(), CacheRejection> {
+        let limit_bytes = self.memory_limit_bytes();
+        if entry_size > limit_bytes {
+            return Err(CacheRejection::EntryTooLarge {
+                entry_bytes: entry_size,
+                limit_bytes,
+            });
+        }
+
+        // The value being replaced is freed by the write itself
+        let (replaced_bytes, replaced_volatile) = match self.shards.lock_key(key).peek(key) {
+            Some(old) => (entry_bytes(key, &old.value), old.expires_at.is_some()),
+            None => (0, false),
+        };
+        let used_after = self.shards.reserve(entry_size).saturating_sub(replaced_bytes);
+        if used_after <= limit_bytes {
+            return Ok(());
+        }
+
+        let needed_bytes = used_after - limit_bytes;
+        let evictable_bytes = if self.policy.is_allkeys() {
+            self.shards.used_bytes().saturating_sub(replaced_bytes)
+        } else if replaced_volatile {
+            self.shards.volatile_bytes().saturating_sub(replaced_bytes)
+        } else {
+            self.shards.volatile_bytes()
+        };
+        if evictable_bytes < needed_bytes {
+            self.shards.release(entry_size);
+            return Err(CacheRejection::NotEnoughEvictable {
+                needed_bytes,
+                evictable_bytes,
+            });
+        }
+
+        // Eviction skips `key`, whose old value goes when the new one lands
+        while self.shards.charged().saturating_sub(replaced_bytes) > limit_bytes {
+            if !self.evict_entry(key) {
+                break;
             }
         }
+        Ok(())
     }
 
-    async fn evict_volatile_lru_entries(&self) {
-        // For volatile LRU, we would check if entries have TTL set
-        // For simplicity, we'll use the same logic as AllKeysLRU
-        self.evict_lru_entries().await;
+    /// Removes one entry chosen by the policy across all shards, never
+    /// `protect`. Locks one shard at a time, so writers evicting from each
+    /// other's shards cannot deadlock. Returns false if none could be evicted.
+    fn evict_entry(&self, protect: &str) -> bool {
+        let victim = match self.policy {
+            CachePolicy::AllKeysRandom | CachePolicy::VolatileRandom => self.random_victim(protect),
+            _ => self.oldest_victim(protect),
+        };
+        let Some((index, key)) = victim else {
+            return false;
+        };
+
+        let mut store = self.shards.lock(index);
+        if store.remove(&key).is_some() {
+            store.counters.evicted += 1;
+        }
+        true
+    }
+
+    /// The lowest ranked candidate over all shards, see `Store::oldest`.
+    fn oldest_victim(&self, protect: &str) -> Option<(usize, String)> {
+        (0..self.shards.len())
+            .filter_map(|index| {
+                let (key, rank) = self.shards.lock(index).oldest(self.policy, protect)?;
+                Some((rank, index, key))
+            })
+            .min_by_key(|(rank, _, _)| *rank)
+            .map(|(_, index, key)| (index, key))
     }
 
-    async fn evict_random_entries(&self) {
-        let mut cache = self.cache.write().await;
-        let mut memory_usage = self.memory_usage.write().await;
-        
-        if let Some((key, _entry)) = cache.iter().next() {
-            let key = key.clone();
-            if let Some(entry) = cache.remove(&key) {
-                *memory_usage -= entry.value.len() as u64;
+    /// A uniformly random candidate: picks a shard weighted by how many
+    /// evictable entries it holds, then a key inside it.
+    fn random_victim(&self, protect: &str) -> Option<(usize, String)> {
+        let mut rng = rand::thread_rng();
+        let mut counts = self.shards.evictable_counts(self.policy);
+        loop {
+            let total: usize = counts.iter().sum();
+            if total == 0 {
+                return None;
             }
+            let mut pick = rng.gen_range(0..total);
+            let index = counts
+                .iter()
+                .position(|&count| {
+                    if pick < count {
+                        return true;
+                    }
+                    pick -= count;
+                    false
+                })
+                .unwrap_or_default();
+            if let Some(key) = self.shards.lock(index).random(self.policy, protect, &mut rng) {
+                return Some((index, key));
+            }
+            // Only `protect` was left there, or the shard emptied meanwhile
+            counts[index] = 0;
         }
     }
+}
 
-    async fn evict_volatile_random_entries(&self) {
-        // For volatile random, we would check if entries have TTL set
-        // For simplicity, we'll use the same logic as AllKeysRandom
-        self.evict_random_entries().await;
-    }
-
-    async fn evict_volatile_ttl_entries(&self) {
-        let mut cache = self.cache.write().await;
-        let mut memory_usage = self.memory_usage.write().await;
-        
-        let current_time = std::time::SystemTime::now()
-            .duration_since(std::time::UNIX_EPOCH)
-            .unwrap()
-            .as_secs();
-        
-        // Find the entry with the shortest TTL remaining
-        let shortest_ttl_key = cache.iter()
-            .filter(|(_, entry)| current_time - entry.timestamp < entry.ttl)
-            .min_by_key(|(_, entry)| entry.ttl - (current_time - entry.timestamp))
-            .map(|(key, _)| key.clone());
-        
-        if let Some(key) = shortest_ttl_key {
-            if let Some(entry) = cache.remove(&key) {
-                *memory_usage -= entry.value.len() as u64;
-            }
+/// What a read-modify-write decided to do with the entry under its key.
+enum Change<R> {
+    Keep(R),
+    Write { value: String, kind: EntryKind, expires_at: Option<u64>, result: R },
+}
+
+impl Drop for RedisCache {
+    fn drop(&mut self) {
+        self.stop_sweeper();
+        if let Some(handle) = self.snapshotter.lock().ok().and_then(|mut snapshotter| snapshotter.take()) {
+            handle.abort();
         }
     }
-} 
\ No newline at end of file
+}
+
+fn take_snapshot(shards: &Shards, now: u64) -> CacheSnapshot {
+    let mut entries = Vec::new();
+    for index in 0..shards.len() {
+        let store = shards.lock(index);
+        entries.extend(store.live_entries(now).map(|(key, entry, touched)| {
+            let entry = SnapshotEntry {
+                key: key.to_string(),
+                value: entry.value.clone(),
+                kind: entry.kind,
+                ttl_millis: entry.expires_at.map(|expires_at| expires_at - now),
+            };
+            (touched, entry)
+        }));
+    }
+    // Shards interleave in recency; the global ticks put them back in order
+    entries.sort_by_key(|(touched, _)| *touched);
+    CacheSnapshot::new(now, entries.into_iter().map(|(_, entry)| entry).collect())
+}
+
+impl<T> CacheEntry<T> {
+    pub(super) fn is_expired(&self, now: u64) -> bool {
+        self.expires_at.is_some_and(|expires_at| now > expires_at)
+    }
+}
+
+fn expiry(now: u64, ttl: Duration) -> u64 {
+    now.saturating_add(ttl.as_millis() as u64)
+}
+
+// The errors Redis answers with, so both backends fail alike
+const NOT_AN_INTEGER: &str = "value is not an integer or out of range";
+const NOT_A_FLOAT: &str = "value is not a valid float";
+const HASH_NOT_AN_INTEGER: &str = "hash value is not an integer";
+const HASH_NOT_A_FLOAT: &str = "hash value is not a float";
+const OVERFLOW: &str = "increment or decrement would overflow";
+const NOT_FINITE: &str = "increment would produce NaN or Infinity";
+const WRONG_TYPE: &str = "WRONGTYPE Operation against a key holding the wrong kind of value";
+
+fn value_error(message: &str) -> PaymentError {
+    PaymentError::Serialization(message.to_string())
+}
+
+/// Integer held in `value`; missing counts as 0.
+fn parse_int(value: Option<&str>, error: &str) -> Result<i64, PaymentError> {
+    value.map_or(Ok(0), |value| value.parse().map_err(|_| value_error(error)))
+}
+
+/// Sum of the float held in `value` and `delta`, both as Redis would store
+/// it and as the number that text reads back as.
+fn add_float(value: Option<&str>, delta: f64, error: &str) -> Result<(String, f64), PaymentError> {
+    let current: f64 = value.map_or(Ok(0.0), |value| value.parse().map_err(|_| value_error(error)))?;
+    let total = current + delta;
+    if !current.is_finite() || !total.is_finite() {
+        return Err(value_error(NOT_FINITE));
+    }
+    let text = format_float(total);
+    let total = text.parse().map_err(|_| value_error(error))?;
+    Ok((text, total))
+}
+
+/// Formats a float the way `INCRBYFLOAT` stores it: fixed notation with at
+/// most 17 decimals and no trailing zeros. Redis adds in long double, so
+/// only the 15 significant digits an f64 holds reliably are kept, which
+/// makes `0.1 + 0.2` come out as `0.3` there and here.
+fn format_float(value: f64) -> String {
+    let magnitude = if value == 0.0 { 0 } else { value.abs().log10().floor() as i32 };
+    let decimals = (14 - magnitude).clamp(0, 17) as usize;
+    let mut text = format!("{:.*}", decimals, value);
+    if text.contains('.') {
+        let trimmed = text.trim_end_matches('0').trim_end_matches('.').len();
+        text.truncate(trimmed);
+    }
+    if text == "-0" {
+        text.remove(0);
+    }
+    text
+}
+
+/// Every field of `entry`, which must hold a hash.
+fn parse_hash(entry: &CacheEntry<String>) -> Result<HashMap<String, String>, PaymentError> {
+    if entry.kind != EntryKind::Hash {
+        return Err(value_error(WRONG_TYPE));
+    }
+    serde_json::from_str(&entry.value).map_err(|_| value_error(WRONG_TYPE))
+}
+
+/// `entry` if it holds a string; string commands fail with WRONGTYPE on
+/// any other type.
+fn string_value(entry: Option<&CacheEntry<String>>) -> Result<Option<&CacheEntry<String>>, PaymentError> {
+    match entry {
+        Some(entry) if entry.kind != EntryKind::String => Err(value_error(WRONG_TYPE)),
+        entry => Ok(entry),
+    }
+}
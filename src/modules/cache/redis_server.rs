@@ -0,0 +1,463 @@
+use std::future::Future;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::time::Duration;
+use axum::async_trait;
+use redis::aio::ConnectionManager;
+use tokio::sync::OnceCell;
+use crate::modules::error::PaymentError;
+use super::backend::{CacheBackend, EntryTtl, MemoryStats};
+use super::stats::CacheStats;
+
+/// Connection settings for `RedisServerCache`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RedisSettings {
+    pub url: String,
+    /// Multiplexed connections opened lazily and used round-robin.
+    pub pool_size: usize,
+    pub connect_timeout: Duration,
+    /// Upper bound on any single command, so a slow Redis cannot stall payments.
+    pub command_timeout: Duration,
+    /// Prepended to every key; `clear` only removes keys with this prefix.
+    pub key_prefix: String,
+    /// Reported by `get_memory_limit_mb`; the server enforces its own maxmemory.
+    pub memory_limit_mb: u64,
+}
+
+impl Default for RedisSettings {
+    fn default() -> Self {
+        Self {
+            url: "redis://redis:6379".to_string(),
+            pool_size: 4,
+            connect_timeout: Duration::from_millis(500),
+            command_timeout: Duration::from_millis(100),
+            key_prefix: "rinha:".to_string(),
+            memory_limit_mb: 50,
+        }
+    }
+}
+
+/// Same check-and-set as `RedisCache::acquire_lease`, done atomically on
+/// the server.
+const ACQUIRE_LEASE_SCRIPT: &str = r"
+local current = redis.call('GET', KEYS[1])
+if current == false or current == ARGV[1] then
+  redis.call('SET', KEYS[1], ARGV[1], 'PX', ARGV[2])
+  return 1
+end
+return 0
+";
+
+/// Compare-and-swap, which Redis has no single command for. An empty
+/// TTL argument stores the new value without expiry.
+const COMPARE_AND_SWAP_SCRIPT: &str = r"
+if redis.call('GET', KEYS[1]) ~= ARGV[1] then
+  return 0
+end
+if ARGV[3] == '' then
+  redis.call('SET', KEYS[1], ARGV[2])
+else
+  redis.call('SET', KEYS[1], ARGV[2], 'PX', ARGV[3])
+end
+return 1
+";
+
+/// Cache backed by a real Redis server, so replicas share one cache.
+pub struct RedisServerCache {
+    settings: RedisSettings,
+    client: redis::Client,
+    pool: Vec<OnceCell<ConnectionManager>>,
+    next: AtomicUsize,
+    lease_script: redis::Script,
+    compare_and_swap_script: redis::Script,
+    counters: Counters,
+}
+
+/// What this client saw; expiries and evictions come from the server.
+#[derive(Default)]
+struct Counters {
+    hits: AtomicU64,
+    misses: AtomicU64,
+    rejected_writes: AtomicU64,
+    bytes_written: AtomicU64,
+}
+
+impl RedisServerCache {
+    /// Validates the URL; connections are only opened on first use.
+    pub fn new(settings: RedisSettings) -> Result<Self, PaymentError> {
+        let client = redis::Client::open(settings.url.as_str()).map_err(cache_error)?;
+        let pool = (0..settings.pool_size.max(1)).map(|_| OnceCell::new()).collect();
+        Ok(Self {
+            settings,
+            client,
+            pool,
+            next: AtomicUsize::new(0),
+            lease_script: redis::Script::new(ACQUIRE_LEASE_SCRIPT),
+            compare_and_swap_script: redis::Script::new(COMPARE_AND_SWAP_SCRIPT),
+            counters: Counters::default(),
+        })
+    }
+
+    pub fn settings(&self) -> &RedisSettings {
+        &self.settings
+    }
+
+    /// Round-trips a PING, for readiness checks.
+    pub async fn ping(&self) -> Result<(), PaymentError> {
+        let mut connection = self.connection().await?;
+        self.run(redis::cmd("PING").query_async::<_, String>(&mut connection))
+            .await
+            .map(|_| ())
+    }
+
+    fn key(&self, key: &str) -> String {
+        format!("{}{}", self.settings.key_prefix, key)
+    }
+
+    async fn connection(&self) -> Result<ConnectionManager, PaymentError> {
+        let slot = self.next.fetch_add(1, Ordering::Relaxed) % self.pool.len();
+        let connect_timeout = self.settings.connect_timeout;
+        let connection = self.pool[slot]
+            .get_or_try_init(|| async {
+                tokio::time::timeout(connect_timeout, self.client.get_tokio_connection_manager())
+                    .await
+                    .map_err(|_| PaymentError::Cache("connect timed out".to_string()))?
+                    .map_err(cache_error)
+            })
+            .await?;
+        Ok(connection.clone())
+    }
+
+    async fn run<T>(&self, command: impl Future<Output = redis::RedisResult<T>>) -> Result<T, PaymentError> {
+        self.run_with(command, cache_error).await
+    }
+
+    /// `run` for commands that read the stored value, where a server error
+    /// means the value has the wrong type rather than that Redis failed.
+    async fn run_on_value<T>(&self, command: impl Future<Output = redis::RedisResult<T>>) -> Result<T, PaymentError> {
+        self.run_with(command, value_error).await
+    }
+
+    async fn run_with<T>(
+        &self,
+        command: impl Future<Output = redis::RedisResult<T>>,
+        map_error: fn(redis::RedisError) -> PaymentError,
+    ) -> Result<T, PaymentError> {
+        tokio::time::timeout(self.settings.command_timeout, command)
+            .await
+            .map_err(|_| PaymentError::Cache("command timed out".to_string()))?
+            .map_err(map_error)
+    }
+
+    async fn info(&self, section: &str) -> Result<String, PaymentError> {
+        let mut connection = self.connection().await?;
+        self.run(redis::cmd("INFO").arg(section).query_async(&mut connection))
+            .await
+    }
+
+    /// Memory figures from an `INFO` reply. The entry count is the keyspace
+    /// section's count for our database, which includes keys outside our
+    /// prefix but needs no SCAN.
+    fn memory_stats_from(&self, info: &str) -> MemoryStats {
+        let limit_bytes = match info_field(info, "maxmemory") {
+            Some(limit) if limit > 0 => limit,
+            _ => self.settings.memory_limit_mb * 1024 * 1024,
+        };
+        let db = format!("db{}", self.client.get_connection_info().redis.db);
+        MemoryStats {
+            used_bytes: info_field(info, "used_memory").unwrap_or(0),
+            limit_bytes,
+            volatile_bytes: 0,
+            entry_count: keyspace_keys(info, &db).unwrap_or(0) as usize,
+        }
+    }
+
+    /// Keys under our prefix, walked with SCAN so large databases are not blocked.
+    async fn scan_keys(&self) -> Result<Vec<String>, PaymentError> {
+        let mut connection = self.connection().await?;
+        let pattern = format!("{}*", self.settings.key_prefix);
+        let mut cursor = 0u64;
+        let mut keys = Vec::new();
+        loop {
+            let (next, batch): (u64, Vec<String>) = self
+                .run(
+                    redis::cmd("SCAN")
+                        .arg(cursor)
+                        .arg("MATCH")
+                        .arg(&pattern)
+                        .arg("COUNT")
+                        .arg(500)
+                        .query_async(&mut connection),
+                )
+                .await?;
+            keys.extend(batch);
+            if next == 0 {
+                return Ok(keys);
+            }
+            cursor = next;
+        }
+    }
+}
+
+#[async_trait]
+impl CacheBackend for RedisServerCache {
+    fn name(&self) -> &'static str {
+        "redis"
+    }
+
+    async fn set_raw(&self, key: &str, value: String, ttl: Option<Duration>) -> Result<(), PaymentError> {
+        let value_len = value.len();
+        let mut command = redis::cmd("SET");
+        command.arg(self.key(key)).arg(value);
+        if let Some(ttl) = ttl {
+            command.arg("PX").arg(ttl_millis(ttl));
+        }
+        let written = (key.len() + value_len) as u64;
+        let mut connection = self.connection().await?;
+        match self.run(command.query_async::<_, ()>(&mut connection)).await {
+            Ok(()) => {
+                self.counters.bytes_written.fetch_add(written, Ordering::Relaxed);
+                Ok(())
+            }
+            Err(PaymentError::Cache(message)) if message.contains("OOM") => {
+                self.counters.rejected_writes.fetch_add(1, Ordering::Relaxed);
+                Err(PaymentError::Cache(message))
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    async fn get_raw(&self, key: &str) -> Result<Option<String>, PaymentError> {
+        let mut connection = self.connection().await?;
+        let value: Option<String> = self
+            .run(redis::cmd("GET").arg(self.key(key)).query_async(&mut connection))
+            .await?;
+        let counter = if value.is_some() { &self.counters.hits } else { &self.counters.misses };
+        counter.fetch_add(1, Ordering::Relaxed);
+        Ok(value)
+    }
+
+    async fn remove(&self, key: &str) -> Result<bool, PaymentError> {
+        let mut connection = self.connection().await?;
+        let removed: u64 = self
+            .run(redis::cmd("DEL").arg(self.key(key)).query_async(&mut connection))
+            .await?;
+        Ok(removed > 0)
+    }
+
+    async fn clear(&self) -> Result<(), PaymentError> {
+        let keys = self.scan_keys().await?;
+        if keys.is_empty() {
+            return Ok(());
+        }
+        let mut connection = self.connection().await?;
+        for chunk in keys.chunks(500) {
+            self.run(redis::cmd("DEL").arg(chunk).query_async::<_, ()>(&mut connection))
+                .await?;
+        }
+        Ok(())
+    }
+
+    async fn acquire_lease(&self, key: &str, holder: &str, ttl: Duration) -> Result<bool, PaymentError> {
+        let serialized_holder = serde_json::to_string(holder)?;
+        let mut connection = self.connection().await?;
+        let acquired: i32 = self
+            .run(
+                self.lease_script
+                    .key(self.key(key))
+                    .arg(serialized_holder)
+                    .arg(ttl_millis(ttl))
+                    .invoke_async(&mut connection),
+            )
+            .await?;
+        Ok(acquired == 1)
+    }
+
+    async fn incr_by(&self, key: &str, delta: i64) -> Result<i64, PaymentError> {
+        let mut connection = self.connection().await?;
+        self.run_on_value(redis::cmd("INCRBY").arg(self.key(key)).arg(delta).query_async(&mut connection))
+            .await
+    }
+
+    async fn incr_by_float(&self, key: &str, delta: f64) -> Result<f64, PaymentError> {
+        let mut connection = self.connection().await?;
+        self.run_on_value(redis::cmd("INCRBYFLOAT").arg(self.key(key)).arg(delta).query_async(&mut connection))
+            .await
+    }
+
+    async fn set_nx(&self, key: &str, value: String, ttl: Option<Duration>) -> Result<bool, PaymentError> {
+        let mut command = redis::cmd("SET");
+        command.arg(self.key(key)).arg(value).arg("NX");
+        if let Some(ttl) = ttl {
+            command.arg("PX").arg(ttl_millis(ttl));
+        }
+        let mut connection = self.connection().await?;
+        let stored: Option<String> = self.run(command.query_async(&mut connection)).await?;
+        Ok(stored.is_some())
+    }
+
+    async fn compare_and_swap(
+        &self,
+        key: &str,
+        expected: &str,
+        value: String,
+        ttl: Option<Duration>,
+    ) -> Result<bool, PaymentError> {
+        let ttl = ttl.map(|ttl| ttl_millis(ttl).to_string()).unwrap_or_default();
+        let mut connection = self.connection().await?;
+        let swapped: i32 = self
+            .run_on_value(
+                self.compare_and_swap_script
+                    .key(self.key(key))
+                    .arg(expected)
+                    .arg(value)
+                    .arg(ttl)
+                    .invoke_async(&mut connection),
+            )
+            .await?;
+        Ok(swapped == 1)
+    }
+
+    async fn hincr_by(&self, key: &str, field: &str, delta: i64) -> Result<i64, PaymentError> {
+        let mut connection = self.connection().await?;
+        self.run_on_value(
+            redis::cmd("HINCRBY")
+                .arg(self.key(key))
+                .arg(field)
+                .arg(delta)
+                .query_async(&mut connection),
+        )
+        .await
+    }
+
+    async fn hincr_by_float(&self, key: &str, field: &str, delta: f64) -> Result<f64, PaymentError> {
+        let mut connection = self.connection().await?;
+        self.run_on_value(
+            redis::cmd("HINCRBYFLOAT")
+                .arg(self.key(key))
+                .arg(field)
+                .arg(delta)
+                .query_async(&mut connection),
+        )
+        .await
+    }
+
+    async fn hget_all(&self, key: &str) -> Result<HashMap<String, String>, PaymentError> {
+        let mut connection = self.connection().await?;
+        self.run_on_value(redis::cmd("HGETALL").arg(self.key(key)).query_async(&mut connection))
+            .await
+    }
+
+    async fn ttl(&self, key: &str) -> Result<EntryTtl, PaymentError> {
+        let mut connection = self.connection().await?;
+        let millis: i64 = self
+            .run(redis::cmd("PTTL").arg(self.key(key)).query_async(&mut connection))
+            .await?;
+        Ok(match millis {
+            -2 => EntryTtl::Missing,
+            -1 => EntryTtl::Persistent,
+            millis => EntryTtl::Expires(Duration::from_millis(millis.max(0) as u64)),
+        })
+    }
+
+    async fn expire(&self, key: &str, ttl: Duration) -> Result<bool, PaymentError> {
+        let mut connection = self.connection().await?;
+        let updated: i32 = self
+            .run(
+                redis::cmd("PEXPIRE")
+                    .arg(self.key(key))
+                    .arg(ttl_millis(ttl))
+                    .query_async(&mut connection),
+            )
+            .await?;
+        Ok(updated == 1)
+    }
+
+    async fn persist(&self, key: &str) -> Result<bool, PaymentError> {
+        let mut connection = self.connection().await?;
+        let removed: i32 = self
+            .run(redis::cmd("PERSIST").arg(self.key(key)).query_async(&mut connection))
+            .await?;
+        Ok(removed == 1)
+    }
+
+    async fn get_memory_usage_mb(&self) -> u64 {
+        self.memory_stats().await.used_bytes / (1024 * 1024)
+    }
+
+    /// Server-wide `used_memory` and `maxmemory`; the server does not
+    /// report volatile bytes, so those stay 0.
+    async fn memory_stats(&self) -> MemoryStats {
+        let info = self.info("default").await.unwrap_or_default();
+        self.memory_stats_from(&info)
+    }
+
+    /// Reads counted by this client; expired and evicted keys are the
+    /// server's totals, under its `maxmemory-policy`. Everything else comes
+    /// from one `INFO` call.
+    async fn stats(&self) -> CacheStats {
+        let info = self.info("default").await.unwrap_or_default();
+        let policy = info_text(&info, "maxmemory_policy").unwrap_or("noeviction");
+        let mut evictions = BTreeMap::new();
+        evictions.insert(policy.to_string(), info_field(&info, "evicted_keys").unwrap_or(0));
+        CacheStats {
+            backend: self.name(),
+            hits: self.counters.hits.load(Ordering::Relaxed),
+            misses: self.counters.misses.load(Ordering::Relaxed),
+            expired_keys: info_field(&info, "expired_keys").unwrap_or(0),
+            evictions,
+            rejected_writes: self.counters.rejected_writes.load(Ordering::Relaxed),
+            bytes_written: self.counters.bytes_written.load(Ordering::Relaxed),
+            memory: self.memory_stats_from(&info),
+            tiers: None,
+        }
+    }
+
+    /// Walks the keys under our prefix; `stats` reports the database's key
+    /// count from `INFO` instead.
+    async fn get_entry_count(&self) -> usize {
+        self.scan_keys().await.map(|keys| keys.len()).unwrap_or(0)
+    }
+
+    fn get_memory_limit_mb(&self) -> u64 {
+        self.settings.memory_limit_mb
+    }
+}
+
+/// Numeric `name:value` line from an `INFO` reply.
+fn info_field(info: &str, name: &str) -> Option<u64> {
+    info_text(info, name).and_then(|value| value.parse().ok())
+}
+
+fn info_text<'a>(info: &'a str, name: &str) -> Option<&'a str> {
+    info.lines()
+        .find_map(|line| line.strip_prefix(name)?.strip_prefix(':'))
+        .map(str::trim)
+}
+
+/// `keys` of a keyspace line such as `db0:keys=5,expires=1,avg_ttl=0`;
+/// Redis leaves out the line of an empty database.
+fn keyspace_keys(info: &str, db: &str) -> Option<u64> {
+    info_text(info, db)?
+        .split(',')
+        .find_map(|pair| pair.strip_prefix("keys="))
+        .and_then(|keys| keys.parse().ok())
+}
+
+fn cache_error(error: redis::RedisError) -> PaymentError {
+    PaymentError::Cache(error.to_string())
+}
+
+/// Errors about the stored value, like `ERR value is not an integer` or
+/// `WRONGTYPE`, are the value's fault, as with the in-memory backend.
+fn value_error(error: redis::RedisError) -> PaymentError {
+    match error.code() {
+        Some("ERR") | Some("WRONGTYPE") => PaymentError::Serialization(error.to_string()),
+        _ => cache_error(error),
+    }
+}
+
+/// Redis rejects a zero expiry, so round up to one millisecond.
+fn ttl_millis(ttl: Duration) -> u64 {
+    (ttl.as_millis() as u64).max(1)
+}
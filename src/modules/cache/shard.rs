@@ -0,0 +1,378 @@
+use std::collections::HashMap;
+use std::hash::{BuildHasher, RandomState};
+use std::ops::{Deref, DerefMut};
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Mutex, MutexGuard};
+use rand::seq::IteratorRandom;
+use rand::Rng;
+use super::lru::LruMap;
+use super::redis::{CacheEntry, CachePolicy, ENTRY_OVERHEAD_BYTES};
+
+/// Keys sampled per active-expiry round, as in Redis.
+const SWEEP_SAMPLE_SIZE: usize = 20;
+/// Upper bound on rounds per sweep, so one sweep never holds a shard lock for long.
+const SWEEP_MAX_ROUNDS: usize = 16;
+
+/// The in-memory cache split into independently locked shards, picked by
+/// hashing the key. Totals are kept in atomics so the memory limit can be
+/// checked without taking every lock.
+pub(super) struct Shards {
+    shards: Box<[Shard]>,
+    hasher: RandomState,
+    /// Bytes held by all shards plus room reserved by writes still in
+    /// progress; this is what the memory limit is checked against.
+    charged: AtomicU64,
+    /// Recency clock shared by all shards, so their least recently used
+    /// entries can be compared with each other.
+    ticks: AtomicU64,
+}
+
+/// One shard: a `Store` behind its own lock, and atomic copies of its sizes
+/// that are refreshed whenever the lock is released.
+#[derive(Default)]
+struct Shard {
+    store: Mutex<Store>,
+    bytes: AtomicU64,
+    volatile_bytes: AtomicU64,
+    entries: AtomicUsize,
+    volatile_entries: AtomicUsize,
+}
+
+impl Shards {
+    pub(super) fn new(count: usize) -> Self {
+        Self {
+            shards: (0..count.max(1)).map(|_| Shard::default()).collect(),
+            hasher: RandomState::new(),
+            charged: AtomicU64::new(0),
+            ticks: AtomicU64::new(0),
+        }
+    }
+
+    pub(super) fn len(&self) -> usize {
+        self.shards.len()
+    }
+
+    pub(super) fn index_of(&self, key: &str) -> usize {
+        (self.hasher.hash_one(key) % self.shards.len() as u64) as usize
+    }
+
+    pub(super) fn lock(&self, index: usize) -> ShardGuard<'_> {
+        let shard = &self.shards[index];
+        ShardGuard {
+            store: shard.store.lock().unwrap_or_else(|poisoned| poisoned.into_inner()),
+            shard,
+            charged: &self.charged,
+        }
+    }
+
+    pub(super) fn lock_key(&self, key: &str) -> ShardGuard<'_> {
+        self.lock(self.index_of(key))
+    }
+
+    pub(super) fn tick(&self) -> u64 {
+        self.ticks.fetch_add(1, Ordering::Relaxed)
+    }
+
+    pub(super) fn charged(&self) -> u64 {
+        self.charged.load(Ordering::Acquire)
+    }
+
+    /// Holds `bytes` against the limit for a write about to land, returning
+    /// the new total.
+    pub(super) fn reserve(&self, bytes: u64) -> u64 {
+        self.charged.fetch_add(bytes, Ordering::AcqRel) + bytes
+    }
+
+    pub(super) fn release(&self, bytes: u64) {
+        self.charged.fetch_sub(bytes, Ordering::AcqRel);
+    }
+
+    pub(super) fn used_bytes(&self) -> u64 {
+        self.shards.iter().map(|shard| shard.bytes.load(Ordering::Acquire)).sum()
+    }
+
+    pub(super) fn volatile_bytes(&self) -> u64 {
+        self.shards.iter().map(|shard| shard.volatile_bytes.load(Ordering::Acquire)).sum()
+    }
+
+    pub(super) fn entry_count(&self) -> usize {
+        self.shards.iter().map(|shard| shard.entries.load(Ordering::Acquire)).sum()
+    }
+
+    /// Entries per shard that `policy` may evict.
+    pub(super) fn evictable_counts(&self, policy: CachePolicy) -> Vec<usize> {
+        self.shards
+            .iter()
+            .map(|shard| match policy.is_allkeys() {
+                true => shard.entries.load(Ordering::Acquire),
+                false => shard.volatile_entries.load(Ordering::Acquire),
+            })
+            .collect()
+    }
+
+    /// One active-expiry pass over every shard, locking one at a time.
+    pub(super) fn sweep_expired(&self, now: u64) -> usize {
+        (0..self.len()).map(|index| self.lock(index).sweep_expired(now)).sum()
+    }
+}
+
+/// Locked shard. Dropping it publishes the store's sizes to the shard's
+/// atomics and moves `charged` by however much the shard grew or shrank.
+pub(super) struct ShardGuard<'a> {
+    store: MutexGuard<'a, Store>,
+    shard: &'a Shard,
+    charged: &'a AtomicU64,
+}
+
+impl Deref for ShardGuard<'_> {
+    type Target = Store;
+
+    fn deref(&self) -> &Store {
+        &self.store
+    }
+}
+
+impl DerefMut for ShardGuard<'_> {
+    fn deref_mut(&mut self) -> &mut Store {
+        &mut self.store
+    }
+}
+
+impl Drop for ShardGuard<'_> {
+    fn drop(&mut self) {
+        let shard = self.shard;
+        let bytes = self.store.memory_usage;
+        let previous = shard.bytes.load(Ordering::Acquire);
+        if bytes != previous {
+            shard.bytes.store(bytes, Ordering::Release);
+            if bytes > previous {
+                self.charged.fetch_add(bytes - previous, Ordering::AcqRel);
+            } else {
+                self.charged.fetch_sub(previous - bytes, Ordering::AcqRel);
+            }
+        }
+        shard.volatile_bytes.store(self.store.volatile_bytes, Ordering::Release);
+        shard.entries.store(self.store.entries.len(), Ordering::Release);
+        shard.volatile_entries.store(self.store.expiring.len(), Ordering::Release);
+    }
+}
+
+/// A shard's entries in recency order plus the bytes they hold.
+#[derive(Default)]
+pub(super) struct Store {
+    entries: LruMap<Slot>,
+    expiring: ExpiringKeys,
+    /// Bytes charged for every entry, see `entry_bytes`.
+    memory_usage: u64,
+    /// The part of `memory_usage` held by entries with a TTL.
+    volatile_bytes: u64,
+    pub(super) counters: Counters,
+}
+
+struct Slot {
+    entry: CacheEntry<String>,
+    /// `Shards::tick` at the last write or read.
+    touched: u64,
+}
+
+/// Plain counters; every change to them happens under the shard lock.
+#[derive(Default)]
+pub(super) struct Counters {
+    pub(super) hits: u64,
+    pub(super) misses: u64,
+    pub(super) expired: u64,
+    pub(super) evicted: u64,
+    pub(super) rejected_writes: u64,
+    pub(super) bytes_written: u64,
+}
+
+impl Store {
+    pub(super) fn peek(&self, key: &str) -> Option<&CacheEntry<String>> {
+        self.entries.peek(key).map(|slot| &slot.entry)
+    }
+
+    /// Looks up `key` and marks it as used at `tick`.
+    pub(super) fn get(&mut self, key: &str, tick: u64) -> Option<&CacheEntry<String>> {
+        self.entries.peek_mut(key)?.touched = tick;
+        self.entries.get(key).map(|slot| &slot.entry)
+    }
+
+    pub(super) fn insert(&mut self, key: &str, entry: CacheEntry<String>, tick: u64) {
+        self.remove(key);
+        let size = entry_bytes(key, &entry.value);
+        let expires = entry.expires_at.is_some();
+        self.entries.insert(key.to_string(), Slot { entry, touched: tick });
+        self.memory_usage += size;
+        if expires {
+            self.volatile_bytes += size;
+        }
+        self.expiring.track(key, expires);
+    }
+
+    pub(super) fn remove(&mut self, key: &str) -> Option<CacheEntry<String>> {
+        let entry = self.entries.remove(key)?.entry;
+        let size = entry_bytes(key, &entry.value);
+        self.memory_usage -= size;
+        if entry.expires_at.is_some() {
+            self.volatile_bytes -= size;
+        }
+        self.expiring.track(key, false);
+        Some(entry)
+    }
+
+    /// Replaces the expiry of a live entry, returning the previous one, or
+    /// `None` if there is no such entry.
+    pub(super) fn set_expiry(&mut self, key: &str, expires_at: Option<u64>, now: u64) -> Option<Option<u64>> {
+        let entry = self.live(key, now)?;
+        let previous = std::mem::replace(&mut entry.expires_at, expires_at);
+        let size = entry_bytes(key, &entry.value);
+        match (previous.is_some(), expires_at.is_some()) {
+            (false, true) => self.volatile_bytes += size,
+            (true, false) => self.volatile_bytes -= size,
+            _ => {}
+        }
+        self.expiring.track(key, expires_at.is_some());
+        Some(previous)
+    }
+
+    pub(super) fn clear(&mut self) {
+        self.entries.clear();
+        self.expiring = ExpiringKeys::default();
+        self.memory_usage = 0;
+        self.volatile_bytes = 0;
+    }
+
+    /// Live entry under `key`; an expired one is removed on the way.
+    pub(super) fn live(&mut self, key: &str, now: u64) -> Option<&mut CacheEntry<String>> {
+        if self.peek(key)?.is_expired(now) {
+            self.remove(key);
+            self.counters.expired += 1;
+            return None;
+        }
+        self.entries.peek_mut(key).map(|slot| &mut slot.entry)
+    }
+
+    /// Entries still live at `now` with their recency tick, least recently
+    /// used first.
+    pub(super) fn live_entries(&self, now: u64) -> impl Iterator<Item = (&str, &CacheEntry<String>, u64)> {
+        self.entries
+            .iter()
+            .filter(move |(_, slot)| !slot.entry.is_expired(now))
+            .map(|(key, slot)| (key, &slot.entry, slot.touched))
+    }
+
+    /// The entry an ordered `policy` would evict first from this shard,
+    /// never `protect`, with its rank: the recency tick for the LRU
+    /// policies, the expiry for `VolatileTTL`. Lower ranks go first.
+    pub(super) fn oldest(&self, policy: CachePolicy, protect: &str) -> Option<(String, u64)> {
+        let mut candidates = self.entries.iter().filter(|(key, _)| *key != protect);
+        let (key, rank) = match policy {
+            CachePolicy::AllKeysLRU => candidates.next().map(|(key, slot)| (key, slot.touched)),
+            // Walks from the least recently used end, so this only costs
+            // more than O(1) when persistent keys pile up there
+            CachePolicy::VolatileLRU => candidates
+                .find(|(_, slot)| slot.entry.expires_at.is_some())
+                .map(|(key, slot)| (key, slot.touched)),
+            CachePolicy::VolatileTTL => candidates
+                .filter_map(|(key, slot)| Some((key, slot.entry.expires_at?)))
+                .min_by_key(|(_, expires_at)| *expires_at),
+            CachePolicy::AllKeysRandom | CachePolicy::VolatileRandom => None,
+        }?;
+        Some((key.to_string(), rank))
+    }
+
+    /// A random entry a random `policy` may evict, never `protect`.
+    pub(super) fn random<R: Rng>(&self, policy: CachePolicy, protect: &str, rng: &mut R) -> Option<String> {
+        let volatile = policy == CachePolicy::VolatileRandom;
+        let picked = if volatile {
+            self.expiring.random(rng)
+        } else {
+            self.entries.random_key(rng, |_| true)
+        };
+        match picked {
+            Some(key) if key != protect => Some(key.to_string()),
+            None => None,
+            // Only worth a scan in the rare case the write's own key came up
+            Some(_) => self
+                .entries
+                .iter()
+                .filter(|(key, slot)| *key != protect && (!volatile || slot.entry.expires_at.is_some()))
+                .map(|(key, _)| key.to_string())
+                .choose(rng),
+        }
+    }
+
+    /// Redis-style active expiry: samples keys with a TTL, removes the
+    /// expired ones, and goes again while more than a quarter of the sample
+    /// had expired.
+    fn sweep_expired(&mut self, now: u64) -> usize {
+        let mut rng = rand::thread_rng();
+        let mut removed = 0;
+        for _ in 0..SWEEP_MAX_ROUNDS {
+            let sample_size = SWEEP_SAMPLE_SIZE.min(self.expiring.len());
+            if sample_size == 0 {
+                break;
+            }
+            let mut expired = 0;
+            for _ in 0..sample_size {
+                let Some(key) = self.expiring.random(&mut rng).map(str::to_string) else {
+                    break;
+                };
+                if self.peek(&key).is_some_and(|entry| entry.is_expired(now)) {
+                    self.remove(&key);
+                    expired += 1;
+                }
+            }
+            removed += expired;
+            self.counters.expired += expired as u64;
+            if expired * 4 <= sample_size {
+                break;
+            }
+        }
+        removed
+    }
+}
+
+/// Keys that carry a TTL, in a vector so a random one can be picked in
+/// O(1) by the sweeper and the volatile-random policy.
+#[derive(Default)]
+struct ExpiringKeys {
+    keys: Vec<String>,
+    positions: HashMap<String, usize>,
+}
+
+impl ExpiringKeys {
+    fn len(&self) -> usize {
+        self.keys.len()
+    }
+
+    /// Adds `key` when it `expires`, removes it otherwise.
+    fn track(&mut self, key: &str, expires: bool) {
+        match (expires, self.positions.contains_key(key)) {
+            (true, false) => {
+                self.positions.insert(key.to_string(), self.keys.len());
+                self.keys.push(key.to_string());
+            }
+            (false, true) => {
+                let position = self.positions.remove(key).unwrap_or_default();
+                self.keys.swap_remove(position);
+                if let Some(moved) = self.keys.get(position) {
+                    self.positions.insert(moved.clone(), position);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn random<R: Rng>(&self, rng: &mut R) -> Option<&str> {
+        if self.keys.is_empty() {
+            return None;
+        }
+        Some(&self.keys[rng.gen_range(0..self.keys.len())])
+    }
+}
+
+/// Bytes charged for one entry.
+pub(super) fn entry_bytes(key: &str, value: &str) -> u64 {
+    (key.len() + value.len()) as u64 + ENTRY_OVERHEAD_BYTES
+}
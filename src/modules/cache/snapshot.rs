@@ -0,0 +1,87 @@
+use std::path::{Path, PathBuf};
+use serde::{Deserialize, Serialize};
+use crate::modules::error::PaymentError;
+use super::redis::EntryKind;
+
+/// Format of `CacheSnapshot`; files of another version are not loaded.
+pub const SNAPSHOT_VERSION: u32 = 1;
+
+/// Live cache entries with the TTL each had left, saved so a restarted
+/// replica starts warm.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CacheSnapshot {
+    pub version: u32,
+    /// Clock milliseconds when the snapshot was taken.
+    pub taken_at: u64,
+    /// Least recently used first, so loading them in order keeps the LRU
+    /// order.
+    pub entries: Vec<SnapshotEntry>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SnapshotEntry {
+    pub key: String,
+    pub value: String,
+    /// Milliseconds left at `taken_at`; `None` never expires.
+    pub ttl_millis: Option<u64>,
+    #[serde(default)]
+    pub kind: EntryKind,
+}
+
+impl CacheSnapshot {
+    pub fn new(taken_at: u64, entries: Vec<SnapshotEntry>) -> Self {
+        Self {
+            version: SNAPSHOT_VERSION,
+            taken_at,
+            entries,
+        }
+    }
+
+    /// Compact MessagePack, with structs as arrays rather than maps.
+    pub fn to_msgpack(&self) -> Result<Vec<u8>, PaymentError> {
+        Ok(rmp_serde::to_vec(self)?)
+    }
+
+    pub fn from_msgpack(data: &[u8]) -> Result<Self, PaymentError> {
+        let snapshot: Self = rmp_serde::from_slice(data)?;
+        if snapshot.version != SNAPSHOT_VERSION {
+            return Err(PaymentError::Serialization(format!(
+                "cache snapshot version {} is not {}",
+                snapshot.version, SNAPSHOT_VERSION
+            )));
+        }
+        Ok(snapshot)
+    }
+
+    /// Writes to a temporary file next to `path` and renames it over
+    /// `path`, so a crash mid-write never leaves a truncated snapshot.
+    pub async fn write_to(&self, path: &Path) -> Result<(), PaymentError> {
+        let data = self.to_msgpack()?;
+        if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            tokio::fs::create_dir_all(dir).await.map_err(|e| io_error(dir, e))?;
+        }
+        // Unique, so a scheduled write and the shutdown write cannot collide
+        let mut temporary = path.as_os_str().to_owned();
+        temporary.push(format!(".{}.tmp", uuid::Uuid::new_v4()));
+        let temporary = PathBuf::from(temporary);
+
+        if let Err(e) = tokio::fs::write(&temporary, data).await {
+            let _ = tokio::fs::remove_file(&temporary).await;
+            return Err(io_error(&temporary, e));
+        }
+        tokio::fs::rename(&temporary, path).await.map_err(|e| io_error(path, e))
+    }
+
+    /// The snapshot at `path`, or `None` if there is no file yet.
+    pub async fn read_from(path: &Path) -> Result<Option<Self>, PaymentError> {
+        match tokio::fs::read(path).await {
+            Ok(data) => Self::from_msgpack(&data).map(Some),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(io_error(path, e)),
+        }
+    }
+}
+
+fn io_error(path: &Path, error: std::io::Error) -> PaymentError {
+    PaymentError::Cache(format!("cache snapshot {}: {}", path.display(), error))
+}
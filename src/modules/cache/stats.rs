@@ -0,0 +1,139 @@
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use serde::Serialize;
+use super::backend::MemoryStats;
+
+/// Counters a backend keeps since it started, plus its current memory.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct CacheStats {
+    pub backend: &'static str,
+    /// Reads that found a live entry.
+    pub hits: u64,
+    /// Reads that found nothing, including entries that had just expired.
+    pub misses: u64,
+    /// Entries removed because their TTL ran out, on read or by the sweeper.
+    pub expired_keys: u64,
+    /// Entries evicted to make room, by the eviction policy that chose them.
+    pub evictions: BTreeMap<String, u64>,
+    /// Writes refused because they could not fit under the memory limit.
+    pub rejected_writes: u64,
+    /// Bytes stored by successful writes, as charged against the limit.
+    pub bytes_written: u64,
+    pub memory: MemoryStats,
+    /// Per-tier reads when the backend is a `TieredCache`.
+    pub tiers: Option<TierStats>,
+}
+
+/// Reads one tier of a `TieredCache` answered or passed on.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize)]
+pub struct HitCounts {
+    pub hits: u64,
+    pub misses: u64,
+}
+
+impl HitCounts {
+    pub fn hit_rate(&self) -> f64 {
+        let reads = self.hits + self.misses;
+        if reads == 0 {
+            return 0.0;
+        }
+        self.hits as f64 / reads as f64
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize)]
+pub struct TierStats {
+    /// Reads of the in-process tier.
+    pub l1: HitCounts,
+    /// Reads that reached the shared tier, after an L1 miss or for keys
+    /// kept out of L1.
+    pub l2: HitCounts,
+    pub invalidations_published: u64,
+    /// Invalidations from other replicas applied to this L1.
+    pub invalidations_received: u64,
+}
+
+/// Section of the `INFO`-style report, as in Redis `INFO memory`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InfoSection {
+    All,
+    Memory,
+    Stats,
+}
+
+impl std::str::FromStr for InfoSection {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.to_ascii_lowercase().as_str() {
+            "" | "all" | "default" => Ok(InfoSection::All),
+            "memory" => Ok(InfoSection::Memory),
+            "stats" => Ok(InfoSection::Stats),
+            other => Err(format!("unknown INFO section: {}", other)),
+        }
+    }
+}
+
+impl CacheStats {
+    pub fn evicted_keys(&self) -> u64 {
+        self.evictions.values().sum()
+    }
+
+    /// Share of reads that were hits; 0 before the first read.
+    pub fn hit_rate(&self) -> f64 {
+        HitCounts { hits: self.hits, misses: self.misses }.hit_rate()
+    }
+
+    /// Renders the snapshot as `field:value` lines under `# Section`
+    /// headers, the format Redis uses for `INFO`.
+    pub fn to_info(&self, section: InfoSection) -> String {
+        let mut info = String::new();
+        if matches!(section, InfoSection::All | InfoSection::Memory) {
+            let memory = &self.memory;
+            info.push_str("# Memory\r\n");
+            let _ = write!(info, "cache_backend:{}\r\n", self.backend);
+            let _ = write!(info, "used_memory:{}\r\n", memory.used_bytes);
+            let _ = write!(info, "used_memory_human:{}\r\n", human_bytes(memory.used_bytes));
+            let _ = write!(info, "maxmemory:{}\r\n", memory.limit_bytes);
+            let _ = write!(info, "maxmemory_human:{}\r\n", human_bytes(memory.limit_bytes));
+            let _ = write!(info, "volatile_memory:{}\r\n", memory.volatile_bytes);
+            let _ = write!(info, "keys:{}\r\n", memory.entry_count);
+        }
+        if section == InfoSection::All {
+            info.push_str("\r\n");
+        }
+        if matches!(section, InfoSection::All | InfoSection::Stats) {
+            info.push_str("# Stats\r\n");
+            let _ = write!(info, "keyspace_hits:{}\r\n", self.hits);
+            let _ = write!(info, "keyspace_misses:{}\r\n", self.misses);
+            let _ = write!(info, "hit_rate:{:.4}\r\n", self.hit_rate());
+            let _ = write!(info, "expired_keys:{}\r\n", self.expired_keys);
+            let _ = write!(info, "evicted_keys:{}\r\n", self.evicted_keys());
+            for (policy, evicted) in &self.evictions {
+                let _ = write!(info, "evicted_keys_{}:{}\r\n", policy.replace('-', "_"), evicted);
+            }
+            let _ = write!(info, "rejected_writes:{}\r\n", self.rejected_writes);
+            let _ = write!(info, "bytes_written:{}\r\n", self.bytes_written);
+            if let Some(tiers) = &self.tiers {
+                for (tier, counts) in [("l1", tiers.l1), ("l2", tiers.l2)] {
+                    let _ = write!(info, "{}_hits:{}\r\n", tier, counts.hits);
+                    let _ = write!(info, "{}_misses:{}\r\n", tier, counts.misses);
+                    let _ = write!(info, "{}_hit_rate:{:.4}\r\n", tier, counts.hit_rate());
+                }
+                let _ = write!(info, "invalidations_published:{}\r\n", tiers.invalidations_published);
+                let _ = write!(info, "invalidations_received:{}\r\n", tiers.invalidations_received);
+            }
+        }
+        info
+    }
+}
+
+/// Formats bytes like Redis `used_memory_human`, e.g. `1.50M`.
+fn human_bytes(bytes: u64) -> String {
+    const UNITS: [(&str, u64); 3] = [("G", 1 << 30), ("M", 1 << 20), ("K", 1 << 10)];
+    UNITS
+        .iter()
+        .find(|(_, size)| bytes >= *size)
+        .map(|(unit, size)| format!("{:.2}{}", bytes as f64 / *size as f64, unit))
+        .unwrap_or_else(|| format!("{}B", bytes))
+}
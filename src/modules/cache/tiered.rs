@@ -0,0 +1,345 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use axum::async_trait;
+use tokio::sync::broadcast::error::RecvError;
+use tokio::task::JoinHandle;
+use crate::modules::clock::SharedClock;
+use crate::modules::error::PaymentError;
+use super::backend::{CacheBackend, EntryTtl, MemoryStats};
+use super::invalidation::{Invalidation, InvalidationBus};
+use super::redis::RedisCache;
+use super::snapshot::CacheSnapshot;
+use super::stats::{CacheStats, HitCounts, TierStats};
+
+/// How `TieredCache` sizes and fills its in-process tier.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TierSettings {
+    pub l1_memory_limit_mb: u64,
+    /// How long a value read from L2 is then served from L1; zero turns
+    /// L1 off.
+    pub l1_ttl: Duration,
+    /// L1 TTLs for keys starting with a prefix, overriding `l1_ttl`. The
+    /// longest matching prefix wins; zero keeps those keys out of L1.
+    pub l1_key_ttls: Vec<(String, Duration)>,
+    /// Pub/sub channel the replicas exchange invalidations on.
+    pub channel: String,
+    /// Names this replica in the invalidations it sends.
+    pub replica_id: String,
+}
+
+impl Default for TierSettings {
+    fn default() -> Self {
+        Self {
+            l1_memory_limit_mb: 8,
+            l1_ttl: Duration::from_millis(200),
+            l1_key_ttls: Vec::new(),
+            channel: "rinha:invalidate".to_string(),
+            replica_id: "local".to_string(),
+        }
+    }
+}
+
+impl TierSettings {
+    pub fn l1_ttl_for(&self, key: &str) -> Duration {
+        self.l1_key_ttls
+            .iter()
+            .filter(|(prefix, _)| key.starts_with(prefix.as_str()))
+            .max_by_key(|(prefix, _)| prefix.len())
+            .map_or(self.l1_ttl, |(_, ttl)| *ttl)
+    }
+}
+
+/// Small in-process L1 in front of a shared L2 such as Redis. Reads try L1
+/// first and copy L2 hits into it for the key's L1 TTL. Every write goes to
+/// L2; writes to keys L1 may hold drop the local copy and are broadcast on
+/// the `InvalidationBus`, so other replicas drop theirs once
+/// `start_invalidation_listener` runs.
+pub struct TieredCache {
+    l1: Arc<RedisCache>,
+    l2: Arc<dyn CacheBackend>,
+    bus: Arc<dyn InvalidationBus>,
+    settings: TierSettings,
+    counters: Arc<TierCounters>,
+    listener: Mutex<Option<JoinHandle<()>>>,
+}
+
+#[derive(Default)]
+struct TierCounters {
+    l1_hits: AtomicU64,
+    l1_misses: AtomicU64,
+    l2_hits: AtomicU64,
+    l2_misses: AtomicU64,
+    published: AtomicU64,
+    received: AtomicU64,
+    /// Bumped by every invalidation, so a read that raced one does not
+    /// copy what may be an old value into L1.
+    generation: AtomicU64,
+}
+
+impl TieredCache {
+    pub fn new(l2: Arc<dyn CacheBackend>, bus: Arc<dyn InvalidationBus>, settings: TierSettings) -> Self {
+        Self {
+            l1: Arc::new(RedisCache::with_memory_limit(settings.l1_memory_limit_mb)),
+            l2,
+            bus,
+            settings,
+            counters: Arc::new(TierCounters::default()),
+            listener: Mutex::new(None),
+        }
+    }
+
+    pub fn settings(&self) -> &TierSettings {
+        &self.settings
+    }
+
+    /// Spawns the task that applies invalidations from other replicas to
+    /// L1. Returns false if it is already running.
+    pub fn start_invalidation_listener(&self) -> bool {
+        let Ok(mut listener) = self.listener.lock() else {
+            return false;
+        };
+        if listener.as_ref().is_some_and(|handle| !handle.is_finished()) {
+            return false;
+        }
+
+        let mut invalidations = self.bus.subscribe();
+        let l1 = Arc::clone(&self.l1);
+        let counters = Arc::clone(&self.counters);
+        let replica_id = self.settings.replica_id.clone();
+        *listener = Some(tokio::spawn(async move {
+            loop {
+                let key = match invalidations.recv().await {
+                    Ok(invalidation) if invalidation.origin == replica_id => continue,
+                    Ok(invalidation) => invalidation.key,
+                    // Some were missed, so none of L1 can be trusted
+                    Err(RecvError::Lagged(_)) => None,
+                    Err(RecvError::Closed) => break,
+                };
+                counters.received.fetch_add(1, Ordering::Relaxed);
+                counters.generation.fetch_add(1, Ordering::AcqRel);
+                let _ = match key {
+                    Some(key) => l1.remove(&key).await.map(|_| ()),
+                    None => l1.clear().await,
+                };
+            }
+        }));
+        true
+    }
+
+    pub fn tier_stats(&self) -> TierStats {
+        let counters = &self.counters;
+        TierStats {
+            l1: HitCounts {
+                hits: counters.l1_hits.load(Ordering::Relaxed),
+                misses: counters.l1_misses.load(Ordering::Relaxed),
+            },
+            l2: HitCounts {
+                hits: counters.l2_hits.load(Ordering::Relaxed),
+                misses: counters.l2_misses.load(Ordering::Relaxed),
+            },
+            invalidations_published: counters.published.load(Ordering::Relaxed),
+            invalidations_received: counters.received.load(Ordering::Relaxed),
+        }
+    }
+
+    async fn read_l2(&self, key: &str) -> Result<Option<String>, PaymentError> {
+        let value = self.l2.get_raw(key).await?;
+        let counter = if value.is_some() { &self.counters.l2_hits } else { &self.counters.l2_misses };
+        counter.fetch_add(1, Ordering::Relaxed);
+        Ok(value)
+    }
+
+    /// Drops the local copy of `key` and tells the other replicas to. Keys
+    /// with an L1 TTL of zero are never copied into L1, so there is nothing
+    /// to drop.
+    async fn invalidate(&self, key: &str) {
+        if self.settings.l1_ttl_for(key).is_zero() {
+            return;
+        }
+        self.counters.generation.fetch_add(1, Ordering::AcqRel);
+        let _ = self.l1.remove(key).await;
+        self.broadcast(Invalidation::key(&self.settings.replica_id, key)).await;
+    }
+
+    async fn broadcast(&self, invalidation: Invalidation) {
+        match self.bus.publish(&invalidation).await {
+            Ok(()) => {
+                self.counters.published.fetch_add(1, Ordering::Relaxed);
+            }
+            // Other replicas catch up when their L1 copy expires
+            Err(e) => log::warn!("Could not broadcast cache invalidation {:?}: {}", invalidation.key, e),
+        }
+    }
+
+    /// Runs a write on L2 and invalidates `key` when `changed` says it
+    /// changed anything.
+    async fn write<T>(&self, key: &str, result: Result<T, PaymentError>, changed: impl Fn(&T) -> bool) -> Result<T, PaymentError> {
+        let value = result?;
+        if changed(&value) {
+            self.invalidate(key).await;
+        }
+        Ok(value)
+    }
+}
+
+#[async_trait]
+impl CacheBackend for TieredCache {
+    fn name(&self) -> &'static str {
+        self.l2.name()
+    }
+
+    async fn set_raw(&self, key: &str, value: String, ttl: Option<Duration>) -> Result<(), PaymentError> {
+        self.write(key, self.l2.set_raw(key, value, ttl).await, |_| true).await
+    }
+
+    async fn get_raw(&self, key: &str) -> Result<Option<String>, PaymentError> {
+        let l1_ttl = self.settings.l1_ttl_for(key);
+        if l1_ttl.is_zero() {
+            return self.read_l2(key).await;
+        }
+        if let Some(value) = self.l1.get_raw(key).await? {
+            self.counters.l1_hits.fetch_add(1, Ordering::Relaxed);
+            return Ok(Some(value));
+        }
+        self.counters.l1_misses.fetch_add(1, Ordering::Relaxed);
+
+        let generation = self.counters.generation.load(Ordering::Acquire);
+        let value = self.read_l2(key).await?;
+        if let Some(value) = &value {
+            if self.counters.generation.load(Ordering::Acquire) == generation {
+                if let Err(e) = self.l1.set_raw(key, value.clone(), Some(l1_ttl)).await {
+                    log::debug!("Not keeping {} in L1: {}", key, e);
+                }
+            }
+        }
+        Ok(value)
+    }
+
+    async fn remove(&self, key: &str) -> Result<bool, PaymentError> {
+        self.write(key, self.l2.remove(key).await, |removed| *removed).await
+    }
+
+    async fn clear(&self) -> Result<(), PaymentError> {
+        self.l2.clear().await?;
+        self.counters.generation.fetch_add(1, Ordering::AcqRel);
+        self.l1.clear().await?;
+        self.broadcast(Invalidation::all(&self.settings.replica_id)).await;
+        Ok(())
+    }
+
+    async fn acquire_lease(&self, key: &str, holder: &str, ttl: Duration) -> Result<bool, PaymentError> {
+        self.write(key, self.l2.acquire_lease(key, holder, ttl).await, |acquired| *acquired).await
+    }
+
+    async fn incr_by(&self, key: &str, delta: i64) -> Result<i64, PaymentError> {
+        self.write(key, self.l2.incr_by(key, delta).await, |_| true).await
+    }
+
+    async fn incr_by_float(&self, key: &str, delta: f64) -> Result<f64, PaymentError> {
+        self.write(key, self.l2.incr_by_float(key, delta).await, |_| true).await
+    }
+
+    async fn set_nx(&self, key: &str, value: String, ttl: Option<Duration>) -> Result<bool, PaymentError> {
+        self.write(key, self.l2.set_nx(key, value, ttl).await, |stored| *stored).await
+    }
+
+    async fn compare_and_swap(
+        &self,
+        key: &str,
+        expected: &str,
+        value: String,
+        ttl: Option<Duration>,
+    ) -> Result<bool, PaymentError> {
+        let result = self.l2.compare_and_swap(key, expected, value, ttl).await;
+        self.write(key, result, |swapped| *swapped).await
+    }
+
+    /// Hashes are not kept in L1, so there is nothing to invalidate.
+    async fn hincr_by(&self, key: &str, field: &str, delta: i64) -> Result<i64, PaymentError> {
+        self.l2.hincr_by(key, field, delta).await
+    }
+
+    async fn hincr_by_float(&self, key: &str, field: &str, delta: f64) -> Result<f64, PaymentError> {
+        self.l2.hincr_by_float(key, field, delta).await
+    }
+
+    /// Hashes are not kept in L1.
+    async fn hget_all(&self, key: &str) -> Result<HashMap<String, String>, PaymentError> {
+        self.l2.hget_all(key).await
+    }
+
+    async fn ttl(&self, key: &str) -> Result<EntryTtl, PaymentError> {
+        self.l2.ttl(key).await
+    }
+
+    async fn expire(&self, key: &str, ttl: Duration) -> Result<bool, PaymentError> {
+        self.write(key, self.l2.expire(key, ttl).await, |updated| *updated).await
+    }
+
+    async fn persist(&self, key: &str) -> Result<bool, PaymentError> {
+        self.write(key, self.l2.persist(key).await, |updated| *updated).await
+    }
+
+    async fn get_memory_usage_mb(&self) -> u64 {
+        self.l2.get_memory_usage_mb().await
+    }
+
+    async fn memory_stats(&self) -> MemoryStats {
+        self.l2.memory_stats().await
+    }
+
+    /// The L2's own stats, plus hit rates per tier.
+    async fn stats(&self) -> CacheStats {
+        let mut stats = self.l2.stats().await;
+        stats.tiers = Some(self.tier_stats());
+        stats
+    }
+
+    async fn get_entry_count(&self) -> usize {
+        self.l2.get_entry_count().await
+    }
+
+    fn get_memory_limit_mb(&self) -> u64 {
+        self.l2.get_memory_limit_mb()
+    }
+
+    /// Only reaches L1; an L2 shared with others keeps its own clock.
+    fn set_clock(&mut self, clock: SharedClock) {
+        if let Some(l1) = Arc::get_mut(&mut self.l1) {
+            l1.set_clock(clock);
+        }
+    }
+
+    fn start_sweeper(&self, interval: Duration) -> bool {
+        let l1 = self.l1.start_sweeper(interval);
+        self.l2.start_sweeper(interval) || l1
+    }
+
+    fn start_invalidation_listener(&self) -> bool {
+        TieredCache::start_invalidation_listener(self)
+    }
+
+    /// Only L2 is worth keeping; L1 copies are short-lived.
+    async fn snapshot(&self) -> Option<CacheSnapshot> {
+        self.l2.snapshot().await
+    }
+
+    async fn restore(&self, snapshot: &CacheSnapshot) -> usize {
+        self.l2.restore(snapshot).await
+    }
+
+    fn start_snapshots(&self, path: PathBuf, interval: Duration) -> bool {
+        self.l2.start_snapshots(path, interval)
+    }
+}
+
+impl Drop for TieredCache {
+    fn drop(&mut self) {
+        if let Some(handle) = self.listener.lock().ok().and_then(|mut listener| listener.take()) {
+            handle.abort();
+        }
+    }
+}
@@ -0,0 +1,111 @@
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use chrono::{DateTime, SecondsFormat, Utc};
+
+/// Source of wall-clock time for every module, so tests can replace it
+/// with a `ManualClock` and drive TTLs, rate limits and summary windows
+/// deterministically.
+pub trait Clock: Send + Sync {
+    /// Milliseconds since the Unix epoch.
+    fn now_millis(&self) -> u64;
+
+    fn now_secs(&self) -> u64 {
+        self.now_millis() / 1000
+    }
+}
+
+pub type SharedClock = Arc<dyn Clock>;
+
+pub fn system_clock() -> SharedClock {
+    Arc::new(SystemClock)
+}
+
+#[derive(Debug, Default, Clone, Copy)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now_millis(&self) -> u64 {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|elapsed| elapsed.as_millis() as u64)
+            .unwrap_or(0)
+    }
+}
+
+/// Epoch milliseconds that never go backwards: read from the system clock
+/// once, then advanced with `Instant`, so adjusting the wall clock cannot
+/// make TTLs jump.
+#[derive(Debug, Clone, Copy)]
+pub struct MonotonicClock {
+    start_millis: u64,
+    start: Instant,
+}
+
+impl MonotonicClock {
+    pub fn new() -> Self {
+        Self {
+            start_millis: SystemClock.now_millis(),
+            start: Instant::now(),
+        }
+    }
+}
+
+impl Default for MonotonicClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Clock for MonotonicClock {
+    fn now_millis(&self) -> u64 {
+        self.start_millis + self.start.elapsed().as_millis() as u64
+    }
+}
+
+pub fn monotonic_clock() -> SharedClock {
+    Arc::new(MonotonicClock::new())
+}
+
+/// Clock that only moves when told to.
+#[derive(Debug, Default)]
+pub struct ManualClock {
+    millis: AtomicU64,
+}
+
+impl ManualClock {
+    pub fn new(start_millis: u64) -> Self {
+        Self {
+            millis: AtomicU64::new(start_millis),
+        }
+    }
+
+    pub fn set_millis(&self, millis: u64) {
+        self.millis.store(millis, Ordering::SeqCst);
+    }
+
+    pub fn advance(&self, by: Duration) {
+        self.millis.fetch_add(by.as_millis() as u64, Ordering::SeqCst);
+    }
+}
+
+impl Clock for ManualClock {
+    fn now_millis(&self) -> u64 {
+        self.millis.load(Ordering::SeqCst)
+    }
+}
+
+/// Formats epoch milliseconds as an ISO-8601 UTC timestamp with millisecond
+/// precision, e.g. `2025-07-15T12:34:56.000Z`.
+pub fn format_iso8601(millis: u64) -> String {
+    DateTime::<Utc>::from_timestamp_millis(millis as i64)
+        .unwrap_or_default()
+        .to_rfc3339_opts(SecondsFormat::Millis, true)
+}
+
+/// Parses an ISO-8601 timestamp into epoch milliseconds. Timestamps before
+/// the epoch clamp to zero.
+pub fn parse_iso8601(value: &str) -> Result<u64, chrono::ParseError> {
+    let parsed = DateTime::parse_from_rfc3339(value)?;
+    Ok(parsed.timestamp_millis().max(0) as u64)
+}
@@ -1,22 +1,134 @@
 use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::time::Duration;
+use crate::modules::cache::redis::CachePolicy;
+use crate::modules::cache::redis_server::RedisSettings;
+use crate::modules::cache::tiered::TierSettings;
+use crate::modules::processors::retry::RetryPolicy;
+use crate::modules::queue::{DEFAULT_MAX_REQUEUES, DEFAULT_REQUEUE_BASE_BACKOFF, DEFAULT_REQUEUE_MAX_BACKOFF};
+
+/// How `POST /payments` hands payments to the processors.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IntakeMode {
+    /// The handler waits for the processor to answer.
+    Sync,
+    /// The handler enqueues the payment and answers 202 right away.
+    Async,
+}
+
+/// What the handler does when the async intake queue is full.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum QueueFullPolicy {
+    /// Answer 503 so the client can retry later.
+    Reject,
+    /// Process the payment synchronously in the handler.
+    ProcessInline,
+}
+
+/// Where `CacheManager` keeps its entries.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CacheBackendKind {
+    /// In-process map; each replica has its own cache.
+    Memory,
+    /// A Redis server shared by all replicas.
+    Redis,
+}
 
 #[derive(Debug, Clone)]
 pub struct Config {
     pub server_addr: SocketAddr,
+    /// Admin listener for `/metrics/*`, kept off the public port; metrics
+    /// are not served when unset.
+    pub metrics_addr: Option<SocketAddr>,
     /// Log level configuration for the application.
     /// Currently not used but kept for future logging implementation.
     /// TODO: Implement logging system using this field (T2.x task)
     #[allow(dead_code)]
-    pub log_level: String
+    pub log_level: String,
+    pub intake_mode: IntakeMode,
+    pub queue_capacity: usize,
+    pub queue_workers: usize,
+    pub queue_full_policy: QueueFullPolicy,
+    /// Backoff before a queued payment that failed with a retryable error
+    /// is requeued; doubles per failure up to `queue_requeue_max_backoff`.
+    pub queue_requeue_base_backoff: Duration,
+    pub queue_requeue_max_backoff: Duration,
+    /// Requeues before a queued payment is dropped as failed.
+    pub queue_max_requeues: u32,
+    pub retry_policy: RetryPolicy,
+    pub health_poll_interval: Duration,
+    /// Up to this much random delay is added to each poll interval.
+    pub health_poll_jitter: Duration,
+    /// Identifies this replica when sharing health checks; defaults to the
+    /// container hostname.
+    pub replica_id: String,
+    pub cache_backend: CacheBackendKind,
+    /// Eviction policy of the in-memory backend; a Redis server uses its own
+    /// `maxmemory-policy`. Volatile by default so entries stored without a
+    /// TTL are never evicted.
+    pub cache_policy: CachePolicy,
+    /// How often the in-memory backend actively removes expired entries.
+    pub cache_sweep_interval: Duration,
+    /// File the in-memory backend is loaded from at startup and saved to;
+    /// unset keeps no snapshots.
+    pub cache_snapshot_path: Option<PathBuf>,
+    /// How often the snapshot is saved besides on shutdown; zero saves it
+    /// only on shutdown.
+    pub cache_snapshot_interval: Duration,
+    pub redis: RedisSettings,
+    /// In-process L1 kept in front of Redis; a zero `l1_ttl` turns it off.
+    pub cache_tiers: TierSettings,
 }
 
 impl Config {
     pub fn new() -> Self {
         let server_addr = SocketAddr::from(([0, 0, 0, 0], 9999));
-        
+
+        let intake_mode = match std::env::var("PAYMENT_INTAKE_MODE").as_deref() {
+            Ok("async") => IntakeMode::Async,
+            _ => IntakeMode::Sync,
+        };
+        let queue_full_policy = match std::env::var("PAYMENT_QUEUE_FULL_POLICY").as_deref() {
+            Ok("inline") => QueueFullPolicy::ProcessInline,
+            _ => QueueFullPolicy::Reject,
+        };
+        let cache_backend = match std::env::var("CACHE_BACKEND").as_deref() {
+            Ok("redis") => CacheBackendKind::Redis,
+            _ => CacheBackendKind::Memory,
+        };
+
+        let replica_id = std::env::var("REPLICA_ID")
+            .or_else(|_| std::env::var("HOSTNAME"))
+            .unwrap_or_else(|_| uuid::Uuid::new_v4().to_string());
+
         Config {
             server_addr,
+            metrics_addr: std::env::var("METRICS_ADDR").ok().and_then(|addr| addr.parse().ok()),
             log_level: std::env::var("RUST_LOG").unwrap_or_else(|_| "info".to_string()),
+            intake_mode,
+            queue_capacity: env_or("PAYMENT_QUEUE_CAPACITY", 10_000),
+            queue_workers: env_or("PAYMENT_QUEUE_WORKERS", 4),
+            queue_full_policy,
+            queue_requeue_base_backoff: Duration::from_millis(env_or(
+                "PAYMENT_QUEUE_REQUEUE_BASE_BACKOFF_MS",
+                DEFAULT_REQUEUE_BASE_BACKOFF.as_millis() as u64,
+            )),
+            queue_requeue_max_backoff: Duration::from_millis(env_or(
+                "PAYMENT_QUEUE_REQUEUE_MAX_BACKOFF_MS",
+                DEFAULT_REQUEUE_MAX_BACKOFF.as_millis() as u64,
+            )),
+            queue_max_requeues: env_or("PAYMENT_QUEUE_MAX_REQUEUES", DEFAULT_MAX_REQUEUES),
+            retry_policy: retry_policy_from_env(),
+            health_poll_interval: Duration::from_millis(env_or("HEALTH_POLL_INTERVAL_MS", 5_000)),
+            health_poll_jitter: Duration::from_millis(env_or("HEALTH_POLL_JITTER_MS", 0)),
+            replica_id: replica_id.clone(),
+            cache_backend,
+            cache_policy: env_or("CACHE_EVICTION_POLICY", CachePolicy::VolatileLRU),
+            cache_sweep_interval: Duration::from_millis(env_or("CACHE_SWEEP_INTERVAL_MS", 100)),
+            cache_snapshot_path: std::env::var("CACHE_SNAPSHOT_PATH").ok().filter(|path| !path.is_empty()).map(PathBuf::from),
+            cache_snapshot_interval: Duration::from_millis(env_or("CACHE_SNAPSHOT_INTERVAL_MS", 60_000)),
+            redis: redis_settings_from_env(),
+            cache_tiers: tier_settings_from_env(&replica_id),
         }
     }
 
@@ -37,4 +149,73 @@ impl Default for Config {
     fn default() -> Self {
         Self::new()
     }
-} 
\ No newline at end of file
+}
+
+fn env_or<T: std::str::FromStr>(name: &str, default: T) -> T {
+    std::env::var(name)
+        .ok()
+        .and_then(|value| value.parse().ok())
+        .unwrap_or(default)
+}
+
+/// Reads the processor retry policy from `PAYMENT_RETRY_*`, falling back to
+/// `RetryPolicy::new()` for anything unset. `PAYMENT_RETRY_ON` is a
+/// comma-separated list of `PaymentError` kinds.
+fn retry_policy_from_env() -> RetryPolicy {
+    let defaults = RetryPolicy::new();
+    let retryable_kinds = match std::env::var("PAYMENT_RETRY_ON") {
+        Ok(kinds) => kinds
+            .split(',')
+            .map(|kind| kind.trim().to_string())
+            .filter(|kind| !kind.is_empty())
+            .collect(),
+        Err(_) => defaults.retryable_kinds,
+    };
+
+    RetryPolicy {
+        max_attempts: env_or("PAYMENT_RETRY_MAX_ATTEMPTS", defaults.max_attempts).max(1),
+        base_backoff: Duration::from_millis(env_or("PAYMENT_RETRY_BASE_BACKOFF_MS", defaults.base_backoff.as_millis() as u64)),
+        max_backoff: Duration::from_millis(env_or("PAYMENT_RETRY_MAX_BACKOFF_MS", defaults.max_backoff.as_millis() as u64)),
+        jitter: env_or("PAYMENT_RETRY_JITTER", defaults.jitter),
+        budget_percent: env_or("PAYMENT_RETRY_BUDGET_PERCENT", defaults.budget_percent),
+        budget_window: Duration::from_millis(env_or("PAYMENT_RETRY_BUDGET_WINDOW_MS", defaults.budget_window.as_millis() as u64)),
+        retryable_kinds,
+    }
+}
+
+/// Reads the Redis connection settings from `REDIS_*`, falling back to
+/// `RedisSettings::default()` for anything unset.
+fn redis_settings_from_env() -> RedisSettings {
+    let defaults = RedisSettings::default();
+    RedisSettings {
+        url: std::env::var("REDIS_URL").unwrap_or(defaults.url),
+        pool_size: env_or("REDIS_POOL_SIZE", defaults.pool_size).max(1),
+        connect_timeout: Duration::from_millis(env_or("REDIS_CONNECT_TIMEOUT_MS", defaults.connect_timeout.as_millis() as u64)),
+        command_timeout: Duration::from_millis(env_or("REDIS_TIMEOUT_MS", defaults.command_timeout.as_millis() as u64)),
+        key_prefix: std::env::var("REDIS_KEY_PREFIX").unwrap_or(defaults.key_prefix),
+        memory_limit_mb: env_or("CACHE_MEMORY_LIMIT_MB", defaults.memory_limit_mb),
+    }
+}
+
+/// Reads the L1 settings from `CACHE_L1_*`, falling back to
+/// `TierSettings::default()` for anything unset. `CACHE_L1_KEY_TTLS` is a
+/// comma-separated list of `prefix=milliseconds`.
+fn tier_settings_from_env(replica_id: &str) -> TierSettings {
+    let defaults = TierSettings::default();
+    let l1_key_ttls = match std::env::var("CACHE_L1_KEY_TTLS") {
+        Ok(ttls) => ttls
+            .split(',')
+            .filter_map(|entry| entry.trim().rsplit_once('='))
+            .filter_map(|(prefix, millis)| Some((prefix.to_string(), Duration::from_millis(millis.parse().ok()?))))
+            .collect(),
+        Err(_) => defaults.l1_key_ttls,
+    };
+
+    TierSettings {
+        l1_memory_limit_mb: env_or("CACHE_L1_MEMORY_LIMIT_MB", defaults.l1_memory_limit_mb),
+        l1_ttl: Duration::from_millis(env_or("CACHE_L1_TTL_MS", defaults.l1_ttl.as_millis() as u64)),
+        l1_key_ttls,
+        channel: std::env::var("CACHE_INVALIDATION_CHANNEL").unwrap_or(defaults.channel),
+        replica_id: replica_id.to_string(),
+    }
+}
@@ -0,0 +1,241 @@
+use axum::{
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Json,
+};
+use serde::{Deserialize, Serialize};
+
+pub type PaymentResult<T> = Result<T, PaymentError>;
+
+/// Every way a payment (or one of the services behind it) can fail.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PaymentError {
+    /// The request itself is invalid; retrying it unchanged will not help.
+    Validation(String),
+    /// The request body is in a format the service does not accept.
+    UnsupportedMediaType(String),
+    /// A processor did not answer within the request timeout.
+    Timeout { processor: String },
+    /// A processor could not be reached at all.
+    ConnectionRefused { processor: String, reason: String },
+    /// A processor answered with a 5xx status.
+    ProcessorServerError { processor: String, status: u16 },
+    /// A processor rejected the payment with a 4xx status.
+    ProcessorClientError { processor: String, status: u16 },
+    /// The same correlationId is already being dispatched.
+    Duplicate { correlation_id: String },
+    /// Every configured processor failed or none is available.
+    AllProcessorsDown,
+    /// The intake queue is full and the payment was not accepted.
+    QueueFull,
+    /// The intake queue's workers have shut down.
+    QueueClosed,
+    /// A body or cached value could not be encoded or decoded.
+    Serialization(String),
+    /// The cache backend could not be reached or answered with an error.
+    Cache(String),
+    /// The cache refused a write because it could not fit under its memory limit.
+    CacheRejected(CacheRejection),
+}
+
+/// Why the cache refused to store an entry.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CacheRejection {
+    /// The entry alone is bigger than the whole memory limit.
+    EntryTooLarge { entry_bytes: u64, limit_bytes: u64 },
+    /// The eviction policy cannot free enough memory, e.g. a volatile
+    /// policy when most entries have no TTL.
+    NotEnoughEvictable { needed_bytes: u64, evictable_bytes: u64 },
+}
+
+impl std::fmt::Display for CacheRejection {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CacheRejection::EntryTooLarge { entry_bytes, limit_bytes } => {
+                write!(f, "entry of {} bytes exceeds the {} byte limit", entry_bytes, limit_bytes)
+            }
+            CacheRejection::NotEnoughEvictable { needed_bytes, evictable_bytes } => {
+                write!(f, "{} bytes must be freed but only {} are evictable", needed_bytes, evictable_bytes)
+            }
+        }
+    }
+}
+
+/// Body returned to clients for a failed request, in the negotiated format.
+#[derive(Debug, Serialize)]
+pub struct ErrorBody {
+    pub error: &'static str,
+    pub message: String,
+    pub retryable: bool,
+}
+
+impl PaymentError {
+    /// Maps a transport error from talking to `processor`.
+    pub fn from_reqwest(processor: &str, error: reqwest::Error) -> Self {
+        if error.is_timeout() {
+            PaymentError::Timeout {
+                processor: processor.to_string(),
+            }
+        } else if error.is_decode() {
+            PaymentError::Serialization(error.to_string())
+        } else {
+            PaymentError::ConnectionRefused {
+                processor: processor.to_string(),
+                reason: error.to_string(),
+            }
+        }
+    }
+
+    /// Maps a non-success processor status.
+    pub fn from_status(processor: &str, status: reqwest::StatusCode) -> Self {
+        if status.is_client_error() {
+            PaymentError::ProcessorClientError {
+                processor: processor.to_string(),
+                status: status.as_u16(),
+            }
+        } else {
+            PaymentError::ProcessorServerError {
+                processor: processor.to_string(),
+                status: status.as_u16(),
+            }
+        }
+    }
+
+    /// Stable machine-readable name used in error bodies and logs.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            PaymentError::Validation(_) => "validation",
+            PaymentError::UnsupportedMediaType(_) => "unsupported_media_type",
+            PaymentError::Timeout { .. } => "timeout",
+            PaymentError::ConnectionRefused { .. } => "connection_refused",
+            PaymentError::ProcessorServerError { .. } => "processor_server_error",
+            PaymentError::ProcessorClientError { .. } => "processor_client_error",
+            PaymentError::Duplicate { .. } => "duplicate",
+            PaymentError::AllProcessorsDown => "all_processors_down",
+            PaymentError::QueueFull => "queue_full",
+            PaymentError::QueueClosed => "queue_closed",
+            PaymentError::Serialization(_) => "serialization",
+            PaymentError::Cache(_) => "cache",
+            PaymentError::CacheRejected(_) => "cache_rejected",
+        }
+    }
+
+    /// Whether sending the same payment again may succeed. Rate limiting
+    /// (429) is the only 4xx worth retrying.
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            PaymentError::Timeout { .. }
+            | PaymentError::ConnectionRefused { .. }
+            | PaymentError::ProcessorServerError { .. }
+            | PaymentError::AllProcessorsDown
+            | PaymentError::QueueFull
+            | PaymentError::QueueClosed
+            | PaymentError::Cache(_) => true,
+            PaymentError::ProcessorClientError { status, .. } => *status == 429,
+            PaymentError::Validation(_)
+            | PaymentError::UnsupportedMediaType(_)
+            | PaymentError::Duplicate { .. }
+            | PaymentError::Serialization(_)
+            | PaymentError::CacheRejected(_) => false,
+        }
+    }
+
+    /// Processor name the error came from, if any.
+    pub fn processor(&self) -> Option<&str> {
+        match self {
+            PaymentError::Timeout { processor }
+            | PaymentError::ConnectionRefused { processor, .. }
+            | PaymentError::ProcessorServerError { processor, .. }
+            | PaymentError::ProcessorClientError { processor, .. } => Some(processor),
+            _ => None,
+        }
+    }
+
+    pub fn status_code(&self) -> StatusCode {
+        match self {
+            PaymentError::Validation(_) => StatusCode::BAD_REQUEST,
+            PaymentError::UnsupportedMediaType(_) => StatusCode::UNSUPPORTED_MEDIA_TYPE,
+            PaymentError::Duplicate { .. } => StatusCode::CONFLICT,
+            PaymentError::Timeout { .. } => StatusCode::GATEWAY_TIMEOUT,
+            PaymentError::ConnectionRefused { .. }
+            | PaymentError::ProcessorServerError { .. }
+            | PaymentError::ProcessorClientError { .. } => StatusCode::BAD_GATEWAY,
+            PaymentError::AllProcessorsDown
+            | PaymentError::QueueFull
+            | PaymentError::QueueClosed
+            | PaymentError::Cache(_) => StatusCode::SERVICE_UNAVAILABLE,
+            PaymentError::Serialization(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            PaymentError::CacheRejected(_) => StatusCode::INSUFFICIENT_STORAGE,
+        }
+    }
+
+    pub fn to_body(&self) -> ErrorBody {
+        ErrorBody {
+            error: self.kind(),
+            message: self.to_string(),
+            retryable: self.is_retryable(),
+        }
+    }
+}
+
+impl std::fmt::Display for PaymentError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PaymentError::Validation(reason) => write!(f, "invalid payment: {}", reason),
+            PaymentError::UnsupportedMediaType(content_type) => {
+                write!(f, "unsupported content type: {}", content_type)
+            }
+            PaymentError::Timeout { processor } => write!(f, "processor {} timed out", processor),
+            PaymentError::ConnectionRefused { processor, reason } => {
+                write!(f, "could not reach processor {}: {}", processor, reason)
+            }
+            PaymentError::ProcessorServerError { processor, status } => {
+                write!(f, "processor {} failed with HTTP {}", processor, status)
+            }
+            PaymentError::ProcessorClientError { processor, status } => {
+                write!(f, "processor {} rejected the payment with HTTP {}", processor, status)
+            }
+            PaymentError::Duplicate { correlation_id } => {
+                write!(f, "payment {} is already in flight", correlation_id)
+            }
+            PaymentError::AllProcessorsDown => write!(f, "no payment processor is available"),
+            PaymentError::QueueFull => write!(f, "payment queue is full"),
+            PaymentError::QueueClosed => write!(f, "payment queue is shut down"),
+            PaymentError::Serialization(reason) => write!(f, "serialization failed: {}", reason),
+            PaymentError::Cache(reason) => write!(f, "cache unavailable: {}", reason),
+            PaymentError::CacheRejected(reason) => write!(f, "cache rejected the entry: {}", reason),
+        }
+    }
+}
+
+impl std::error::Error for PaymentError {}
+
+impl From<serde_json::Error> for PaymentError {
+    fn from(error: serde_json::Error) -> Self {
+        PaymentError::Serialization(error.to_string())
+    }
+}
+
+impl From<rmp_serde::encode::Error> for PaymentError {
+    fn from(error: rmp_serde::encode::Error) -> Self {
+        PaymentError::Serialization(error.to_string())
+    }
+}
+
+impl From<rmp_serde::decode::Error> for PaymentError {
+    fn from(error: rmp_serde::decode::Error) -> Self {
+        PaymentError::Serialization(error.to_string())
+    }
+}
+
+impl From<validator::ValidationErrors> for PaymentError {
+    fn from(errors: validator::ValidationErrors) -> Self {
+        PaymentError::Validation(errors.to_string())
+    }
+}
+
+impl IntoResponse for PaymentError {
+    fn into_response(self) -> Response {
+        (self.status_code(), Json(self.to_body())).into_response()
+    }
+}
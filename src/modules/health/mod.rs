@@ -1,18 +1,62 @@
 pub mod service;
-use service::HealthCheckService;
+pub mod poller;
+use std::sync::Arc;
+use std::time::Duration;
+use service::{HealthCheckService, SharedHealthSnapshot};
+use poller::HealthPoller;
+use crate::modules::error::PaymentError;
 
 pub struct HealthManager {
-    health_service: HealthCheckService,
+    health_service: Arc<HealthCheckService>,
+    poller: HealthPoller,
+}
+
+impl Default for HealthManager {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl HealthManager {
     pub fn new() -> Self {
+        Self::with_health_service(HealthCheckService::new())
+    }
+
+    /// Takes a fully configured service, e.g. with its clock set or shared
+    /// through a cache, as it cannot be changed once the manager holds it.
+    pub fn with_health_service(health_service: HealthCheckService) -> Self {
         Self {
-            health_service: HealthCheckService::new(),
+            health_service: Arc::new(health_service),
+            poller: HealthPoller::default(),
         }
     }
 
-    pub async fn check_processor_health(&self, processor_name: &str) -> Result<service::HealthStatus, Box<dyn std::error::Error + Send + Sync>> {
+    /// Replaces the poller settings. Has no effect on a poller that is
+    /// already running until it is restarted.
+    pub fn set_poll_interval(&mut self, interval: Duration, jitter: Duration) {
+        self.poller.stop();
+        self.poller = HealthPoller::new(interval, jitter);
+    }
+
+    /// Starts checking every processor in the background. Returns false if
+    /// the poller is already running.
+    pub fn start_poller(&self) -> bool {
+        self.poller.start(Arc::clone(&self.health_service))
+    }
+
+    pub fn stop_poller(&self) -> bool {
+        self.poller.stop()
+    }
+
+    pub fn is_poller_running(&self) -> bool {
+        self.poller.is_running()
+    }
+
+    pub fn subscribe(&self) -> tokio::sync::watch::Receiver<SharedHealthSnapshot> {
+        self.health_service.subscribe()
+    }
+
+    pub async fn check_processor_health(&self, processor_name: &str) -> Result<service::HealthStatus, PaymentError> {
         self.health_service.check_processor_health(processor_name).await
     }
 
@@ -24,7 +68,7 @@ impl HealthManager {
         self.health_service.get_health_endpoint_url(processor_name).await
     }
 
-    pub async fn check_all_processors_health(&self, processor_names: &[String]) -> std::collections::HashMap<String, Result<service::HealthStatus, Box<dyn std::error::Error + Send + Sync>>> {
+    pub async fn check_all_processors_health(&self, processor_names: &[String]) -> std::collections::HashMap<String, Result<service::HealthStatus, PaymentError>> {
         self.health_service.check_all_processors_health(processor_names).await
     }
 
@@ -36,4 +80,4 @@ impl HealthManager {
     pub fn get_cache_ttl(&self) -> std::time::Duration {
         self.health_service.get_cache_ttl()
     }
-} 
\ No newline at end of file
+}
@@ -0,0 +1,96 @@
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use rand::Rng;
+use tokio::task::JoinHandle;
+use super::service::HealthCheckService;
+
+/// Background task that refreshes every configured processor's health on a
+/// fixed interval. Results reach the selector through the service's watch
+/// channel, so the request path never waits on a health check.
+pub struct HealthPoller {
+    interval: Duration,
+    jitter: Duration,
+    task: Mutex<Option<JoinHandle<()>>>,
+}
+
+impl Default for HealthPoller {
+    fn default() -> Self {
+        Self::new(Duration::from_secs(5), Duration::ZERO)
+    }
+}
+
+impl HealthPoller {
+    /// Each round waits `interval` plus a random delay of up to `jitter`.
+    pub fn new(interval: Duration, jitter: Duration) -> Self {
+        Self {
+            interval,
+            jitter,
+            task: Mutex::new(None),
+        }
+    }
+
+    pub fn get_interval(&self) -> Duration {
+        self.interval
+    }
+
+    pub fn get_jitter(&self) -> Duration {
+        self.jitter
+    }
+
+    /// Spawns the polling task on the current runtime. Returns false if it
+    /// is already running.
+    pub fn start(&self, service: Arc<HealthCheckService>) -> bool {
+        let Ok(mut task) = self.task.lock() else {
+            return false;
+        };
+        if task.as_ref().is_some_and(|handle| !handle.is_finished()) {
+            return false;
+        }
+
+        let interval = self.interval;
+        let jitter = self.jitter;
+        *task = Some(tokio::spawn(async move {
+            loop {
+                for processor_name in service.get_processor_names().await {
+                    if let Err(e) = service.refresh_processor_health(&processor_name).await {
+                        log::warn!("Health check for {} failed: {}", processor_name, e);
+                    }
+                }
+                tokio::time::sleep(interval + random_jitter(jitter)).await;
+            }
+        }));
+        true
+    }
+
+    /// Stops the polling task. Returns false if it was not running.
+    pub fn stop(&self) -> bool {
+        let handle = self.task.lock().ok().and_then(|mut task| task.take());
+        match handle {
+            Some(handle) => {
+                handle.abort();
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.task
+            .lock()
+            .map(|task| task.as_ref().is_some_and(|handle| !handle.is_finished()))
+            .unwrap_or(false)
+    }
+}
+
+impl Drop for HealthPoller {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+fn random_jitter(max: Duration) -> Duration {
+    if max.is_zero() {
+        return Duration::ZERO;
+    }
+    Duration::from_millis(rand::thread_rng().gen_range(0..=max.as_millis() as u64))
+}
@@ -1,9 +1,12 @@
 use std::collections::HashMap;
 use std::sync::Arc;
-use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
-use tokio::sync::RwLock;
-use reqwest::Client;
+use std::time::Duration;
+use tokio::sync::{watch, RwLock};
+use reqwest::{Client, StatusCode};
 use serde::{Serialize, Deserialize};
+use crate::modules::cache::CacheManager;
+use crate::modules::clock::{system_clock, SharedClock};
+use crate::modules::error::PaymentError;
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 pub struct HealthStatus {
@@ -11,16 +14,78 @@ pub struct HealthStatus {
     pub last_check: Option<u64>,
     pub response_time_ms: Option<u64>,
     pub error_message: Option<String>,
+    /// `failing` as reported by the processor's service-health body.
+    #[serde(default)]
+    pub failing: bool,
+    /// `minResponseTime` as reported by the processor's service-health body.
+    #[serde(default)]
+    pub min_response_time_ms: Option<u64>,
 }
 
+/// Body of `GET /payments/service-health`.
+#[derive(Debug, Deserialize)]
+struct ServiceHealthBody {
+    failing: bool,
+    #[serde(rename = "minResponseTime")]
+    min_response_time: u64,
+}
+
+/// Result of asking a processor for its health.
+enum CheckOutcome {
+    Checked(HealthStatus),
+    /// The processor answered 429; it may say how long to wait.
+    RateLimited { retry_after: Option<Duration> },
+}
+
+/// What routing needs to know about a processor's health.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ProcessorHealth {
+    pub failing: bool,
+    pub min_response_time_ms: u64,
+}
+
+impl From<&HealthStatus> for ProcessorHealth {
+    /// Only a processor that says it is failing counts as failing; one whose
+    /// health check could not be completed is left to the circuit breaker.
+    fn from(status: &HealthStatus) -> Self {
+        Self {
+            failing: status.failing,
+            min_response_time_ms: status.min_response_time_ms.unwrap_or(0),
+        }
+    }
+}
+
+/// Latest health per processor name.
+pub type HealthSnapshot = HashMap<String, ProcessorHealth>;
+
+/// What subscribers receive: a snapshot that is never mutated once
+/// published, so readers can hold on to it without blocking the publisher.
+pub type SharedHealthSnapshot = Arc<HealthSnapshot>;
+
 #[allow(dead_code)]
 pub struct HealthCheckService {
     client: Client,
     rate_limit: Duration,
     cache_ttl: Duration,
-    last_checks: Arc<RwLock<HashMap<String, Instant>>>,
-    health_cache: Arc<RwLock<HashMap<String, (HealthStatus, Instant)>>>,
+    /// Epoch millis of the last check per processor
+    last_checks: Arc<RwLock<HashMap<String, u64>>>,
+    /// Epoch millis before which a processor that answered 429 is not checked
+    backoff_until: Arc<RwLock<HashMap<String, u64>>>,
+    health_cache: Arc<RwLock<HashMap<String, (HealthStatus, u64)>>>,
     processor_urls: Arc<RwLock<HashMap<String, String>>>,
+    clock: SharedClock,
+    updates: watch::Sender<SharedHealthSnapshot>,
+    /// Cache shared with other replicas, if health results are shared.
+    shared_cache: Option<Arc<CacheManager>>,
+    /// Identifies this replica as a lease holder.
+    replica_id: String,
+    lease_ttl: Duration,
+}
+
+impl Default for HealthCheckService {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl HealthCheckService {
@@ -34,13 +99,23 @@ impl HealthCheckService {
         processor_urls.insert("default".to_string(), default_url);
         processor_urls.insert("fallback".to_string(), fallback_url);
         
+        Self::with_processor_urls(processor_urls)
+    }
+
+    pub fn with_processor_urls(processor_urls: HashMap<String, String>) -> Self {
         Self {
             client: Client::new(),
             rate_limit: Duration::from_secs(5),
             cache_ttl: Duration::from_secs(300),
             last_checks: Arc::new(RwLock::new(HashMap::new())),
+            backoff_until: Arc::new(RwLock::new(HashMap::new())),
             health_cache: Arc::new(RwLock::new(HashMap::new())),
             processor_urls: Arc::new(RwLock::new(processor_urls)),
+            clock: system_clock(),
+            updates: watch::channel(SharedHealthSnapshot::default()).0,
+            shared_cache: None,
+            replica_id: String::new(),
+            lease_ttl: Duration::from_secs(10),
         }
     }
 
@@ -60,12 +135,50 @@ impl HealthCheckService {
         self.cache_ttl = cache_ttl;
     }
 
-    pub async fn check_processor_health(&self, processor_name: &str) -> Result<HealthStatus, Box<dyn std::error::Error + Send + Sync>> {
+    pub fn set_clock(&mut self, clock: SharedClock) {
+        self.clock = clock;
+    }
+
+    /// Shares health results with other replicas through `cache`. For each
+    /// processor, only the replica holding its lease calls the processor;
+    /// the others read the status it stored. A lease that is not renewed
+    /// within the lease TTL can be taken over by another replica.
+    pub fn share_through(&mut self, cache: Arc<CacheManager>, replica_id: impl Into<String>) {
+        self.shared_cache = Some(cache);
+        self.replica_id = replica_id.into();
+    }
+
+    pub fn set_lease_ttl(&mut self, lease_ttl: Duration) {
+        self.lease_ttl = lease_ttl;
+    }
+
+    pub fn get_lease_ttl(&self) -> Duration {
+        self.lease_ttl
+    }
+
+    /// Receives a new snapshot every time a health check completes.
+    pub fn subscribe(&self) -> watch::Receiver<SharedHealthSnapshot> {
+        self.updates.subscribe()
+    }
+
+    pub async fn check_processor_health(&self, processor_name: &str) -> Result<HealthStatus, PaymentError> {
         // Check if we have a cached result that's still valid
         if let Some(cached_status) = self.get_cached_health_status(processor_name).await {
             return Ok(cached_status);
         }
 
+        self.refresh_processor_health(processor_name).await
+    }
+
+    /// Checks a processor without consulting the result cache, still
+    /// honouring the rate limit and any 429 back-off.
+    pub async fn refresh_processor_health(&self, processor_name: &str) -> Result<HealthStatus, PaymentError> {
+        if let Some(cache) = &self.shared_cache {
+            if !self.hold_lease(cache, processor_name).await {
+                return self.read_shared_status(cache, processor_name).await;
+            }
+        }
+
         // Check rate limiting
         if !self.can_perform_health_check(processor_name).await {
             // Return last known status or default unhealthy status
@@ -73,10 +186,19 @@ impl HealthCheckService {
         }
 
         // Perform actual health check
-        let health_status = self.perform_health_check(processor_name).await?;
+        let health_status = match self.perform_health_check(processor_name).await? {
+            CheckOutcome::Checked(status) => status,
+            CheckOutcome::RateLimited { retry_after } => {
+                // Being throttled says nothing about the processor's health
+                self.back_off(processor_name, retry_after).await;
+                return self.get_last_known_status(processor_name).await;
+            }
+        };
         
         // Cache the result
         self.cache_health_status(processor_name, &health_status).await;
+        self.publish(processor_name, &health_status);
+        self.write_shared_status(processor_name, &health_status).await;
         
         // Update last check time
         self.update_last_check_time(processor_name).await;
@@ -90,9 +212,7 @@ impl HealthCheckService {
 
     pub async fn get_last_check_time(&self, processor_name: &str) -> Option<u64> {
         let last_checks = self.last_checks.read().await;
-        last_checks.get(processor_name).map(|_instant| {
-            SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs()
-        })
+        last_checks.get(processor_name).map(|millis| millis / 1000)
     }
 
     pub async fn get_health_endpoint_url(&self, processor_name: &str) -> String {
@@ -103,7 +223,14 @@ impl HealthCheckService {
         format!("{}/payments/service-health", base_url)
     }
 
-    pub async fn check_all_processors_health(&self, processor_names: &[String]) -> HashMap<String, Result<HealthStatus, Box<dyn std::error::Error + Send + Sync>>> {
+    pub async fn get_processor_names(&self) -> Vec<String> {
+        let processor_urls = self.processor_urls.read().await;
+        let mut names: Vec<String> = processor_urls.keys().cloned().collect();
+        names.sort();
+        names
+    }
+
+    pub async fn check_all_processors_health(&self, processor_names: &[String]) -> HashMap<String, Result<HealthStatus, PaymentError>> {
         let mut results = HashMap::new();
         
         for processor_name in processor_names {
@@ -115,9 +242,15 @@ impl HealthCheckService {
     }
 
     async fn can_perform_health_check(&self, processor_name: &str) -> bool {
+        let backoff_until = self.backoff_until.read().await;
+        if backoff_until.get(processor_name).is_some_and(|until| self.clock.now_millis() < *until) {
+            return false;
+        }
+        drop(backoff_until);
+
         let last_checks = self.last_checks.read().await;
         if let Some(last_check) = last_checks.get(processor_name) {
-            return last_check.elapsed() >= self.rate_limit;
+            return self.elapsed_since(*last_check) >= self.rate_limit;
         }
         true
     }
@@ -125,14 +258,14 @@ impl HealthCheckService {
     async fn get_cached_health_status(&self, processor_name: &str) -> Option<HealthStatus> {
         let health_cache = self.health_cache.read().await;
         if let Some((status, timestamp)) = health_cache.get(processor_name) {
-            if timestamp.elapsed() < self.cache_ttl {
+            if self.elapsed_since(*timestamp) < self.cache_ttl {
                 return Some(status.clone());
             }
         }
         None
     }
 
-    async fn get_last_known_status(&self, processor_name: &str) -> Result<HealthStatus, Box<dyn std::error::Error + Send + Sync>> {
+    async fn get_last_known_status(&self, processor_name: &str) -> Result<HealthStatus, PaymentError> {
         let health_cache = self.health_cache.read().await;
         if let Some((status, _)) = health_cache.get(processor_name) {
             return Ok(status.clone());
@@ -144,12 +277,14 @@ impl HealthCheckService {
             last_check: None,
             response_time_ms: None,
             error_message: Some("No health data available".to_string()),
+            failing: false,
+            min_response_time_ms: None,
         })
     }
 
-    async fn perform_health_check(&self, processor_name: &str) -> Result<HealthStatus, Box<dyn std::error::Error + Send + Sync>> {
+    async fn perform_health_check(&self, processor_name: &str) -> Result<CheckOutcome, PaymentError> {
         let url = self.get_health_endpoint_url(processor_name).await;
-        let start_time = Instant::now();
+        let start_time = self.clock.now_millis();
         
         match self.client
             .get(&url)
@@ -157,36 +292,130 @@ impl HealthCheckService {
             .send()
             .await
         {
+            Ok(response) if response.status() == StatusCode::TOO_MANY_REQUESTS => {
+                let retry_after = response
+                    .headers()
+                    .get(reqwest::header::RETRY_AFTER)
+                    .and_then(|value| value.to_str().ok())
+                    .and_then(|value| value.trim().parse::<u64>().ok())
+                    .map(Duration::from_secs);
+                Ok(CheckOutcome::RateLimited { retry_after })
+            }
             Ok(response) => {
-                let response_time = start_time.elapsed().as_millis() as u64;
-                let is_healthy = response.status().is_success();
+                let response_time = self.clock.now_millis().saturating_sub(start_time);
+                let status = response.status();
+                let body = if status.is_success() {
+                    match response.json::<ServiceHealthBody>().await {
+                        Ok(body) => Some(body),
+                        Err(e) => {
+                            log::warn!("Unreadable health body from {}: {}", processor_name, e);
+                            None
+                        }
+                    }
+                } else {
+                    None
+                };
+                let failing = body.as_ref().is_some_and(|body| body.failing);
+                let is_healthy = status.is_success() && !failing;
                 
-                Ok(HealthStatus {
+                Ok(CheckOutcome::Checked(HealthStatus {
                     is_healthy,
-                    last_check: Some(SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs()),
+                    last_check: Some(self.clock.now_secs()),
                     response_time_ms: Some(response_time),
-                    error_message: if is_healthy { None } else { Some(format!("HTTP {}", response.status())) },
-                })
+                    error_message: if !status.is_success() {
+                        Some(format!("HTTP {}", status))
+                    } else if failing {
+                        Some("processor reports failing".to_string())
+                    } else {
+                        None
+                    },
+                    failing,
+                    min_response_time_ms: body.map(|body| body.min_response_time),
+                }))
             }
             Err(e) => {
-                let response_time = start_time.elapsed().as_millis() as u64;
-                Ok(HealthStatus {
+                let response_time = self.clock.now_millis().saturating_sub(start_time);
+                Ok(CheckOutcome::Checked(HealthStatus {
                     is_healthy: false,
-                    last_check: Some(SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs()),
+                    last_check: Some(self.clock.now_secs()),
                     response_time_ms: Some(response_time),
                     error_message: Some(e.to_string()),
-                })
+                    failing: false,
+                    min_response_time_ms: None,
+                }))
+            }
+        }
+    }
+
+    /// Holds off the next check for the `Retry-After` the processor asked
+    /// for, or one rate-limit interval if it did not say.
+    async fn back_off(&self, processor_name: &str, retry_after: Option<Duration>) {
+        let delay = retry_after.unwrap_or(self.rate_limit).max(self.rate_limit);
+        let mut backoff_until = self.backoff_until.write().await;
+        backoff_until.insert(processor_name.to_string(), self.clock.now_millis() + delay.as_millis() as u64);
+    }
+
+    /// Takes or renews this replica's lease on a processor. Cache errors
+    /// count as holding it, so a broken cache means more checks rather
+    /// than none.
+    async fn hold_lease(&self, cache: &CacheManager, processor_name: &str) -> bool {
+        let key = format!("health:lease:{}", processor_name);
+        match cache.acquire_lease(&key, &self.replica_id, self.lease_ttl).await {
+            Ok(held) => held,
+            Err(e) => {
+                log::warn!("Health lease for {} unavailable: {}", processor_name, e);
+                true
+            }
+        }
+    }
+
+    async fn read_shared_status(&self, cache: &CacheManager, processor_name: &str) -> Result<HealthStatus, PaymentError> {
+        let key = format!("health:status:{}", processor_name);
+        match cache.get::<HealthStatus>(&key).await {
+            Ok(Some(status)) => {
+                self.cache_health_status(processor_name, &status).await;
+                self.publish(processor_name, &status);
+                Ok(status)
+            }
+            Ok(None) => self.get_last_known_status(processor_name).await,
+            Err(e) => {
+                log::warn!("Shared health status for {} unreadable: {}", processor_name, e);
+                self.get_last_known_status(processor_name).await
             }
         }
     }
 
+    async fn write_shared_status(&self, processor_name: &str, status: &HealthStatus) {
+        let Some(cache) = &self.shared_cache else {
+            return;
+        };
+        let key = format!("health:status:{}", processor_name);
+        // Outlives the lease so readers keep a status through a takeover
+        if let Err(e) = cache.set(&key, status, self.lease_ttl * 2).await {
+            log::warn!("Failed to share health status for {}: {}", processor_name, e);
+        }
+    }
+
     async fn cache_health_status(&self, processor_name: &str, status: &HealthStatus) {
         let mut health_cache = self.health_cache.write().await;
-        health_cache.insert(processor_name.to_string(), (status.clone(), Instant::now()));
+        health_cache.insert(processor_name.to_string(), (status.clone(), self.clock.now_millis()));
+    }
+
+    fn publish(&self, processor_name: &str, status: &HealthStatus) {
+        // Copy-on-write: subscribers still reading the old snapshot keep it
+        self.updates.send_modify(|snapshot| {
+            let mut next = HealthSnapshot::clone(snapshot);
+            next.insert(processor_name.to_string(), ProcessorHealth::from(status));
+            *snapshot = Arc::new(next);
+        });
     }
 
     async fn update_last_check_time(&self, processor_name: &str) {
         let mut last_checks = self.last_checks.write().await;
-        last_checks.insert(processor_name.to_string(), Instant::now());
+        last_checks.insert(processor_name.to_string(), self.clock.now_millis());
+    }
+
+    fn elapsed_since(&self, millis: u64) -> Duration {
+        Duration::from_millis(self.clock.now_millis().saturating_sub(millis))
     }
 } 
\ No newline at end of file
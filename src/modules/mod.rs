@@ -4,31 +4,107 @@ pub mod payment;
 pub mod processors;
 pub mod health;
 pub mod cache;
+pub mod summary;
+pub mod queue;
+pub mod clock;
+pub mod error;
 
+use std::sync::Arc;
 use processors::PaymentProcessor;
 use health::HealthManager;
+use health::service::HealthCheckService;
+use cache::redis::CachePolicy;
 use cache::CacheManager;
+use config::{CacheBackendKind, Config};
+use queue::PaymentQueue;
+
+/// Services shared by every request handler through axum `State`.
+pub type SharedServices = Arc<ApplicationServices>;
 
 pub struct ApplicationServices {
-    pub payment_processor: PaymentProcessor,
+    pub payment_processor: Arc<PaymentProcessor>,
     pub health_manager: HealthManager,
-    pub cache_manager: CacheManager,
+    pub cache_manager: Arc<CacheManager>,
+    /// Present only when async intake is enabled.
+    pub payment_queue: Option<PaymentQueue>,
+}
+
+impl Default for ApplicationServices {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl ApplicationServices {
     pub fn new() -> Self {
-        Self {
-            payment_processor: PaymentProcessor::new(),
-            health_manager: HealthManager::new(),
-            cache_manager: CacheManager::new(),
+        Self::with_cache_memory_limit(50)
+    }
+
+    pub fn with_services(
+        payment_processor: PaymentProcessor,
+        health_manager: HealthManager,
+        cache_manager: CacheManager,
+    ) -> Self {
+        Self::assemble(payment_processor, health_manager, Arc::new(cache_manager))
+    }
+
+    /// Builds the services the server runs with, applying `config`.
+    pub fn with_config(config: &Config) -> Self {
+        let cache_manager = Arc::new(CacheManager::from_config(config));
+        log::info!("Using the {} cache backend", cache_manager.backend_name());
+        if config.cache_backend == CacheBackendKind::Memory && config.cache_policy.is_allkeys() {
+            log::warn!(
+                "Cache policy {} may evict entries stored without a TTL; use a volatile-* policy",
+                config.cache_policy.name()
+            );
         }
+        let mut payment_processor = PaymentProcessor::with_cache(Arc::clone(&cache_manager));
+        payment_processor.set_retry_policy(config.retry_policy.clone());
+        let mut health_service = HealthCheckService::new();
+        health_service.share_through(Arc::clone(&cache_manager), config.replica_id.clone());
+        let mut health_manager = HealthManager::with_health_service(health_service);
+        health_manager.set_poll_interval(config.health_poll_interval, config.health_poll_jitter);
+        Self::assemble(payment_processor, health_manager, cache_manager)
     }
 
+    /// In-memory cache under a volatile policy, so entries stored without a
+    /// TTL are never evicted.
     pub fn with_cache_memory_limit(memory_limit_mb: u64) -> Self {
+        Self::with_cache_manager(CacheManager::with_policy(memory_limit_mb, CachePolicy::VolatileLRU))
+    }
+
+    fn with_cache_manager(cache_manager: CacheManager) -> Self {
+        let cache_manager = Arc::new(cache_manager);
+        let payment_processor = PaymentProcessor::with_cache(Arc::clone(&cache_manager));
+        Self::assemble(payment_processor, HealthManager::new(), cache_manager)
+    }
+
+    /// Wires the processor to the health manager's updates so routing
+    /// follows the latest health checks.
+    fn assemble(
+        mut payment_processor: PaymentProcessor,
+        health_manager: HealthManager,
+        cache_manager: Arc<CacheManager>,
+    ) -> Self {
+        payment_processor.set_health_updates(health_manager.subscribe());
         Self {
-            payment_processor: PaymentProcessor::new(),
-            health_manager: HealthManager::new(),
-            cache_manager: CacheManager::with_memory_limit(memory_limit_mb),
+            payment_processor: Arc::new(payment_processor),
+            health_manager,
+            cache_manager,
+            payment_queue: None,
         }
     }
+
+    /// Starts the async intake queue and its workers on the current runtime.
+    pub fn start_payment_queue(&mut self, config: &Config) {
+        self.payment_queue = Some(PaymentQueue::start_with_requeue_backoff(
+            Arc::clone(&self.payment_processor),
+            config.queue_capacity,
+            config.queue_workers,
+            config.queue_full_policy,
+            config.queue_requeue_base_backoff,
+            config.queue_requeue_max_backoff,
+            config.queue_max_requeues,
+        ));
+    }
 } 
\ No newline at end of file
@@ -3,14 +3,27 @@ use validator::Validate;
 use uuid::Uuid;
 use rmp_serde::{from_slice, to_vec_named};
 
+pub mod money;
+pub use money::Money;
+
 #[derive(Debug, Deserialize, Serialize, Validate)]
 pub struct PaymentRequest {
     #[serde(rename = "correlationId")]
     #[validate(custom = "validate_uuid")]
     pub correlation_id: String,
     
-    #[validate(range(min = 0.01))]
-    pub amount: f64,
+    #[validate(custom = "validate_amount")]
+    pub amount: Money,
+}
+
+fn validate_amount(amount: &Money) -> Result<(), validator::ValidationError> {
+    if amount.cents() < 1 {
+        Err(validator::ValidationError::new("amount_too_small"))
+    } else if *amount > Money::MAX_PAYMENT {
+        Err(validator::ValidationError::new("amount_too_large"))
+    } else {
+        Ok(())
+    }
 }
 
 fn validate_uuid(correlation_id: &str) -> Result<(), validator::ValidationError> {
@@ -21,26 +34,20 @@ fn validate_uuid(correlation_id: &str) -> Result<(), validator::ValidationError>
     }
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct PaymentResponse {
     pub message: String,
 }
 
 impl PaymentRequest {
-    /// Deserializa um payload MessagePack para PaymentRequest
-    /// Mantido para futura evolução do endpoint (ex: aceitar entrada binária)
-    /// TODO: Ativar uso quando endpoint aceitar application/x-msgpack
-    #[allow(dead_code)]
+    /// Deserializa um payload MessagePack (application/x-msgpack) para PaymentRequest
     pub fn from_msgpack(data: &[u8]) -> Result<Self, rmp_serde::decode::Error> {
         from_slice(data)
     }
 }
 
 impl PaymentResponse {
-    /// Serializa PaymentResponse para MessagePack
-    /// Mantido para futura evolução do endpoint (ex: retornar resposta binária)
-    /// TODO: Ativar uso quando endpoint retornar application/x-msgpack
-    #[allow(dead_code)]
+    /// Serializa PaymentResponse para MessagePack (application/x-msgpack)
     pub fn to_msgpack(&self) -> Result<Vec<u8>, rmp_serde::encode::Error> {
         to_vec_named(self)
     }
@@ -0,0 +1,220 @@
+use std::fmt;
+use std::iter::Sum;
+use std::ops::{Add, AddAssign};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use serde::de::{self, MapAccess, Visitor};
+
+/// Key serde_json's `arbitrary_precision` feature uses to hand a number's
+/// original text to `visit_map`.
+const JSON_NUMBER_TOKEN: &str = "$serde_json::private::Number";
+/// Beyond 2^53 cents an f64 cannot tell neighbouring cents apart, so larger
+/// amounts could not be serialized back exactly.
+const MAX_EXACT_CENTS: u64 = 1 << 53;
+
+/// Exact monetary amount stored as integer cents.
+///
+/// On the wire (JSON and MessagePack) it is a plain decimal number such as
+/// `19.90`, so the API format is unchanged, but sums never drift the way
+/// `f64` accumulation does. JSON amounts are parsed from their digits, so
+/// `1.0000000000000000001` is rejected rather than rounded to `1.00`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Money(i64);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MoneyError {
+    NotFinite,
+    TooManyDecimals,
+    OutOfRange,
+    Malformed,
+}
+
+impl fmt::Display for MoneyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MoneyError::NotFinite => write!(f, "amount must be a finite number"),
+            MoneyError::TooManyDecimals => write!(f, "amount must have at most two decimal places"),
+            MoneyError::OutOfRange => write!(f, "amount is out of range"),
+            MoneyError::Malformed => write!(f, "amount is not a decimal number"),
+        }
+    }
+}
+
+impl std::error::Error for MoneyError {}
+
+impl Money {
+    pub const ZERO: Money = Money(0);
+    /// Largest amount a single payment may carry, 1,000,000,000.00.
+    pub const MAX_PAYMENT: Money = Money(100_000_000_000);
+
+    pub const fn from_cents(cents: i64) -> Self {
+        Money(cents)
+    }
+
+    pub const fn cents(&self) -> i64 {
+        self.0
+    }
+
+    /// Converts a decimal amount, rejecting NaN, infinities and anything
+    /// with more than two decimal places.
+    pub fn try_from_f64(amount: f64) -> Result<Self, MoneyError> {
+        if !amount.is_finite() {
+            return Err(MoneyError::NotFinite);
+        }
+        if (amount * 100.0).abs() >= MAX_EXACT_CENTS as f64 {
+            return Err(MoneyError::OutOfRange);
+        }
+        // Exact only if the two-decimal rendering reads back as the same
+        // f64, so 0.29 passes but 0.010000001 does not
+        let decimal = format!("{:.2}", amount);
+        if decimal.parse::<f64>() != Ok(amount) {
+            return Err(MoneyError::TooManyDecimals);
+        }
+        decimal
+            .replace('.', "")
+            .parse()
+            .map(Money)
+            .map_err(|_| MoneyError::OutOfRange)
+    }
+
+    /// Parses a decimal number such as `19.90` or `1.999e1` from its text,
+    /// rejecting anything with non-zero digits past the second decimal.
+    pub fn from_decimal_str(text: &str) -> Result<Self, MoneyError> {
+        let (negative, unsigned) = match text.strip_prefix('-') {
+            Some(rest) => (true, rest),
+            None => (false, text),
+        };
+        let (mantissa, exponent) = match unsigned.split_once(['e', 'E']) {
+            Some((mantissa, exponent)) => (mantissa, parse_exponent(exponent)?),
+            None => (unsigned, 0),
+        };
+        let (integer, fraction) = mantissa.split_once('.').unwrap_or((mantissa, ""));
+        if integer.is_empty() || !integer.bytes().chain(fraction.bytes()).all(|b| b.is_ascii_digit()) {
+            return Err(MoneyError::Malformed);
+        }
+
+        // The value is `digits * 10^-scale`; trailing zeros do not count
+        // towards the decimal places
+        let digits = format!("{}{}", integer, fraction);
+        let significant = digits.trim_end_matches('0');
+        let scale = fraction.len() as i64 - exponent - (digits.len() - significant.len()) as i64;
+        let significant = significant.trim_start_matches('0');
+        if significant.is_empty() {
+            return Ok(Money::ZERO);
+        }
+        if scale > 2 {
+            return Err(MoneyError::TooManyDecimals);
+        }
+        // Bounds the shift so the power below cannot overflow
+        if significant.len() as i64 + 2 - scale > 16 {
+            return Err(MoneyError::OutOfRange);
+        }
+        let cents = significant
+            .parse::<u64>()
+            .ok()
+            .and_then(|value| value.checked_mul(10u64.pow((2 - scale) as u32)))
+            .filter(|cents| *cents < MAX_EXACT_CENTS)
+            .ok_or(MoneyError::OutOfRange)? as i64;
+        Ok(Money(if negative { -cents } else { cents }))
+    }
+
+    /// `None` if the sum overflows.
+    pub fn checked_add(self, other: Money) -> Option<Money> {
+        self.0.checked_add(other.0).map(Money)
+    }
+
+    pub fn to_f64(&self) -> f64 {
+        self.0 as f64 / 100.0
+    }
+}
+
+/// An exponent too large for an `i32` is out of range either way.
+fn parse_exponent(text: &str) -> Result<i64, MoneyError> {
+    let digits = text.strip_prefix(['+', '-']).unwrap_or(text);
+    if digits.is_empty() || !digits.bytes().all(|b| b.is_ascii_digit()) {
+        return Err(MoneyError::Malformed);
+    }
+    text.parse::<i32>().map(i64::from).map_err(|_| MoneyError::OutOfRange)
+}
+
+impl fmt::Display for Money {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let sign = if self.0 < 0 { "-" } else { "" };
+        let cents = self.0.unsigned_abs();
+        write!(f, "{}{}.{:02}", sign, cents / 100, cents % 100)
+    }
+}
+
+impl Add for Money {
+    type Output = Money;
+
+    /// Saturates rather than overflowing, so summaries never panic.
+    fn add(self, other: Money) -> Money {
+        Money(self.0.saturating_add(other.0))
+    }
+}
+
+impl AddAssign for Money {
+    fn add_assign(&mut self, other: Money) {
+        *self = *self + other;
+    }
+}
+
+impl Sum for Money {
+    fn sum<I: Iterator<Item = Money>>(iter: I) -> Money {
+        iter.fold(Money::ZERO, Add::add)
+    }
+}
+
+impl Serialize for Money {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        // cents / 100 is the closest f64 to the decimal value, so both
+        // serde_json and rmp-serde emit exactly two-decimal numbers
+        serializer.serialize_f64(self.to_f64())
+    }
+}
+
+impl<'de> Deserialize<'de> for Money {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_any(MoneyVisitor)
+    }
+}
+
+struct MoneyVisitor;
+
+impl<'de> Visitor<'de> for MoneyVisitor {
+    type Value = Money;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "a decimal amount with at most two decimal places")
+    }
+
+    /// JSON numbers arrive here as their original text.
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Money, A::Error> {
+        match map.next_key::<String>()? {
+            Some(key) if key == JSON_NUMBER_TOKEN => {
+                let text: String = map.next_value()?;
+                Money::from_decimal_str(&text).map_err(de::Error::custom)
+            }
+            _ => Err(de::Error::invalid_type(de::Unexpected::Map, &self)),
+        }
+    }
+
+    fn visit_f64<E: de::Error>(self, value: f64) -> Result<Money, E> {
+        Money::try_from_f64(value).map_err(E::custom)
+    }
+
+    fn visit_i64<E: de::Error>(self, value: i64) -> Result<Money, E> {
+        value
+            .checked_mul(100)
+            .map(Money)
+            .ok_or_else(|| E::custom(MoneyError::OutOfRange))
+    }
+
+    fn visit_u64<E: de::Error>(self, value: u64) -> Result<Money, E> {
+        i64::try_from(value)
+            .ok()
+            .and_then(|value| value.checked_mul(100))
+            .map(Money)
+            .ok_or_else(|| E::custom(MoneyError::OutOfRange))
+    }
+}
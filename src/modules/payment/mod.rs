@@ -1,29 +1,74 @@
 use axum::{
-    extract::Json,
-    http::StatusCode,
+    extract::State,
+    http::{header, HeaderValue, StatusCode},
     response::IntoResponse,
 };
 use axum_macros::debug_handler;
-use crate::modules::models::{PaymentRequest, PaymentResponse};
-use crate::modules::processors::PaymentProcessor;
+use crate::modules::models::PaymentResponse;
+use crate::modules::SharedServices;
+use crate::modules::config::QueueFullPolicy;
+use crate::modules::error::PaymentError;
+use crate::modules::queue::{EnqueueError, QueuedPayment};
 use validator::Validate;
 
+pub mod negotiation;
+use negotiation::{Accept, NegotiatedPayment};
+
+/// Seconds a client is asked to wait before retrying a payment the queue
+/// could not take.
+const QUEUE_RETRY_AFTER_SECS: &str = "1";
+
 #[debug_handler]
 pub async fn create_payment(
-    Json(payment): Json<PaymentRequest>,
+    State(services): State<SharedServices>,
+    Accept(format): Accept,
+    NegotiatedPayment(payment): NegotiatedPayment,
 ) -> impl IntoResponse {
-    if let Err(_) = payment.validate() {
-        return StatusCode::BAD_REQUEST.into_response();
+    // JSON and MessagePack bodies share this validation path; malformed
+    // amounts (NaN, extra decimals) were already rejected while decoding
+    if let Err(errors) = payment.validate() {
+        return format.render_error(&PaymentError::from(errors));
+    }
+
+    if let Some(queue) = &services.payment_queue {
+        let queued = QueuedPayment {
+            correlation_id: payment.correlation_id.clone(),
+            amount: payment.amount,
+        };
+        match queue.try_enqueue(queued) {
+            Ok(()) => {
+                let response = PaymentResponse {
+                    message: "payment accepted".to_string(),
+                };
+                return format.render(StatusCode::ACCEPTED, &response);
+            }
+            // Fall through to synchronous processing below
+            Err(EnqueueError::Full(_)) if queue.full_policy() == QueueFullPolicy::ProcessInline => {}
+            Err(e) => {
+                let error = match e {
+                    EnqueueError::Full(_) => PaymentError::QueueFull,
+                    EnqueueError::Closed => PaymentError::QueueClosed,
+                };
+                let mut response = format.render_error(&error);
+                response
+                    .headers_mut()
+                    .insert(header::RETRY_AFTER, HeaderValue::from_static(QUEUE_RETRY_AFTER_SECS));
+                return response;
+            }
+        }
     }
 
-    let processor = PaymentProcessor::new();
-    match processor.process_payment(&payment.correlation_id, payment.amount).await {
+    // Duplicates of an accepted payment get the stored outcome back from here
+    match services.payment_processor.process_payment(&payment.correlation_id, payment.amount).await {
         Ok(processor_response) => {
             let response = PaymentResponse {
                 message: processor_response.message,
             };
-            (StatusCode::OK, axum::Json(response)).into_response()
+            format.render(StatusCode::OK, &response)
+        }
+        Err(e) => {
+            log::warn!("Payment {} failed: {}", payment.correlation_id, e);
+            format.render_error(&e)
         }
-        Err(_) => StatusCode::INTERNAL_SERVER_ERROR.into_response(),
     }
 } 
\ No newline at end of file
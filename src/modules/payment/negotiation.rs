@@ -0,0 +1,169 @@
+use axum::{
+    async_trait,
+    body::Bytes,
+    extract::{FromRequest, FromRequestParts, Request},
+    http::{header, request::Parts, HeaderMap, HeaderValue, StatusCode},
+    response::{IntoResponse, Response},
+};
+use serde::Serialize;
+use crate::modules::error::PaymentError;
+use crate::modules::models::PaymentRequest;
+
+pub const JSON_CONTENT_TYPE: &str = "application/json";
+pub const MSGPACK_CONTENT_TYPE: &str = "application/x-msgpack";
+
+/// Wire format of a request or response body.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BodyFormat {
+    Json,
+    MessagePack,
+}
+
+impl BodyFormat {
+    fn from_mime(value: &str) -> Option<Self> {
+        let essence = value.split(';').next().unwrap_or("").trim();
+        if essence.eq_ignore_ascii_case(JSON_CONTENT_TYPE) {
+            Some(BodyFormat::Json)
+        } else if essence.eq_ignore_ascii_case(MSGPACK_CONTENT_TYPE)
+            || essence.eq_ignore_ascii_case("application/msgpack")
+        {
+            Some(BodyFormat::MessagePack)
+        } else {
+            None
+        }
+    }
+
+    /// Picks the request body format from `Content-Type`.
+    pub fn from_content_type(headers: &HeaderMap) -> Option<Self> {
+        headers
+            .get(header::CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .and_then(Self::from_mime)
+    }
+
+    /// Picks the response format from `Accept`: the one with the highest
+    /// q-value, the earliest on a tie. Defaults to JSON when the header is
+    /// missing or names nothing we can produce with q above 0.
+    pub fn from_accept(headers: &HeaderMap) -> Self {
+        let Some(accept) = headers.get(header::ACCEPT).and_then(|value| value.to_str().ok()) else {
+            return BodyFormat::Json;
+        };
+        let mut best = None;
+        let mut best_quality = 0.0;
+        for range in accept.split(',') {
+            let Some(format) = Self::from_mime(range) else {
+                continue;
+            };
+            let quality = quality(range);
+            if quality > best_quality {
+                best = Some(format);
+                best_quality = quality;
+            }
+        }
+        best.unwrap_or(BodyFormat::Json)
+    }
+
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            BodyFormat::Json => JSON_CONTENT_TYPE,
+            BodyFormat::MessagePack => MSGPACK_CONTENT_TYPE,
+        }
+    }
+
+    /// Renders a response body in this format.
+    pub fn render<T: Serialize>(&self, status: StatusCode, response: &T) -> Response {
+        let body = match self {
+            BodyFormat::Json => serde_json::to_vec(response).map_err(|e| e.to_string()),
+            BodyFormat::MessagePack => rmp_serde::to_vec_named(response).map_err(|e| e.to_string()),
+        };
+        match body {
+            Ok(body) => (
+                status,
+                [(header::CONTENT_TYPE, HeaderValue::from_static(self.content_type()))],
+                body,
+            )
+                .into_response(),
+            Err(e) => {
+                log::error!("Failed to serialize response: {}", e);
+                StatusCode::INTERNAL_SERVER_ERROR.into_response()
+            }
+        }
+    }
+
+    /// Renders an error body in this format, with the error's status.
+    pub fn render_error(&self, error: &PaymentError) -> Response {
+        self.render(error.status_code(), &error.to_body())
+    }
+}
+
+/// The `q` parameter of an `Accept` media range, 1 when absent.
+fn quality(range: &str) -> f32 {
+    range
+        .split(';')
+        .skip(1)
+        .filter_map(|param| param.split_once('='))
+        .find(|(name, _)| name.trim().eq_ignore_ascii_case("q"))
+        .map_or(1.0, |(_, value)| value.trim().parse().unwrap_or(0.0))
+}
+
+/// Response format requested through the `Accept` header.
+pub struct Accept(pub BodyFormat);
+
+#[async_trait]
+impl<S: Send + Sync> FromRequestParts<S> for Accept {
+    type Rejection = StatusCode;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        Ok(Accept(BodyFormat::from_accept(&parts.headers)))
+    }
+}
+
+/// A `PaymentRequest` decoded from either JSON or MessagePack depending on
+/// the request's `Content-Type`. Unsupported content types are rejected
+/// with 415 and undecodable bodies with 400.
+pub struct NegotiatedPayment(pub PaymentRequest);
+
+/// Why a payment body could not be extracted, rendered as an error body in
+/// the format the request's `Accept` header asked for.
+#[derive(Debug)]
+pub struct PaymentRejection {
+    pub format: BodyFormat,
+    pub error: PaymentError,
+}
+
+impl IntoResponse for PaymentRejection {
+    fn into_response(self) -> Response {
+        self.format.render_error(&self.error)
+    }
+}
+
+#[async_trait]
+impl<S: Send + Sync> FromRequest<S> for NegotiatedPayment {
+    type Rejection = PaymentRejection;
+
+    async fn from_request(req: Request, state: &S) -> Result<Self, Self::Rejection> {
+        let accept = BodyFormat::from_accept(req.headers());
+        let reject = |error| PaymentRejection { format: accept, error };
+
+        let Some(format) = BodyFormat::from_content_type(req.headers()) else {
+            let content_type = req
+                .headers()
+                .get(header::CONTENT_TYPE)
+                .and_then(|value| value.to_str().ok())
+                .unwrap_or("none")
+                .to_string();
+            return Err(reject(PaymentError::UnsupportedMediaType(content_type)));
+        };
+        let bytes = Bytes::from_request(req, state)
+            .await
+            .map_err(|e| reject(PaymentError::Validation(e.body_text())))?;
+
+        let payment = match format {
+            BodyFormat::Json => serde_json::from_slice(&bytes).map_err(|e| e.to_string()),
+            BodyFormat::MessagePack => PaymentRequest::from_msgpack(&bytes).map_err(|e| e.to_string()),
+        };
+        payment
+            .map(NegotiatedPayment)
+            .map_err(|reason| reject(PaymentError::Validation(reason)))
+    }
+}
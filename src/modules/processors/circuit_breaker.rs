@@ -0,0 +1,243 @@
+use std::sync::atomic::{AtomicU32, AtomicU64, AtomicU8, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use serde::{Serialize, Deserialize};
+use crate::modules::clock::SharedClock;
+use super::window::SlidingWindow;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CircuitState {
+    /// Calls go through and outcomes are counted.
+    Closed,
+    /// Calls are refused until the cooldown has passed.
+    Open,
+    /// A limited number of probe calls decide whether to close again.
+    HalfOpen,
+}
+
+const CLOSED: u8 = 0;
+const OPEN: u8 = 1;
+const HALF_OPEN: u8 = 2;
+
+impl CircuitState {
+    fn from_u8(value: u8) -> Self {
+        match value {
+            OPEN => CircuitState::Open,
+            HALF_OPEN => CircuitState::HalfOpen,
+            _ => CircuitState::Closed,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CircuitBreakerConfig {
+    /// Length of the sliding window failures are counted over.
+    pub window: Duration,
+    /// Number of buckets the window is split into.
+    pub buckets: usize,
+    /// Failure ratio within the window that opens the circuit.
+    pub failure_threshold: f64,
+    /// Calls needed in the window before the ratio is trusted.
+    pub min_requests: u64,
+    /// How long the circuit stays open before probing.
+    pub open_cooldown: Duration,
+    /// Probe calls let through while half-open; all must succeed to close.
+    pub half_open_probes: u32,
+}
+
+impl Default for CircuitBreakerConfig {
+    fn default() -> Self {
+        Self {
+            window: Duration::from_secs(10),
+            buckets: 10,
+            failure_threshold: 0.5,
+            min_requests: 5,
+            open_cooldown: Duration::from_secs(5),
+            half_open_probes: 3,
+        }
+    }
+}
+
+/// Leave for one call from `CircuitBreaker::try_acquire`. A half-open probe
+/// dropped without an outcome, e.g. because its request was cancelled,
+/// counts as failed, so it cannot hold the circuit half-open forever.
+#[must_use]
+pub struct CallPermit<'a> {
+    breaker: &'a CircuitBreaker,
+    probe: bool,
+    recorded: bool,
+}
+
+impl CallPermit<'_> {
+    pub fn is_probe(&self) -> bool {
+        self.probe
+    }
+
+    pub fn record_success(mut self) {
+        self.recorded = true;
+        self.breaker.record_success();
+    }
+
+    pub fn record_failure(mut self) {
+        self.recorded = true;
+        self.breaker.record_failure();
+    }
+}
+
+impl Drop for CallPermit<'_> {
+    fn drop(&mut self) {
+        if self.probe && !self.recorded {
+            self.breaker.record_failure();
+        }
+    }
+}
+
+/// Per-processor circuit breaker built entirely on atomics, so recording an
+/// outcome never blocks other requests.
+pub struct CircuitBreaker {
+    config: CircuitBreakerConfig,
+    /// Successes and failures.
+    window: SlidingWindow,
+    state: AtomicU8,
+    opened_at: AtomicU64,
+    probes_issued: AtomicU32,
+    probe_successes: AtomicU32,
+    clock: SharedClock,
+}
+
+impl CircuitBreaker {
+    pub fn new(config: CircuitBreakerConfig, clock: SharedClock) -> Self {
+        Self {
+            window: SlidingWindow::new(config.window, config.buckets, Arc::clone(&clock)),
+            config,
+            state: AtomicU8::new(CLOSED),
+            opened_at: AtomicU64::new(0),
+            probes_issued: AtomicU32::new(0),
+            probe_successes: AtomicU32::new(0),
+            clock,
+        }
+    }
+
+    pub fn config(&self) -> &CircuitBreakerConfig {
+        &self.config
+    }
+
+    /// Current state, reporting an open circuit whose cooldown has passed
+    /// as half-open even before the next call moves it there.
+    pub fn state(&self) -> CircuitState {
+        match CircuitState::from_u8(self.state.load(Ordering::Acquire)) {
+            CircuitState::Open if self.cooldown_elapsed() => CircuitState::HalfOpen,
+            state => state,
+        }
+    }
+
+    /// Asks to send one call through. The permit records the call's outcome.
+    pub fn try_acquire(&self) -> Option<CallPermit<'_>> {
+        let probe = match CircuitState::from_u8(self.state.load(Ordering::Acquire)) {
+            CircuitState::Closed => false,
+            CircuitState::Open => {
+                if !self.cooldown_elapsed() {
+                    return None;
+                }
+                // Losing this race just means another call moved it first
+                let _ = self.state.compare_exchange(OPEN, HALF_OPEN, Ordering::AcqRel, Ordering::Acquire);
+                true
+            }
+            CircuitState::HalfOpen => true,
+        };
+        if probe && !self.try_acquire_probe() {
+            return None;
+        }
+        Some(CallPermit {
+            breaker: self,
+            probe,
+            recorded: false,
+        })
+    }
+
+    pub fn record_success(&self) {
+        match CircuitState::from_u8(self.state.load(Ordering::Acquire)) {
+            CircuitState::Closed => {
+                self.window.add(1, 0);
+            }
+            CircuitState::HalfOpen => {
+                let successes = self.probe_successes.fetch_add(1, Ordering::AcqRel) + 1;
+                if successes >= self.config.half_open_probes
+                    && self
+                        .state
+                        .compare_exchange(HALF_OPEN, CLOSED, Ordering::AcqRel, Ordering::Acquire)
+                        .is_ok()
+                {
+                    self.window.clear();
+                }
+            }
+            // A call that started before the circuit opened
+            CircuitState::Open => {}
+        }
+    }
+
+    pub fn record_failure(&self) {
+        match CircuitState::from_u8(self.state.load(Ordering::Acquire)) {
+            CircuitState::Closed => {
+                self.window.add(0, 1);
+                let (successes, failures) = self.window_counts();
+                let total = successes + failures;
+                if total >= self.config.min_requests
+                    && failures as f64 / total as f64 >= self.config.failure_threshold
+                {
+                    self.trip(CLOSED);
+                }
+            }
+            CircuitState::HalfOpen => self.trip(HALF_OPEN),
+            CircuitState::Open => {}
+        }
+    }
+
+    /// Opens the circuit regardless of the window.
+    pub fn force_open(&self) {
+        self.prepare_open();
+        self.state.store(OPEN, Ordering::Release);
+    }
+
+    /// Closes the circuit and forgets the window.
+    pub fn reset(&self) {
+        self.state.store(CLOSED, Ordering::Release);
+        self.window.clear();
+    }
+
+    /// Failures counted in the current window.
+    pub fn failure_count(&self) -> u64 {
+        self.window_counts().1
+    }
+
+    /// Successes and failures counted in the current window.
+    pub fn window_counts(&self) -> (u64, u64) {
+        self.window.totals()
+    }
+
+    fn try_acquire_probe(&self) -> bool {
+        let limit = self.config.half_open_probes;
+        self.probes_issued
+            .fetch_update(Ordering::AcqRel, Ordering::Acquire, |issued| (issued < limit).then_some(issued + 1))
+            .is_ok()
+    }
+
+    fn trip(&self, from: u8) {
+        self.prepare_open();
+        let _ = self.state.compare_exchange(from, OPEN, Ordering::AcqRel, Ordering::Acquire);
+    }
+
+    /// Resets the probe counters and stamps the open time before the state
+    /// flips, so nobody sees an open circuit with stale values.
+    fn prepare_open(&self) {
+        self.probes_issued.store(0, Ordering::Release);
+        self.probe_successes.store(0, Ordering::Release);
+        self.opened_at.store(self.clock.now_millis(), Ordering::Release);
+    }
+
+    fn cooldown_elapsed(&self) -> bool {
+        let opened_at = self.opened_at.load(Ordering::Acquire);
+        self.clock.now_millis().saturating_sub(opened_at) >= self.config.open_cooldown.as_millis() as u64
+    }
+}
@@ -0,0 +1,128 @@
+use std::sync::Arc;
+use std::time::Duration;
+use serde::{Serialize, Deserialize};
+use crate::modules::cache::CacheManager;
+
+const KEY_PREFIX: &str = "idempotency:";
+
+/// How long an accepted correlationId is remembered.
+pub const DEFAULT_RECORD_TTL: Duration = Duration::from_secs(24 * 60 * 60);
+/// How long a claim holds a correlationId while its payment is dispatched;
+/// a replica that dies mid-dispatch frees it after this.
+pub const DEFAULT_CLAIM_TTL: Duration = Duration::from_secs(30);
+
+/// Outcome remembered for a correlationId once a processor has accepted it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StoredOutcome {
+    pub message: String,
+    pub processor: String,
+}
+
+/// What the cache holds for a correlationId.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum Record {
+    /// Claimed by a replica that is dispatching it.
+    Pending,
+    Accepted(StoredOutcome),
+}
+
+/// Result of trying to claim a correlationId before dispatching it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Claim {
+    /// This caller holds the correlationId and must dispatch it, then
+    /// `store` or `release` it.
+    Claimed,
+    /// Another replica is dispatching it right now.
+    Pending,
+    /// It was already accepted with this outcome.
+    Accepted(StoredOutcome),
+}
+
+/// Remembers correlationIds in the shared cache so every replica sends a
+/// payment to a processor at most once. A caller claims the correlationId
+/// with `set_nx` before dispatching; retries are answered from the stored
+/// outcome instead of being sent again. Failures are not stored, so a
+/// retry after a failed attempt is dispatched normally.
+pub struct IdempotencyStore {
+    cache: Arc<CacheManager>,
+    ttl: Duration,
+    claim_ttl: Duration,
+}
+
+impl IdempotencyStore {
+    pub fn new(cache: Arc<CacheManager>) -> Self {
+        Self::with_ttl(cache, DEFAULT_RECORD_TTL)
+    }
+
+    /// Store whose accepted records expire after `ttl`.
+    pub fn with_ttl(cache: Arc<CacheManager>, ttl: Duration) -> Self {
+        Self {
+            cache,
+            ttl,
+            claim_ttl: DEFAULT_CLAIM_TTL,
+        }
+    }
+
+    pub fn set_claim_ttl(&mut self, claim_ttl: Duration) {
+        self.claim_ttl = claim_ttl;
+    }
+
+    /// Claims `correlation_id` for dispatch. If the cache cannot be reached
+    /// the claim is granted, so payments keep flowing without it.
+    pub async fn claim(&self, correlation_id: &str) -> Claim {
+        let key = Self::key(correlation_id);
+        match self.cache.set_nx(&key, &Record::Pending, self.claim_ttl).await {
+            Ok(true) => return Claim::Claimed,
+            Ok(false) => {}
+            Err(e) => {
+                log::warn!("Failed to claim idempotency key {}: {}", correlation_id, e);
+                return Claim::Claimed;
+            }
+        }
+        match self.cache.get::<Record>(&key).await {
+            Ok(Some(Record::Accepted(outcome))) => Claim::Accepted(outcome),
+            // Still being dispatched, or released since the claim failed;
+            // either way the caller can retry later
+            Ok(_) => Claim::Pending,
+            Err(e) => {
+                log::warn!("Failed to read idempotency key {}: {}", correlation_id, e);
+                Claim::Pending
+            }
+        }
+    }
+
+    pub async fn get(&self, correlation_id: &str) -> Option<StoredOutcome> {
+        match self.cache.get::<Record>(&Self::key(correlation_id)).await {
+            Ok(Some(Record::Accepted(outcome))) => Some(outcome),
+            Ok(_) => None,
+            Err(e) => {
+                log::warn!("Failed to read idempotency key {}: {}", correlation_id, e);
+                None
+            }
+        }
+    }
+
+    /// Replaces the claim with the accepted outcome.
+    pub async fn store(&self, correlation_id: &str, outcome: &StoredOutcome) {
+        let record = Record::Accepted(outcome.clone());
+        if let Err(e) = self.cache.set(&Self::key(correlation_id), &record, self.ttl).await {
+            log::warn!("Failed to store idempotency key {}: {}", correlation_id, e);
+        }
+    }
+
+    /// Drops the claim after a failed dispatch so the payment can be retried.
+    pub async fn release(&self, correlation_id: &str) {
+        if let Err(e) = self.cache.remove(&Self::key(correlation_id)).await {
+            log::warn!("Failed to release idempotency key {}: {}", correlation_id, e);
+        }
+    }
+
+    pub fn get_ttl(&self) -> Duration {
+        self.ttl
+    }
+
+    fn key(correlation_id: &str) -> String {
+        format!("{}{}", KEY_PREFIX, correlation_id)
+    }
+}
@@ -1,13 +1,25 @@
+use std::sync::Arc;
 use serde::{Serialize, Deserialize};
 
 pub mod selector;
+pub mod idempotency;
+pub mod retry;
+pub mod circuit_breaker;
+mod window;
 use selector::ProcessorSelector;
+use idempotency::{Claim, IdempotencyStore, StoredOutcome};
+use retry::RetryPolicy;
+use crate::modules::cache::CacheManager;
+use crate::modules::error::{PaymentError, PaymentResult};
+use crate::modules::health::service::SharedHealthSnapshot;
+use crate::modules::models::Money;
+use crate::modules::summary::ledger::{PaymentLedger, PaymentsSummary};
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct PaymentProcessorRequest {
     #[serde(rename = "correlationId")]
     pub correlation_id: String,
-    pub amount: f64,
+    pub amount: Money,
     #[serde(rename = "requestedAt")]
     pub requested_at: String,
 }
@@ -19,29 +31,99 @@ pub struct PaymentProcessorResponse {
 
 pub struct PaymentProcessor {
     selector: ProcessorSelector,
+    idempotency: IdempotencyStore,
+}
+
+impl Default for PaymentProcessor {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl PaymentProcessor {
     pub fn new() -> Self {
+        Self::with_cache(Arc::new(CacheManager::new()))
+    }
+
+    /// Builds a processor that keeps idempotency keys and the payment
+    /// ledger in a shared cache.
+    pub fn with_cache(cache: Arc<CacheManager>) -> Self {
+        Self::with_selector_and_cache(ProcessorSelector::new(), cache)
+    }
+
+    pub fn with_selector(selector: ProcessorSelector) -> Self {
+        Self::with_selector_and_cache(selector, Arc::new(CacheManager::new()))
+    }
+
+    /// Keeps idempotency keys and the payment ledger in `cache`.
+    pub fn with_selector_and_cache(mut selector: ProcessorSelector, cache: Arc<CacheManager>) -> Self {
+        selector.set_ledger(Arc::new(PaymentLedger::with_cache(Arc::clone(&cache))));
         Self {
-            selector: ProcessorSelector::new(),
+            selector,
+            idempotency: IdempotencyStore::new(cache),
         }
     }
 
+    pub fn set_health_updates(&mut self, updates: tokio::sync::watch::Receiver<SharedHealthSnapshot>) {
+        self.selector.set_health_updates(updates);
+    }
+
+    pub fn set_retry_policy(&mut self, policy: RetryPolicy) {
+        self.selector.set_retry_policy(policy);
+    }
+
+    /// Sends the payment to a processor, unless it was already accepted:
+    /// a retry of an accepted payment gets the original outcome back, and
+    /// one that another replica is still dispatching is a `Duplicate`.
     pub async fn process_payment(
         &self,
         correlation_id: &str,
-        amount: f64,
-    ) -> Result<PaymentProcessorResponse, Box<dyn std::error::Error + Send + Sync>> {
+        amount: Money,
+    ) -> PaymentResult<PaymentProcessorResponse> {
+        match self.idempotency.claim(correlation_id).await {
+            Claim::Claimed => {}
+            Claim::Accepted(outcome) => {
+                return Ok(PaymentProcessorResponse {
+                    message: outcome.message,
+                });
+            }
+            Claim::Pending => {
+                return Err(PaymentError::Duplicate {
+                    correlation_id: correlation_id.to_string(),
+                });
+            }
+        }
+
         // Use the ProcessorSelector for intelligent routing
-        let selector_response = self.selector.process_payment(correlation_id, amount).await?;
-        
+        let selector_response = match self.selector.process_payment(correlation_id, amount).await {
+            Ok(response) => response,
+            Err(e) => {
+                self.idempotency.release(correlation_id).await;
+                return Err(e);
+            }
+        };
+
+        self.idempotency
+            .store(correlation_id, &StoredOutcome {
+                message: selector_response.message.clone(),
+                processor: selector_response.processor,
+            })
+            .await;
+
         // Convert selector response to our response type
         Ok(PaymentProcessorResponse {
             message: selector_response.message,
         })
     }
 
+    pub async fn get_stored_outcome(&self, correlation_id: &str) -> Option<StoredOutcome> {
+        self.idempotency.get(correlation_id).await
+    }
+
+    pub fn is_in_flight(&self, correlation_id: &str) -> bool {
+        self.selector.is_in_flight(correlation_id)
+    }
+
     // Expose ProcessorSelector methods for advanced usage
     pub async fn get_processor_info(&self) -> std::collections::HashMap<String, selector::ProcessorInfo> {
         self.selector.get_processors().await
@@ -54,4 +136,12 @@ impl PaymentProcessor {
     pub async fn is_processor_healthy(&self, name: &str) -> bool {
         self.selector.is_processor_healthy(name).await
     }
+
+    pub fn get_ledger(&self) -> Arc<PaymentLedger> {
+        self.selector.ledger()
+    }
+
+    pub async fn get_payments_summary(&self, from: Option<u64>, to: Option<u64>) -> PaymentsSummary {
+        self.selector.ledger().summary(from, to).await
+    }
 } 
\ No newline at end of file
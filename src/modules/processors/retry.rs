@@ -0,0 +1,113 @@
+use std::time::Duration;
+use rand::Rng;
+use crate::modules::clock::SharedClock;
+use crate::modules::error::PaymentError;
+use super::window::SlidingWindow;
+
+/// Error kinds retried against the same processor when no list is configured.
+/// Timeouts are left out: each one already cost a full request timeout, and
+/// the processor may have accepted the payment anyway.
+pub const DEFAULT_RETRYABLE_KINDS: &[&str] = &["connection_refused", "processor_server_error"];
+
+/// Buckets the retry budget window is split into.
+const BUDGET_BUCKETS: usize = 10;
+
+/// How often a processor call is retried before the selector fails over.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RetryPolicy {
+    /// Total attempts per processor, including the first one.
+    pub max_attempts: u32,
+    pub base_backoff: Duration,
+    pub max_backoff: Duration,
+    /// Sleep a random duration between zero and the computed backoff.
+    pub jitter: bool,
+    /// Retries allowed as a percentage of first attempts.
+    pub budget_percent: f64,
+    /// Sliding window the budget counts first attempts and retries over.
+    pub budget_window: Duration,
+    /// `PaymentError::kind()` values worth retrying.
+    pub retryable_kinds: Vec<String>,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl RetryPolicy {
+    pub fn new() -> Self {
+        Self {
+            max_attempts: 3,
+            base_backoff: Duration::from_millis(10),
+            max_backoff: Duration::from_millis(200),
+            jitter: true,
+            budget_percent: 20.0,
+            budget_window: Duration::from_secs(10),
+            retryable_kinds: DEFAULT_RETRYABLE_KINDS.iter().map(|kind| kind.to_string()).collect(),
+        }
+    }
+
+    /// A policy that makes a single attempt.
+    pub fn no_retries() -> Self {
+        Self {
+            max_attempts: 1,
+            ..Self::new()
+        }
+    }
+
+    pub fn should_retry(&self, error: &PaymentError) -> bool {
+        self.retryable_kinds.iter().any(|kind| kind == error.kind())
+    }
+
+    /// Delay before retry number `retry` (starting at 1): the base backoff
+    /// doubled per retry and capped at the max, then fully jittered.
+    pub fn backoff(&self, retry: u32) -> Duration {
+        let factor = 2u32.saturating_pow(retry.saturating_sub(1));
+        let capped = self.base_backoff.saturating_mul(factor).min(self.max_backoff);
+        if self.jitter && !capped.is_zero() {
+            let millis = rand::thread_rng().gen_range(0..=capped.as_millis() as u64);
+            Duration::from_millis(millis)
+        } else {
+            capped
+        }
+    }
+}
+
+/// Caps retries at a percentage of recent traffic so a failing processor
+/// cannot multiply the load sent to it. Counts slide out of the window, so
+/// a burst of failures cannot use up the budget for good.
+pub struct RetryBudget {
+    percent: f64,
+    /// First attempts and retries.
+    window: SlidingWindow,
+}
+
+impl RetryBudget {
+    pub fn new(percent: f64, window: Duration, clock: SharedClock) -> Self {
+        Self {
+            percent,
+            window: SlidingWindow::new(window, BUDGET_BUCKETS, clock),
+        }
+    }
+
+    pub fn record_request(&self) {
+        self.window.add(1, 0);
+    }
+
+    /// Takes one retry out of the budget if there is room left.
+    pub fn try_acquire(&self) -> bool {
+        let percent = self.percent;
+        self.window.try_add(0, 1, |(requests, retries)| (retries as f64) < requests as f64 * percent / 100.0)
+    }
+
+    /// First attempts in the current window.
+    pub fn requests(&self) -> u64 {
+        self.window.totals().0
+    }
+
+    /// Retries in the current window.
+    pub fn retries(&self) -> u64 {
+        self.window.totals().1
+    }
+}
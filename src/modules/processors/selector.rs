@@ -1,24 +1,33 @@
-use std::collections::HashMap;
-use std::sync::Arc;
-use tokio::sync::RwLock;
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex};
+use tokio::sync::{watch, RwLock};
 use serde::{Serialize, Deserialize};
-use reqwest::Client;
-use std::time::{SystemTime, UNIX_EPOCH};
+use reqwest::{Client, StatusCode};
+use crate::modules::clock::{format_iso8601, system_clock, SharedClock};
+use crate::modules::error::{PaymentError, PaymentResult};
+use crate::modules::health::service::{ProcessorHealth, SharedHealthSnapshot};
+use crate::modules::models::Money;
+use crate::modules::processors::circuit_breaker::{CircuitBreaker, CircuitBreakerConfig, CircuitState};
+use crate::modules::processors::retry::{RetryBudget, RetryPolicy};
+use crate::modules::summary::ledger::PaymentLedger;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ProcessorInfo {
     pub name: String,
     pub url: String,
     pub rate: f64,
+    /// False while the circuit is open.
     pub is_healthy: bool,
+    /// Failures counted in the circuit breaker's current window.
     pub failure_count: u32,
+    pub circuit_state: CircuitState,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct PaymentProcessorRequest {
     #[serde(rename = "correlationId")]
     pub correlation_id: String,
-    pub amount: f64,
+    pub amount: Money,
     #[serde(rename = "requestedAt")]
     pub requested_at: String,
 }
@@ -26,12 +35,49 @@ pub struct PaymentProcessorRequest {
 #[derive(Debug, Serialize, Deserialize)]
 pub struct PaymentProcessorResponse {
     pub message: String,
+    /// Name of the processor that accepted the payment.
+    #[serde(skip)]
+    pub processor: String,
+}
+
+/// Removes a correlationId from the in-flight set when dispatch finishes,
+/// whichever way it ends.
+struct InFlightGuard<'a> {
+    in_flight: &'a Mutex<HashSet<String>>,
+    correlation_id: String,
+}
+
+impl Drop for InFlightGuard<'_> {
+    fn drop(&mut self) {
+        if let Ok(mut in_flight) = self.in_flight.lock() {
+            in_flight.remove(&self.correlation_id);
+        }
+    }
 }
 
 #[allow(dead_code)]
 pub struct ProcessorSelector {
     processors: Arc<RwLock<HashMap<String, ProcessorInfo>>>,
     client: Client,
+    ledger: Arc<PaymentLedger>,
+    in_flight: Arc<Mutex<HashSet<String>>>,
+    clock: SharedClock,
+    retry_policy: RetryPolicy,
+    retry_budget: RetryBudget,
+    circuit_config: CircuitBreakerConfig,
+    /// One breaker per configured processor; the set never changes after
+    /// construction, so lookups need no lock.
+    breakers: HashMap<String, CircuitBreaker>,
+    /// Latest processor health published by the health service, if subscribed.
+    health: Option<watch::Receiver<SharedHealthSnapshot>>,
+    /// Added to a processor's rate per millisecond of its `minResponseTime`.
+    latency_penalty_per_ms: f64,
+}
+
+impl Default for ProcessorSelector {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl ProcessorSelector {
@@ -62,28 +108,144 @@ impl ProcessorSelector {
                 rate: 0.05, // Default rate
                 is_healthy: true,
                 failure_count: 0,
+                circuit_state: CircuitState::Closed,
             });
         }
         
+        let clock = system_clock();
+        let circuit_config = CircuitBreakerConfig::default();
+        let breakers = Self::build_breakers(processors.keys(), &circuit_config, &clock);
+        let retry_policy = RetryPolicy::new();
+        let retry_budget = Self::build_retry_budget(&retry_policy, &clock);
+
         Self {
             processors: Arc::new(RwLock::new(processors)),
             client,
+            ledger: Arc::new(PaymentLedger::new()),
+            in_flight: Arc::new(Mutex::new(HashSet::new())),
+            clock,
+            retry_policy,
+            retry_budget,
+            circuit_config,
+            breakers,
+            health: None,
+            latency_penalty_per_ms: 0.0001,
         }
     }
 
+    fn build_breakers<'a>(
+        names: impl Iterator<Item = &'a String>,
+        config: &CircuitBreakerConfig,
+        clock: &SharedClock,
+    ) -> HashMap<String, CircuitBreaker> {
+        names
+            .map(|name| (name.clone(), CircuitBreaker::new(config.clone(), Arc::clone(clock))))
+            .collect()
+    }
+
+    fn build_retry_budget(policy: &RetryPolicy, clock: &SharedClock) -> RetryBudget {
+        RetryBudget::new(policy.budget_percent, policy.budget_window, Arc::clone(clock))
+    }
+
+    /// Replaces the clock used to stamp `requestedAt`.
+    /// Breakers and the retry budget start over.
+    pub fn set_clock(&mut self, clock: SharedClock) {
+        self.clock = clock;
+        self.breakers = Self::build_breakers(self.breakers.keys(), &self.circuit_config, &self.clock);
+        self.retry_budget = Self::build_retry_budget(&self.retry_policy, &self.clock);
+    }
+
+    /// Replaces the circuit breaker settings; breakers restart closed.
+    pub fn set_circuit_breaker_config(&mut self, config: CircuitBreakerConfig) {
+        self.breakers = Self::build_breakers(self.breakers.keys(), &config, &self.clock);
+        self.circuit_config = config;
+    }
+
+    /// Routes using health snapshots from this channel from now on.
+    pub fn set_health_updates(&mut self, updates: watch::Receiver<SharedHealthSnapshot>) {
+        self.health = Some(updates);
+    }
+
+    /// How much each millisecond of reported `minResponseTime` adds to a
+    /// processor's rate when ranking processors.
+    pub fn set_latency_penalty(&mut self, penalty_per_ms: f64) {
+        self.latency_penalty_per_ms = penalty_per_ms;
+    }
+
+    /// Last published health for a processor.
+    pub fn get_processor_health(&self, name: &str) -> Option<ProcessorHealth> {
+        self.health
+            .as_ref()
+            .and_then(|updates| updates.borrow().get(name).copied())
+    }
+
+    pub fn circuit_state(&self, name: &str) -> Option<CircuitState> {
+        self.breakers.get(name).map(|breaker| breaker.state())
+    }
+
+    /// Replaces the retry policy and starts a fresh retry budget for it.
+    pub fn set_retry_policy(&mut self, policy: RetryPolicy) {
+        self.retry_budget = Self::build_retry_budget(&policy, &self.clock);
+        self.retry_policy = policy;
+    }
+
+    pub fn retry_policy(&self) -> &RetryPolicy {
+        &self.retry_policy
+    }
+
+    pub fn retry_budget(&self) -> &RetryBudget {
+        &self.retry_budget
+    }
+
+    /// Records accepted payments in `ledger` from now on, e.g. one kept in
+    /// the cache shared by every replica.
+    pub fn set_ledger(&mut self, ledger: Arc<PaymentLedger>) {
+        self.ledger = ledger;
+    }
+
+    pub fn ledger(&self) -> Arc<PaymentLedger> {
+        Arc::clone(&self.ledger)
+    }
+
     pub async fn get_processors(&self) -> HashMap<String, ProcessorInfo> {
-        self.processors.read().await.clone()
+        self.processors
+            .read()
+            .await
+            .iter()
+            .map(|(name, info)| (name.clone(), self.with_circuit_state(info)))
+            .collect()
     }
 
     pub async fn get_default_processor(&self) -> Option<ProcessorInfo> {
+        // The cheapest processor that is healthy and not reported failing
+        self.dispatch_order()
+            .await
+            .into_iter()
+            .find(|p| p.is_healthy)
+    }
+
+    /// Processors that may receive payments, cheapest first. Processors the
+    /// health service reports as failing are left out, and a slow
+    /// `minResponseTime` makes a processor look more expensive.
+    async fn dispatch_order(&self) -> Vec<ProcessorInfo> {
         let processors = self.processors.read().await;
-        
-        // Find the processor with the lowest rate that is healthy
-        processors
+        // Cheap Arc clone; the watch lock is released right away
+        let health = self.health.as_ref().map(|updates| SharedHealthSnapshot::clone(&updates.borrow()));
+
+        let mut ranked: Vec<(f64, ProcessorInfo)> = processors
             .values()
-            .filter(|p| p.is_healthy)
-            .min_by(|a, b| a.rate.partial_cmp(&b.rate).unwrap_or(std::cmp::Ordering::Equal))
-            .cloned()
+            .filter_map(|info| {
+                let reported = health.as_ref().and_then(|snapshot| snapshot.get(&info.name));
+                if reported.is_some_and(|h| h.failing) {
+                    return None;
+                }
+                let latency_ms = reported.map(|h| h.min_response_time_ms).unwrap_or(0);
+                let cost = info.rate + latency_ms as f64 * self.latency_penalty_per_ms;
+                Some((cost, self.with_circuit_state(info)))
+            })
+            .collect();
+        ranked.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap_or(std::cmp::Ordering::Equal));
+        ranked.into_iter().map(|(_, info)| info).collect()
     }
 
     pub async fn get_processor_rates(&self) -> HashMap<String, f64> {
@@ -101,63 +263,136 @@ impl ProcessorSelector {
         }
     }
 
+    /// Opens the processor's circuit until its cooldown passes.
     pub async fn mark_processor_failed(&self, name: &str) {
-        let mut processors = self.processors.write().await;
-        if let Some(processor) = processors.get_mut(name) {
-            processor.is_healthy = false;
-            processor.failure_count += 1;
+        if let Some(breaker) = self.breakers.get(name) {
+            breaker.force_open();
         }
     }
 
+    /// Closes the processor's circuit and forgets its recent failures.
     pub async fn mark_processor_healthy(&self, name: &str) {
-        let mut processors = self.processors.write().await;
-        if let Some(processor) = processors.get_mut(name) {
-            processor.is_healthy = true;
-            processor.failure_count = 0;
+        if let Some(breaker) = self.breakers.get(name) {
+            breaker.reset();
         }
     }
 
     pub async fn is_processor_healthy(&self, name: &str) -> bool {
-        let processors = self.processors.read().await;
-        processors.get(name).map(|p| p.is_healthy).unwrap_or(false)
+        self.circuit_state(name)
+            .map(|state| state != CircuitState::Open)
+            .unwrap_or(false)
+    }
+
+    fn with_circuit_state(&self, info: &ProcessorInfo) -> ProcessorInfo {
+        let mut info = info.clone();
+        if let Some(breaker) = self.breakers.get(&info.name) {
+            info.circuit_state = breaker.state();
+            info.is_healthy = info.circuit_state != CircuitState::Open;
+            info.failure_count = breaker.failure_count() as u32;
+        }
+        info
+    }
+
+    pub fn is_in_flight(&self, correlation_id: &str) -> bool {
+        self.in_flight
+            .lock()
+            .map(|in_flight| in_flight.contains(correlation_id))
+            .unwrap_or(false)
+    }
+
+    fn begin_dispatch(&self, correlation_id: &str) -> Option<InFlightGuard<'_>> {
+        let mut in_flight = self.in_flight.lock().ok()?;
+        if !in_flight.insert(correlation_id.to_string()) {
+            return None;
+        }
+        Some(InFlightGuard {
+            in_flight: &self.in_flight,
+            correlation_id: correlation_id.to_string(),
+        })
     }
 
     pub async fn process_payment(
         &self,
         correlation_id: &str,
-        amount: f64,
-    ) -> Result<PaymentProcessorResponse, Box<dyn std::error::Error + Send + Sync>> {
-        let requested_at = SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .unwrap()
-            .as_millis();
+        amount: Money,
+    ) -> PaymentResult<PaymentProcessorResponse> {
+        let Some(_guard) = self.begin_dispatch(correlation_id) else {
+            return Err(PaymentError::Duplicate {
+                correlation_id: correlation_id.to_string(),
+            });
+        };
+
+        let requested_at = self.clock.now_millis();
 
         let payload = PaymentProcessorRequest {
             correlation_id: correlation_id.to_string(),
             amount,
-            requested_at: format!("{}", requested_at),
+            requested_at: format_iso8601(requested_at),
         };
 
-        // Get the default processor (lowest rate)
-        let default_processor = self.get_default_processor().await;
-        
-        if let Some(processor) = default_processor {
-            // Try the default processor first
-            match self.try_processor(&processor, &payload).await {
+        // Each breaker decides whether its processor may be called right
+        // now, including half-open probes after a cooldown.
+        for processor in &self.dispatch_order().await {
+            let Some(breaker) = self.breakers.get(&processor.name) else {
+                continue;
+            };
+            let Some(permit) = breaker.try_acquire() else {
+                continue;
+            };
+
+            match self.try_processor_with_retries(processor, &payload, requested_at).await {
                 Ok(response) => {
-                    // Success - mark processor as healthy
-                    self.mark_processor_healthy(&processor.name).await;
-                    Ok(response)
+                    permit.record_success();
+                    return Ok(response);
+                }
+                Err(e) if e.is_retryable() => {
+                    log::warn!("Processor {} failed, failing over: {}", processor.name, e);
+                    permit.record_failure();
                 }
-                Err(_) => {
-                    // Mark as failed and try fallback
-                    self.mark_processor_failed(&processor.name).await;
-                    self.try_fallback_processor(&payload).await
+                // The processor is up but refused this payment; another
+                // processor would refuse it too.
+                Err(e) => {
+                    permit.record_success();
+                    return Err(e);
+                }
+            }
+        }
+
+        Err(PaymentError::AllProcessorsDown)
+    }
+
+    /// Calls one processor, retrying under the retry policy before the
+    /// caller gives up on it and fails over.
+    async fn try_processor_with_retries(
+        &self,
+        processor: &ProcessorInfo,
+        payload: &PaymentProcessorRequest,
+        requested_at: u64,
+    ) -> PaymentResult<PaymentProcessorResponse> {
+        self.retry_budget.record_request();
+        let mut attempt = 1;
+        loop {
+            match self.try_processor(processor, payload, requested_at).await {
+                Ok(response) => return Ok(response),
+                Err(e) => {
+                    if attempt >= self.retry_policy.max_attempts
+                        || !self.retry_policy.should_retry(&e)
+                        || !self.retry_budget.try_acquire()
+                    {
+                        return Err(e);
+                    }
+                    let delay = self.retry_policy.backoff(attempt);
+                    log::debug!(
+                        "Retrying processor {} in {:?} (attempt {}): {}",
+                        processor.name,
+                        delay,
+                        attempt + 1,
+                        e
+                    );
+                    tokio::time::sleep(delay).await;
+                    attempt += 1;
                 }
             }
-        } else {
-            // No healthy processors available, try any available processor
-            self.try_any_processor(&payload).await
         }
     }
 
@@ -165,72 +400,78 @@ impl ProcessorSelector {
         &self,
         processor: &ProcessorInfo,
         payload: &PaymentProcessorRequest,
-    ) -> Result<PaymentProcessorResponse, Box<dyn std::error::Error + Send + Sync>> {
-        let response = self
+        requested_at: u64,
+    ) -> PaymentResult<PaymentProcessorResponse> {
+        let response = match self
             .client
-            .post(&format!("{}/payments", processor.url))
+            .post(format!("{}/payments", processor.url))
             .json(payload)
             .timeout(std::time::Duration::from_secs(5))
             .send()
-            .await?;
+            .await
+        {
+            Ok(response) => response,
+            Err(e) if e.is_timeout() => {
+                // The processor may have accepted the payment before we gave
+                // up on it; failing over now would charge it twice.
+                if self.confirm_payment(processor, payload).await {
+                    self.ledger
+                        .record(&payload.correlation_id, &processor.name, payload.amount, requested_at)
+                        .await;
+                    return Ok(PaymentProcessorResponse {
+                        message: "payment processed successfully".to_string(),
+                        processor: processor.name.clone(),
+                    });
+                }
+                return Err(PaymentError::from_reqwest(&processor.name, e));
+            }
+            Err(e) => return Err(PaymentError::from_reqwest(&processor.name, e)),
+        };
 
-        if response.status().is_success() {
-            let processor_response: PaymentProcessorResponse = response.json().await?;
+        let status = response.status();
+        if status.is_success() {
+            // The processor has accepted the payment at this point, so it must
+            // be in the ledger even if the response body turns out malformed.
+            self.ledger
+                .record(&payload.correlation_id, &processor.name, payload.amount, requested_at)
+                .await;
+            let mut processor_response: PaymentProcessorResponse = response
+                .json()
+                .await
+                .map_err(|e| PaymentError::from_reqwest(&processor.name, e))?;
+            processor_response.processor = processor.name.clone();
             Ok(processor_response)
-        } else {
-            Err("Processor failed".into())
-        }
-    }
-
-    async fn try_fallback_processor(
-        &self,
-        payload: &PaymentProcessorRequest,
-    ) -> Result<PaymentProcessorResponse, Box<dyn std::error::Error + Send + Sync>> {
-        let processors_guard = self.processors.read().await;
-        let processors: Vec<ProcessorInfo> = processors_guard.values().cloned().collect();
-        drop(processors_guard); // Release lock before async call
-        
-        // Try any healthy processor that's not the default
-        for processor in processors.iter() {
-            if processor.is_healthy {
-                match self.try_processor(processor, payload).await {
-                    Ok(response) => {
-                        self.mark_processor_healthy(&processor.name).await;
-                        return Ok(response);
-                    }
-                    Err(_) => {
-                        self.mark_processor_failed(&processor.name).await;
-                        continue;
-                    }
+        } else if status == StatusCode::UNPROCESSABLE_ENTITY || status == StatusCode::CONFLICT {
+            // Only a duplicate if the processor holds this correlationId from
+            // an earlier attempt; otherwise it rejected the payment.
+            if !self.ledger.contains(&payload.correlation_id).await {
+                if !self.confirm_payment(processor, payload).await {
+                    return Err(PaymentError::from_status(&processor.name, status));
                 }
+                self.ledger
+                    .record(&payload.correlation_id, &processor.name, payload.amount, requested_at)
+                    .await;
             }
+            Ok(PaymentProcessorResponse {
+                message: "payment already processed".to_string(),
+                processor: processor.name.clone(),
+            })
+        } else {
+            Err(PaymentError::from_status(&processor.name, status))
         }
-        
-        Err("All processors failed".into())
     }
 
-    async fn try_any_processor(
-        &self,
-        payload: &PaymentProcessorRequest,
-    ) -> Result<PaymentProcessorResponse, Box<dyn std::error::Error + Send + Sync>> {
-        let processors_guard = self.processors.read().await;
-        let processors: Vec<ProcessorInfo> = processors_guard.values().cloned().collect();
-        drop(processors_guard); // Release lock before async call
-        
-        // Try any available processor
-        for processor in processors.iter() {
-            match self.try_processor(processor, payload).await {
-                Ok(response) => {
-                    self.mark_processor_healthy(&processor.name).await;
-                    return Ok(response);
-                }
-                Err(_) => {
-                    self.mark_processor_failed(&processor.name).await;
-                    continue;
-                }
-            }
+    /// Asks a processor whether it holds a payment, using its payment details endpoint.
+    async fn confirm_payment(&self, processor: &ProcessorInfo, payload: &PaymentProcessorRequest) -> bool {
+        match self
+            .client
+            .get(format!("{}/payments/{}", processor.url, payload.correlation_id))
+            .timeout(std::time::Duration::from_secs(1))
+            .send()
+            .await
+        {
+            Ok(response) => response.status().is_success(),
+            Err(_) => false,
         }
-        
-        Err("No processors available".into())
     }
-} 
\ No newline at end of file
+}
\ No newline at end of file
@@ -0,0 +1,127 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+use crate::modules::clock::SharedClock;
+
+const COUNT_BITS: u32 = 20;
+const COUNT_MASK: u64 = (1 << COUNT_BITS) - 1;
+const EPOCH_MASK: u64 = (1 << (64 - 2 * COUNT_BITS)) - 1;
+
+/// A window bucket packed into one word: the bucket's epoch in the top 24
+/// bits, then 20 bits for each of the two counts. Packing lets a bucket be
+/// recycled and counted in the same compare-and-swap, so no count is lost
+/// when a bucket rolls over.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct BucketValue {
+    epoch: u64,
+    first: u64,
+    second: u64,
+}
+
+impl BucketValue {
+    fn unpack(word: u64) -> Self {
+        Self {
+            epoch: word >> (2 * COUNT_BITS),
+            first: (word >> COUNT_BITS) & COUNT_MASK,
+            second: word & COUNT_MASK,
+        }
+    }
+
+    /// The bucket's counts for `epoch`, recycled if it still holds counts
+    /// from an older window.
+    fn at(word: u64, epoch: u64) -> Self {
+        let value = Self::unpack(word);
+        if value.epoch == epoch {
+            value
+        } else {
+            Self { epoch, first: 0, second: 0 }
+        }
+    }
+
+    fn pack(self) -> u64 {
+        ((self.epoch & EPOCH_MASK) << (2 * COUNT_BITS))
+            | (self.first.min(COUNT_MASK) << COUNT_BITS)
+            | self.second.min(COUNT_MASK)
+    }
+}
+
+/// A pair of counts over a sliding time window, split into buckets of
+/// atomics so counting never blocks.
+pub(crate) struct SlidingWindow {
+    bucket_millis: u64,
+    buckets: Vec<AtomicU64>,
+    clock: SharedClock,
+}
+
+impl SlidingWindow {
+    pub(crate) fn new(window: Duration, buckets: usize, clock: SharedClock) -> Self {
+        let bucket_count = buckets.max(1);
+        Self {
+            bucket_millis: (window.as_millis() as u64 / bucket_count as u64).max(1),
+            buckets: (0..bucket_count).map(|_| AtomicU64::new(0)).collect(),
+            clock,
+        }
+    }
+
+    /// Both counts summed over the buckets still inside the window.
+    pub(crate) fn totals(&self) -> (u64, u64) {
+        self.totals_except(self.current_epoch(), usize::MAX)
+    }
+
+    pub(crate) fn add(&self, first: u64, second: u64) {
+        let epoch = self.current_epoch();
+        let _ = self.current_bucket(epoch).fetch_update(Ordering::AcqRel, Ordering::Acquire, |word| {
+            let mut value = BucketValue::at(word, epoch);
+            value.first += first;
+            value.second += second;
+            Some(value.pack())
+        });
+    }
+
+    /// Adds to the current bucket only if `allow` accepts the window totals.
+    /// The current bucket is checked and updated in one compare-and-swap, so
+    /// concurrent callers cannot all pass the same check.
+    pub(crate) fn try_add(&self, first: u64, second: u64, allow: impl Fn((u64, u64)) -> bool) -> bool {
+        let epoch = self.current_epoch();
+        let (others_first, others_second) = self.totals_except(epoch, self.bucket_index(epoch));
+        self.current_bucket(epoch)
+            .fetch_update(Ordering::AcqRel, Ordering::Acquire, |word| {
+                let mut value = BucketValue::at(word, epoch);
+                if !allow((others_first + value.first, others_second + value.second)) {
+                    return None;
+                }
+                value.first += first;
+                value.second += second;
+                Some(value.pack())
+            })
+            .is_ok()
+    }
+
+    pub(crate) fn clear(&self) {
+        for bucket in &self.buckets {
+            bucket.store(0, Ordering::Release);
+        }
+    }
+
+    fn current_epoch(&self) -> u64 {
+        (self.clock.now_millis() / self.bucket_millis) & EPOCH_MASK
+    }
+
+    fn bucket_index(&self, epoch: u64) -> usize {
+        (epoch % self.buckets.len() as u64) as usize
+    }
+
+    fn current_bucket(&self, epoch: u64) -> &AtomicU64 {
+        &self.buckets[self.bucket_index(epoch)]
+    }
+
+    fn totals_except(&self, epoch: u64, skipped: usize) -> (u64, u64) {
+        let window = self.buckets.len() as u64;
+        self.buckets
+            .iter()
+            .enumerate()
+            .filter(|(index, _)| *index != skipped)
+            .map(|(_, bucket)| BucketValue::unpack(bucket.load(Ordering::Acquire)))
+            .filter(|bucket| epoch.wrapping_sub(bucket.epoch) & EPOCH_MASK < window)
+            .fold((0, 0), |(first, second), bucket| (first + bucket.first, second + bucket.second))
+    }
+}
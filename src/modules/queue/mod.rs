@@ -0,0 +1,256 @@
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::time::Duration;
+use axum::{
+    extract::State,
+    http::StatusCode,
+    response::IntoResponse,
+};
+use serde::Serialize;
+use tokio::sync::{mpsc, Mutex};
+use crate::modules::SharedServices;
+use crate::modules::config::QueueFullPolicy;
+use crate::modules::models::Money;
+use crate::modules::processors::retry::RetryPolicy;
+use crate::modules::processors::PaymentProcessor;
+
+/// Backoff before the first requeue of a payment that failed with a
+/// retryable error; it doubles with every further failure.
+pub const DEFAULT_REQUEUE_BASE_BACKOFF: Duration = Duration::from_millis(100);
+pub const DEFAULT_REQUEUE_MAX_BACKOFF: Duration = Duration::from_secs(5);
+/// Times a payment is requeued before it is given up on and counted as
+/// failed.
+pub const DEFAULT_MAX_REQUEUES: u32 = 5;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QueuedPayment {
+    pub correlation_id: String,
+    pub amount: Money,
+}
+
+/// A payment on the channel and how many times it has failed so far.
+struct Pending {
+    payment: QueuedPayment,
+    failures: u32,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum EnqueueError {
+    /// The queue is at capacity; the payment is handed back to the caller.
+    Full(QueuedPayment),
+    /// The workers have shut down.
+    Closed,
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+pub struct QueueMetrics {
+    pub depth: usize,
+    pub capacity: usize,
+    pub workers: usize,
+    pub enqueued: u64,
+    pub rejected: u64,
+    pub processed: u64,
+    /// Payments dropped after a non-retryable error or too many requeues.
+    pub failed: u64,
+    /// Retryable failures sent back to the queue.
+    pub requeued: u64,
+    /// Payments waiting out their backoff before going back on the queue;
+    /// they count against `capacity`.
+    pub retrying: usize,
+}
+
+#[derive(Default)]
+struct QueueCounters {
+    enqueued: AtomicU64,
+    rejected: AtomicU64,
+    processed: AtomicU64,
+    failed: AtomicU64,
+    requeued: AtomicU64,
+    retrying: AtomicUsize,
+}
+
+/// Bounded in-process queue drained by a pool of workers that send each
+/// payment through the shared `PaymentProcessor`. Payments were already
+/// answered with 202, so one that fails with a retryable error goes back on
+/// the queue after a backoff instead of being dropped, up to a limit.
+pub struct PaymentQueue {
+    sender: mpsc::Sender<Pending>,
+    capacity: usize,
+    workers: usize,
+    full_policy: QueueFullPolicy,
+    counters: Arc<QueueCounters>,
+}
+
+impl PaymentQueue {
+    /// Creates the queue and spawns `workers` tasks on the current runtime.
+    pub fn start(
+        processor: Arc<PaymentProcessor>,
+        capacity: usize,
+        workers: usize,
+        full_policy: QueueFullPolicy,
+    ) -> Self {
+        Self::start_with_requeue_backoff(
+            processor,
+            capacity,
+            workers,
+            full_policy,
+            DEFAULT_REQUEUE_BASE_BACKOFF,
+            DEFAULT_REQUEUE_MAX_BACKOFF,
+            DEFAULT_MAX_REQUEUES,
+        )
+    }
+
+    /// Like `start`, with the backoff before requeueing a failed payment
+    /// doubling from `base_backoff` up to `max_backoff`, and a payment
+    /// counted as failed once it has been requeued `max_requeues` times.
+    pub fn start_with_requeue_backoff(
+        processor: Arc<PaymentProcessor>,
+        capacity: usize,
+        workers: usize,
+        full_policy: QueueFullPolicy,
+        base_backoff: Duration,
+        max_backoff: Duration,
+        max_requeues: u32,
+    ) -> Self {
+        let backoff = Arc::new(RetryPolicy {
+            base_backoff,
+            max_backoff,
+            jitter: true,
+            ..RetryPolicy::new()
+        });
+        let capacity = capacity.max(1);
+        let workers = workers.max(1);
+        let (sender, receiver) = mpsc::channel(capacity);
+        let receiver = Arc::new(Mutex::new(receiver));
+        let counters = Arc::new(QueueCounters::default());
+
+        for _ in 0..workers {
+            tokio::spawn(run_worker(
+                Arc::clone(&receiver),
+                sender.downgrade(),
+                Arc::clone(&processor),
+                Arc::clone(&backoff),
+                max_requeues,
+                Arc::clone(&counters),
+            ));
+        }
+
+        Self {
+            sender,
+            capacity,
+            workers,
+            full_policy,
+            counters,
+        }
+    }
+
+    /// Enqueues `payment` unless the queued payments plus those waiting to
+    /// be requeued already fill the capacity.
+    pub fn try_enqueue(&self, payment: QueuedPayment) -> Result<(), EnqueueError> {
+        if self.depth() + self.counters.retrying.load(Ordering::Acquire) >= self.capacity {
+            self.counters.rejected.fetch_add(1, Ordering::Relaxed);
+            return Err(EnqueueError::Full(payment));
+        }
+        match self.sender.try_send(Pending { payment, failures: 0 }) {
+            Ok(()) => {
+                self.counters.enqueued.fetch_add(1, Ordering::Relaxed);
+                Ok(())
+            }
+            Err(mpsc::error::TrySendError::Full(pending)) => {
+                self.counters.rejected.fetch_add(1, Ordering::Relaxed);
+                Err(EnqueueError::Full(pending.payment))
+            }
+            Err(mpsc::error::TrySendError::Closed(_)) => Err(EnqueueError::Closed),
+        }
+    }
+
+    pub fn full_policy(&self) -> QueueFullPolicy {
+        self.full_policy
+    }
+
+    pub fn depth(&self) -> usize {
+        self.sender.max_capacity() - self.sender.capacity()
+    }
+
+    pub fn metrics(&self) -> QueueMetrics {
+        QueueMetrics {
+            depth: self.depth(),
+            capacity: self.capacity,
+            workers: self.workers,
+            enqueued: self.counters.enqueued.load(Ordering::Relaxed),
+            rejected: self.counters.rejected.load(Ordering::Relaxed),
+            processed: self.counters.processed.load(Ordering::Relaxed),
+            failed: self.counters.failed.load(Ordering::Relaxed),
+            requeued: self.counters.requeued.load(Ordering::Relaxed),
+            retrying: self.counters.retrying.load(Ordering::Relaxed),
+        }
+    }
+}
+
+pub async fn get_queue_metrics(
+    State(services): State<SharedServices>,
+) -> impl IntoResponse {
+    match &services.payment_queue {
+        Some(queue) => (StatusCode::OK, axum::Json(queue.metrics())).into_response(),
+        None => StatusCode::NOT_FOUND.into_response(),
+    }
+}
+
+async fn run_worker(
+    receiver: Arc<Mutex<mpsc::Receiver<Pending>>>,
+    // Weak, so the workers stop once the queue itself is dropped
+    sender: mpsc::WeakSender<Pending>,
+    processor: Arc<PaymentProcessor>,
+    backoff: Arc<RetryPolicy>,
+    max_requeues: u32,
+    counters: Arc<QueueCounters>,
+) {
+    loop {
+        // Hold the lock only while waiting for the next item so the other
+        // workers can pick up payments while this one is processing.
+        let next = receiver.lock().await.recv().await;
+        let Some(Pending { payment, failures }) = next else {
+            break;
+        };
+
+        match processor.process_payment(&payment.correlation_id, payment.amount).await {
+            Ok(_) => {
+                counters.processed.fetch_add(1, Ordering::Relaxed);
+            }
+            Err(e) if e.is_retryable() && failures < max_requeues => {
+                let failures = failures + 1;
+                let delay = backoff.backoff(failures);
+                log::warn!(
+                    "Queued payment {} failed ({} so far), requeueing in {:?}: {}",
+                    payment.correlation_id, failures, delay, e
+                );
+                counters.requeued.fetch_add(1, Ordering::Relaxed);
+                counters.retrying.fetch_add(1, Ordering::AcqRel);
+                tokio::spawn(requeue(sender.clone(), Pending { payment, failures }, delay, Arc::clone(&counters)));
+            }
+            Err(e) => {
+                counters.failed.fetch_add(1, Ordering::Relaxed);
+                log::warn!(
+                    "Queued payment {} failed after {} requeues, dropping it: {}",
+                    payment.correlation_id, failures, e
+                );
+            }
+        }
+    }
+}
+
+/// Puts `pending` back on the queue after `delay`. Its slot stays reserved
+/// through `retrying`, so the send only waits while racing new payments.
+async fn requeue(sender: mpsc::WeakSender<Pending>, pending: Pending, delay: Duration, counters: Arc<QueueCounters>) {
+    tokio::time::sleep(delay).await;
+    let correlation_id = pending.payment.correlation_id.clone();
+    let sent = match sender.upgrade() {
+        Some(sender) => sender.send(pending).await.is_ok(),
+        None => false,
+    };
+    counters.retrying.fetch_sub(1, Ordering::AcqRel);
+    if !sent {
+        counters.failed.fetch_add(1, Ordering::Relaxed);
+        log::error!("Queue shut down before payment {} could be retried", correlation_id);
+    }
+}
@@ -0,0 +1,143 @@
+use std::sync::Arc;
+use std::time::Duration;
+use serde::{Serialize, Deserialize};
+use crate::modules::cache::CacheManager;
+use crate::modules::models::Money;
+
+const KEY_PREFIX: &str = "ledger:";
+/// Width of the time buckets totals are kept in.
+const BUCKET_MILLIS: u64 = 1_000;
+/// How long a recorded correlationId is remembered to keep it from being
+/// counted twice.
+pub const DEFAULT_RECORDED_TTL: Duration = Duration::from_secs(24 * 60 * 60);
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProcessorSummary {
+    #[serde(rename = "totalRequests")]
+    pub total_requests: u64,
+    #[serde(rename = "totalAmount")]
+    pub total_amount: Money,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PaymentsSummary {
+    pub default: ProcessorSummary,
+    pub fallback: ProcessorSummary,
+}
+
+/// Totals of the payments accepted by each processor, kept in the shared
+/// cache so every replica's `/payments-summary` covers the payments all of
+/// them handled.
+///
+/// Each processor has one hash holding a request count and an amount in
+/// cents per second of `requestedAt`, so the size grows with the seconds
+/// that saw traffic rather than with the number of payments. The hashes
+/// have no TTL; under a volatile-* policy they are never evicted.
+pub struct PaymentLedger {
+    cache: Arc<CacheManager>,
+    recorded_ttl: Duration,
+}
+
+impl Default for PaymentLedger {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PaymentLedger {
+    /// Ledger in a cache of its own, seen by this process only.
+    pub fn new() -> Self {
+        Self::with_cache(Arc::new(CacheManager::new()))
+    }
+
+    pub fn with_cache(cache: Arc<CacheManager>) -> Self {
+        Self {
+            cache,
+            recorded_ttl: DEFAULT_RECORDED_TTL,
+        }
+    }
+
+    /// Records an accepted payment. Each correlationId is recorded at most
+    /// once; returns `false` if it was already in the ledger.
+    pub async fn record(&self, correlation_id: &str, processor: &str, amount: Money, requested_at: u64) -> bool {
+        match self.cache.set_nx(&Self::recorded_key(correlation_id), &processor, self.recorded_ttl).await {
+            Ok(true) => {}
+            Ok(false) => return false,
+            Err(e) => log::warn!("Failed to mark payment {} as recorded: {}", correlation_id, e),
+        }
+
+        let key = Self::totals_key(processor);
+        let bucket = requested_at - requested_at % BUCKET_MILLIS;
+        let counted = self.cache.hincr_by(&key, &format!("{}:requests", bucket), 1).await;
+        let summed = self.cache.hincr_by(&key, &format!("{}:cents", bucket), amount.cents()).await;
+        if let Err(e) = counted.and(summed) {
+            log::error!("Failed to record payment {} in the ledger: {}", correlation_id, e);
+        }
+        true
+    }
+
+    pub async fn contains(&self, correlation_id: &str) -> bool {
+        matches!(self.cache.get::<String>(&Self::recorded_key(correlation_id)).await, Ok(Some(_)))
+    }
+
+    /// Aggregates payments whose `requested_at` (epoch millis) falls within
+    /// the inclusive `[from, to]` window. Missing bounds are open; bounds
+    /// are widened to whole seconds.
+    pub async fn summary(&self, from: Option<u64>, to: Option<u64>) -> PaymentsSummary {
+        PaymentsSummary {
+            default: self.processor_summary("default", from, to).await,
+            fallback: self.processor_summary("fallback", from, to).await,
+        }
+    }
+
+    /// Payments recorded across all processors.
+    pub async fn len(&self) -> u64 {
+        let summary = self.summary(None, None).await;
+        summary.default.total_requests + summary.fallback.total_requests
+    }
+
+    pub async fn is_empty(&self) -> bool {
+        self.len().await == 0
+    }
+
+    async fn processor_summary(&self, processor: &str, from: Option<u64>, to: Option<u64>) -> ProcessorSummary {
+        let fields = match self.cache.hget_all(&Self::totals_key(processor)).await {
+            Ok(fields) => fields,
+            Err(e) => {
+                log::error!("Failed to read the {} ledger: {}", processor, e);
+                return ProcessorSummary::default();
+            }
+        };
+
+        let mut summary = ProcessorSummary::default();
+        for (field, value) in fields {
+            let Some((bucket, total)) = field.split_once(':') else {
+                continue;
+            };
+            let Ok(bucket) = bucket.parse::<u64>() else {
+                continue;
+            };
+            if from.is_some_and(|from| bucket + BUCKET_MILLIS <= from) {
+                continue;
+            }
+            if to.is_some_and(|to| bucket > to) {
+                continue;
+            }
+            let value: i64 = value.parse().unwrap_or(0);
+            match total {
+                "requests" => summary.total_requests += value.max(0) as u64,
+                "cents" => summary.total_amount += Money::from_cents(value),
+                _ => {}
+            }
+        }
+        summary
+    }
+
+    fn recorded_key(correlation_id: &str) -> String {
+        format!("{}recorded:{}", KEY_PREFIX, correlation_id)
+    }
+
+    fn totals_key(processor: &str) -> String {
+        format!("{}totals:{}", KEY_PREFIX, processor)
+    }
+}
@@ -0,0 +1,42 @@
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    response::IntoResponse,
+};
+use serde::Deserialize;
+
+use crate::modules::SharedServices;
+use crate::modules::clock::parse_iso8601;
+
+pub mod ledger;
+
+#[derive(Debug, Deserialize)]
+pub struct SummaryQuery {
+    pub from: Option<String>,
+    pub to: Option<String>,
+}
+
+pub async fn get_payments_summary(
+    State(services): State<SharedServices>,
+    Query(query): Query<SummaryQuery>,
+) -> impl IntoResponse {
+    let from = match parse_timestamp(query.from.as_deref()) {
+        Ok(from) => from,
+        Err(_) => return StatusCode::BAD_REQUEST.into_response(),
+    };
+    let to = match parse_timestamp(query.to.as_deref()) {
+        Ok(to) => to,
+        Err(_) => return StatusCode::BAD_REQUEST.into_response(),
+    };
+
+    let summary = services.payment_processor.get_payments_summary(from, to).await;
+    (StatusCode::OK, axum::Json(summary)).into_response()
+}
+
+/// Parses an optional ISO-8601 UTC timestamp into epoch milliseconds.
+fn parse_timestamp(value: Option<&str>) -> Result<Option<u64>, chrono::ParseError> {
+    match value {
+        Some(value) if !value.is_empty() => parse_iso8601(value).map(Some),
+        _ => Ok(None),
+    }
+}
@@ -0,0 +1,92 @@
+mod common;
+
+use axum::{Router, routing::{get, post}};
+use httpmock::MockServer;
+use httpmock::Method::POST;
+use reqwest::Client;
+use rinha::modules::cache::CacheManager;
+use rinha::modules::health::HealthManager;
+use rinha::modules::payment::create_payment;
+use rinha::modules::processors::PaymentProcessor;
+use rinha::modules::summary::get_payments_summary;
+use rinha::modules::{ApplicationServices, SharedServices};
+use std::net::SocketAddr;
+use std::sync::Arc;
+
+async fn services_for(server: &MockServer) -> SharedServices {
+    let selector = common::selector_for(server);
+    selector.update_processor_rate("default", 0.01).await;
+    selector.update_processor_rate("fallback", 0.02).await;
+
+    Arc::new(ApplicationServices::with_services(
+        PaymentProcessor::with_selector(selector),
+        HealthManager::new(),
+        CacheManager::new(),
+    ))
+}
+
+async fn start_server(services: SharedServices) -> SocketAddr {
+    let app = Router::new()
+        .route("/payments", post(create_payment))
+        .route("/payments-summary", get(get_payments_summary))
+        .with_state(services);
+    common::start_server(app).await
+}
+
+#[tokio::test]
+async fn test_payments_share_injected_processor() {
+    let server = MockServer::start_async().await;
+    let _default_mock = server.mock_async(|when, then| {
+        when.method(POST).path("/default/payments");
+        then.status(200).json_body_obj(&serde_json::json!({"message": "ok"}));
+    }).await;
+    let services = services_for(&server).await;
+    let addr = start_server(Arc::clone(&services)).await;
+    let client = Client::new();
+
+    for correlation_id in [
+        "4a7901b8-7d26-4d9d-aa19-4dc1c7cf60b3",
+        "5b8a02c9-8e37-4e0e-bb2a-5ed2d8d071c4",
+    ] {
+        let resp = client.post(format!("http://{}/payments", addr))
+            .json(&serde_json::json!({"correlationId": correlation_id, "amount": 19.9}))
+            .send().await.unwrap();
+        assert_eq!(resp.status().as_u16(), 200);
+    }
+
+    // Both requests were recorded by the same injected selector
+    let resp = client.get(format!("http://{}/payments-summary", addr)).send().await.unwrap();
+    let body: serde_json::Value = resp.json().await.unwrap();
+    let total = body["default"]["totalRequests"].as_u64().unwrap()
+        + body["fallback"]["totalRequests"].as_u64().unwrap();
+    assert_eq!(total, 2);
+    assert_eq!(services.payment_processor.get_ledger().len().await, 2);
+}
+
+#[tokio::test]
+async fn test_processor_failures_persist_across_requests() {
+    let server = MockServer::start_async().await;
+    let _default_mock = server.mock_async(|when, then| {
+        when.method(POST).path("/default/payments");
+        then.status(500);
+    }).await;
+    let _fallback_mock = server.mock_async(|when, then| {
+        when.method(POST).path("/fallback/payments");
+        then.status(200).json_body_obj(&serde_json::json!({"message": "ok"}));
+    }).await;
+    let services = services_for(&server).await;
+    let addr = start_server(Arc::clone(&services)).await;
+
+    let resp = Client::new().post(format!("http://{}/payments", addr))
+        .json(&serde_json::json!({
+            "correlationId": "4a7901b8-7d26-4d9d-aa19-4dc1c7cf60b3",
+            "amount": 19.9
+        }))
+        .send().await.unwrap();
+    assert_eq!(resp.status().as_u16(), 200);
+
+    // The failure is counted by the shared selector's circuit breaker
+    let info = services.payment_processor.get_processor_info().await;
+    assert_eq!(info["default"].failure_count, 1);
+    assert_eq!(info["fallback"].failure_count, 0);
+}
@@ -0,0 +1,338 @@
+use rinha::modules::cache::backend::{CacheBackend, EntryTtl};
+use rinha::modules::cache::redis::RedisCache;
+use rinha::modules::cache::redis_server::{RedisServerCache, RedisSettings};
+use rinha::modules::cache::CacheManager;
+use rinha::modules::config::{CacheBackendKind, Config};
+use rinha::modules::error::PaymentError;
+use std::time::{Duration, Instant};
+
+/// The in-memory backend, plus the Redis server at `REDIS_URL` (or a local
+/// redis-server) when one answers, so both are held to the same
+/// assertions.
+async fn backends(prefix: &str) -> Vec<Box<dyn CacheBackend>> {
+    let mut backends: Vec<Box<dyn CacheBackend>> = vec![Box::new(RedisCache::new())];
+    let settings = RedisSettings {
+        url: std::env::var("REDIS_URL").unwrap_or_else(|_| "redis://127.0.0.1:6379".to_string()),
+        key_prefix: format!("rinha-test:{}:{}:", prefix, uuid::Uuid::new_v4()),
+        ..RedisSettings::default()
+    };
+    if let Ok(cache) = RedisServerCache::new(settings) {
+        if cache.ping().await.is_ok() {
+            backends.push(Box::new(cache));
+        }
+    }
+    backends
+}
+
+#[tokio::test]
+async fn test_set_get_remove_round_trip() {
+    for cache in backends("round-trip").await {
+        cache.set_raw("key", "\"value\"".to_string(), Some(Duration::from_secs(60))).await.unwrap();
+        assert_eq!(cache.get_raw("key").await.unwrap().as_deref(), Some("\"value\""));
+        assert_eq!(cache.get_entry_count().await, 1);
+
+        assert!(cache.remove("key").await.unwrap());
+        assert!(!cache.remove("key").await.unwrap());
+        assert_eq!(cache.get_raw("key").await.unwrap(), None);
+    }
+}
+
+#[tokio::test]
+async fn test_clear_removes_every_entry() {
+    for cache in backends("clear").await {
+        for i in 0..5 {
+            cache.set_raw(&format!("key-{}", i), i.to_string(), Some(Duration::from_secs(60))).await.unwrap();
+        }
+        assert_eq!(cache.get_entry_count().await, 5);
+
+        cache.clear().await.unwrap();
+
+        assert_eq!(cache.get_entry_count().await, 0);
+        assert_eq!(cache.get_raw("key-0").await.unwrap(), None);
+    }
+}
+
+#[tokio::test]
+async fn test_stats_report_the_stored_entries() {
+    for cache in backends("stats").await {
+        cache.set_raw("a", "1".to_string(), None).await.unwrap();
+        cache.hincr_by("b", "field", 1).await.unwrap();
+
+        // A Redis server counts every key in the database, not just ours
+        let stats = cache.stats().await;
+        assert!(stats.memory.entry_count >= 2);
+        assert!(stats.memory.used_bytes > 0);
+    }
+}
+
+#[tokio::test]
+async fn test_lease_is_exclusive_until_it_expires() {
+    for cache in backends("lease").await {
+        let ttl = Duration::from_secs(60);
+
+        assert!(cache.acquire_lease("lease", "replica-1", ttl).await.unwrap());
+        assert!(cache.acquire_lease("lease", "replica-1", ttl).await.unwrap());
+        assert!(!cache.acquire_lease("lease", "replica-2", ttl).await.unwrap());
+
+        cache.remove("lease").await.unwrap();
+        assert!(cache.acquire_lease("lease", "replica-2", ttl).await.unwrap());
+    }
+}
+
+#[tokio::test]
+async fn test_ttl_expire_and_persist() {
+    for cache in backends("ttl").await {
+        cache.set_raw("persistent", "1".to_string(), None).await.unwrap();
+        cache.set_raw("volatile", "2".to_string(), Some(Duration::from_secs(60))).await.unwrap();
+
+        assert_eq!(cache.ttl("missing").await.unwrap(), EntryTtl::Missing);
+        assert_eq!(cache.ttl("persistent").await.unwrap(), EntryTtl::Persistent);
+        assert!(matches!(
+            cache.ttl("volatile").await.unwrap(),
+            EntryTtl::Expires(left) if left > Duration::from_secs(59) && left <= Duration::from_secs(60)
+        ));
+
+        assert!(cache.persist("volatile").await.unwrap());
+        assert_eq!(cache.ttl("volatile").await.unwrap(), EntryTtl::Persistent);
+        assert!(cache.expire("persistent", Duration::from_millis(50)).await.unwrap());
+        assert!(!cache.expire("missing", Duration::from_millis(50)).await.unwrap());
+
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert_eq!(cache.get_raw("persistent").await.unwrap(), None);
+        assert_eq!(cache.get_raw("volatile").await.unwrap().as_deref(), Some("2"));
+    }
+}
+
+#[tokio::test]
+async fn test_cache_manager_round_trips_typed_values() {
+    for backend in backends("typed").await {
+        let manager = CacheManager::with_backend(backend);
+
+        manager.set("numbers", &vec![1, 2, 3], Duration::from_secs(60)).await.unwrap();
+
+        assert_eq!(manager.get::<Vec<i32>>("numbers").await.unwrap(), Some(vec![1, 2, 3]));
+        assert_eq!(manager.get::<Vec<i32>>("missing").await.unwrap(), None);
+    }
+}
+
+#[tokio::test]
+async fn test_incr_by_counts_from_zero_and_keeps_the_ttl() {
+    for cache in backends("incr").await {
+        assert_eq!(cache.incr_by("counter", 5).await.unwrap(), 5);
+        assert_eq!(cache.incr_by("counter", -2).await.unwrap(), 3);
+        assert_eq!(cache.get_raw("counter").await.unwrap().as_deref(), Some("3"));
+        assert_eq!(cache.ttl("counter").await.unwrap(), EntryTtl::Persistent);
+
+        cache.set_raw("volatile", "10".to_string(), Some(Duration::from_secs(60))).await.unwrap();
+        assert_eq!(cache.incr_by("volatile", 1).await.unwrap(), 11);
+        assert!(matches!(cache.ttl("volatile").await.unwrap(), EntryTtl::Expires(_)));
+    }
+}
+
+#[tokio::test]
+async fn test_incr_by_rejects_non_integers_and_overflow() {
+    for cache in backends("incr-errors").await {
+        cache.set_raw("text", "\"abc\"".to_string(), None).await.unwrap();
+        cache.set_raw("max", i64::MAX.to_string(), None).await.unwrap();
+
+        assert!(matches!(cache.incr_by("text", 1).await, Err(PaymentError::Serialization(_))));
+        assert!(matches!(cache.incr_by("max", 1).await, Err(PaymentError::Serialization(_))));
+        assert!(matches!(cache.incr_by_float("text", 1.0).await, Err(PaymentError::Serialization(_))));
+        assert_eq!(cache.get_raw("max").await.unwrap(), Some(i64::MAX.to_string()));
+    }
+}
+
+#[tokio::test]
+async fn test_incr_by_float() {
+    for cache in backends("incr-float").await {
+        assert_eq!(cache.incr_by_float("amount", 10.5).await.unwrap(), 10.5);
+        assert_eq!(cache.incr_by_float("amount", 0.25).await.unwrap(), 10.75);
+        assert_eq!(cache.incr_by("amount", 1).await.ok(), None);
+
+        cache.set_raw("integer", "3".to_string(), None).await.unwrap();
+        assert_eq!(cache.incr_by_float("integer", 1.5).await.unwrap(), 4.5);
+    }
+}
+
+#[tokio::test]
+async fn test_set_nx_only_stores_missing_keys() {
+    for cache in backends("set-nx").await {
+        let ttl = Some(Duration::from_secs(60));
+
+        assert!(cache.set_nx("key", "1".to_string(), ttl).await.unwrap());
+        assert!(!cache.set_nx("key", "2".to_string(), ttl).await.unwrap());
+        assert_eq!(cache.get_raw("key").await.unwrap().as_deref(), Some("1"));
+        assert!(matches!(cache.ttl("key").await.unwrap(), EntryTtl::Expires(_)));
+
+        assert!(cache.set_nx("persistent", "1".to_string(), None).await.unwrap());
+        assert_eq!(cache.ttl("persistent").await.unwrap(), EntryTtl::Persistent);
+    }
+}
+
+#[tokio::test]
+async fn test_compare_and_swap_only_replaces_the_expected_value() {
+    for cache in backends("cas").await {
+        cache.set_raw("key", "1".to_string(), None).await.unwrap();
+
+        assert!(!cache.compare_and_swap("key", "2", "3".to_string(), None).await.unwrap());
+        assert!(!cache.compare_and_swap("missing", "1", "3".to_string(), None).await.unwrap());
+        assert_eq!(cache.get_raw("missing").await.unwrap(), None);
+
+        assert!(cache.compare_and_swap("key", "1", "3".to_string(), Some(Duration::from_secs(60))).await.unwrap());
+        assert_eq!(cache.get_raw("key").await.unwrap().as_deref(), Some("3"));
+        assert!(matches!(cache.ttl("key").await.unwrap(), EntryTtl::Expires(_)));
+    }
+}
+
+#[tokio::test]
+async fn test_hash_field_increments() {
+    for cache in backends("hash").await {
+        assert_eq!(cache.hincr_by("summary", "default.totalRequests", 1).await.unwrap(), 1);
+        assert_eq!(cache.hincr_by("summary", "default.totalRequests", 2).await.unwrap(), 3);
+        assert_eq!(cache.hincr_by_float("summary", "default.totalAmount", 19.5).await.unwrap(), 19.5);
+        assert_eq!(cache.hincr_by("summary", "fallback.totalRequests", 1).await.unwrap(), 1);
+
+        let hash = cache.hget_all("summary").await.unwrap();
+        assert_eq!(hash.len(), 3);
+        assert_eq!(hash["default.totalRequests"], "3");
+        assert_eq!(hash["default.totalAmount"], "19.5");
+        assert!(cache.hget_all("missing").await.unwrap().is_empty());
+
+        assert!(matches!(
+            cache.hincr_by("summary", "default.totalAmount", 1).await,
+            Err(PaymentError::Serialization(_))
+        ));
+        cache.set_raw("plain", "1".to_string(), None).await.unwrap();
+        assert!(matches!(cache.hincr_by("plain", "field", 1).await, Err(PaymentError::Serialization(_))));
+    }
+}
+
+#[tokio::test]
+async fn test_string_commands_on_a_hash_fail_with_wrong_type() {
+    for cache in backends("wrong-type").await {
+        cache.hincr_by("hash", "field", 1).await.unwrap();
+
+        assert!(matches!(cache.get_raw("hash").await, Err(PaymentError::Serialization(_))));
+        assert!(matches!(cache.incr_by("hash", 1).await, Err(PaymentError::Serialization(_))));
+        assert!(matches!(cache.incr_by_float("hash", 1.0).await, Err(PaymentError::Serialization(_))));
+        assert!(!cache.set_nx("hash", "1".to_string(), None).await.unwrap());
+
+        // A string that happens to be a JSON object is still not a hash
+        cache.set_raw("object", "{\"field\":\"1\"}".to_string(), None).await.unwrap();
+        assert!(matches!(cache.hget_all("object").await, Err(PaymentError::Serialization(_))));
+
+        // SET replaces a hash with a string
+        cache.set_raw("hash", "1".to_string(), None).await.unwrap();
+        assert_eq!(cache.get_raw("hash").await.unwrap().as_deref(), Some("1"));
+    }
+}
+
+#[tokio::test]
+async fn test_float_increments_are_stored_like_incrbyfloat() {
+    for cache in backends("float-format").await {
+        assert_eq!(cache.incr_by_float("sum", 0.1).await.unwrap(), 0.1);
+        assert_eq!(cache.incr_by_float("sum", 0.2).await.unwrap(), 0.3);
+        assert_eq!(cache.get_raw("sum").await.unwrap().as_deref(), Some("0.3"));
+
+        assert_eq!(cache.incr_by_float("sum", 2.7).await.unwrap(), 3.0);
+        assert_eq!(cache.get_raw("sum").await.unwrap().as_deref(), Some("3"));
+
+        cache.incr_by_float("large", 1e20).await.unwrap();
+        assert_eq!(cache.get_raw("large").await.unwrap().as_deref(), Some("100000000000000000000"));
+        cache.incr_by_float("negative", -1.5).await.unwrap();
+        assert_eq!(cache.get_raw("negative").await.unwrap().as_deref(), Some("-1.5"));
+
+        cache.hincr_by_float("hash", "field", 0.1).await.unwrap();
+        cache.hincr_by_float("hash", "field", 0.2).await.unwrap();
+        assert_eq!(cache.hget_all("hash").await.unwrap()["field"], "0.3");
+    }
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn test_concurrent_increments_are_not_lost() {
+    for backend in backends("concurrent-incr").await {
+        let manager = std::sync::Arc::new(CacheManager::with_backend(backend));
+
+        let mut tasks = Vec::new();
+        for _ in 0..8 {
+            let manager = std::sync::Arc::clone(&manager);
+            tasks.push(tokio::spawn(async move {
+                for _ in 0..100 {
+                    manager.incr_by("counter", 1).await.unwrap();
+                    manager.hincr_by("hash", "field", 2).await.unwrap();
+                }
+            }));
+        }
+        for task in tasks {
+            task.await.unwrap();
+        }
+
+        assert_eq!(manager.get::<i64>("counter").await.unwrap(), Some(800));
+        assert_eq!(manager.hget_all("hash").await.unwrap()["field"], "1600");
+    }
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn test_only_one_compare_and_swap_wins() {
+    for backend in backends("concurrent-cas").await {
+        let manager = std::sync::Arc::new(CacheManager::with_backend(backend));
+        let ttl = Duration::from_secs(60);
+        manager.set("version", &1, ttl).await.unwrap();
+
+        let mut tasks = Vec::new();
+        for candidate in 0..8 {
+            let manager = std::sync::Arc::clone(&manager);
+            tasks.push(tokio::spawn(async move { manager.compare_and_swap("version", &1, &(10 + candidate), ttl).await.unwrap() }));
+        }
+        let mut winners = 0;
+        for task in tasks {
+            winners += task.await.unwrap() as usize;
+        }
+
+        assert_eq!(winners, 1);
+        assert!(manager.get::<i32>("version").await.unwrap().unwrap() >= 10);
+        assert!(!manager.set_nx("version", &0, ttl).await.unwrap());
+    }
+}
+
+#[tokio::test]
+async fn test_unreachable_redis_fails_fast_with_cache_error() {
+    let cache = RedisServerCache::new(RedisSettings {
+        url: "redis://127.0.0.1:1".to_string(),
+        connect_timeout: Duration::from_millis(200),
+        ..RedisSettings::default()
+    })
+    .unwrap();
+
+    let started = Instant::now();
+    let result = cache.get_raw("key").await;
+
+    assert!(matches!(result, Err(PaymentError::Cache(_))));
+    assert!(started.elapsed() < Duration::from_secs(2));
+    assert!(result.unwrap_err().is_retryable());
+}
+
+#[test]
+fn test_invalid_redis_url_is_rejected() {
+    let result = RedisServerCache::new(RedisSettings {
+        url: "not a url".to_string(),
+        ..RedisSettings::default()
+    });
+
+    assert!(matches!(result, Err(PaymentError::Cache(_))));
+}
+
+#[test]
+fn test_cache_manager_picks_backend_from_config() {
+    let mut config = Config::new();
+
+    config.cache_backend = CacheBackendKind::Memory;
+    assert_eq!(CacheManager::from_config(&config).backend_name(), "memory");
+
+    config.cache_backend = CacheBackendKind::Redis;
+    config.redis.url = "redis://127.0.0.1:1".to_string();
+    assert_eq!(CacheManager::from_config(&config).backend_name(), "redis");
+
+    config.redis.url = "not a url".to_string();
+    assert_eq!(CacheManager::from_config(&config).backend_name(), "memory");
+}
@@ -0,0 +1,189 @@
+use rinha::modules::cache::compute::ComputePolicy;
+use rinha::modules::cache::CacheManager;
+use rinha::modules::clock::ManualClock;
+use rinha::modules::error::PaymentError;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+const START_MILLIS: u64 = 1_752_582_896_000;
+const TTL: Duration = Duration::from_secs(1);
+
+fn manager_with_clock() -> (Arc<CacheManager>, Arc<ManualClock>) {
+    let clock = Arc::new(ManualClock::new(START_MILLIS));
+    let mut cache = CacheManager::new();
+    cache.set_clock(clock.clone());
+    (Arc::new(cache), clock)
+}
+
+/// Counts calls and answers `value` after a short delay, so concurrent
+/// callers overlap.
+async fn slow_compute(calls: Arc<AtomicUsize>, value: u64) -> Result<u64, PaymentError> {
+    calls.fetch_add(1, Ordering::SeqCst);
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    Ok(value)
+}
+
+#[tokio::test]
+async fn test_concurrent_misses_share_one_computation() {
+    let (cache, _clock) = manager_with_clock();
+    let calls = Arc::new(AtomicUsize::new(0));
+
+    let mut tasks = Vec::new();
+    for _ in 0..50 {
+        let cache = Arc::clone(&cache);
+        let calls = Arc::clone(&calls);
+        tasks.push(tokio::spawn(async move {
+            cache.get_or_compute("summary", TTL, || slow_compute(calls, 42)).await
+        }));
+    }
+    for task in tasks {
+        assert_eq!(task.await.unwrap(), Ok(42));
+    }
+
+    assert_eq!(calls.load(Ordering::SeqCst), 1);
+    let cached: u64 = cache.get_or_compute("summary", TTL, || slow_compute(Arc::clone(&calls), 7)).await.unwrap();
+    assert_eq!(cached, 42);
+    assert_eq!(calls.load(Ordering::SeqCst), 1);
+}
+
+#[tokio::test]
+async fn test_value_is_recomputed_after_ttl() {
+    let (cache, clock) = manager_with_clock();
+    let calls = Arc::new(AtomicUsize::new(0));
+
+    let first: u64 = cache.get_or_compute("key", TTL, || slow_compute(Arc::clone(&calls), 1)).await.unwrap();
+    clock.advance(Duration::from_millis(1500));
+    let second: u64 = cache.get_or_compute("key", TTL, || slow_compute(Arc::clone(&calls), 2)).await.unwrap();
+
+    assert_eq!((first, second), (1, 2));
+    assert_eq!(calls.load(Ordering::SeqCst), 2);
+}
+
+#[tokio::test]
+async fn test_errors_reach_every_waiter_and_are_not_cached() {
+    let (cache, _clock) = manager_with_clock();
+    let calls = Arc::new(AtomicUsize::new(0));
+
+    let failing = |calls: Arc<AtomicUsize>| async move {
+        calls.fetch_add(1, Ordering::SeqCst);
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        Err::<u64, _>(PaymentError::AllProcessorsDown)
+    };
+    let (first, second) = tokio::join!(
+        cache.get_or_compute("key", TTL, || failing(Arc::clone(&calls))),
+        cache.get_or_compute("key", TTL, || failing(Arc::clone(&calls))),
+    );
+    assert_eq!(first, Err(PaymentError::AllProcessorsDown));
+    assert_eq!(second, Err(PaymentError::AllProcessorsDown));
+    assert_eq!(calls.load(Ordering::SeqCst), 1);
+
+    let retried: u64 = cache.get_or_compute("key", TTL, || slow_compute(Arc::clone(&calls), 5)).await.unwrap();
+    assert_eq!(retried, 5);
+    assert_eq!(calls.load(Ordering::SeqCst), 2);
+}
+
+#[tokio::test]
+async fn test_error_ttl_caches_failures() {
+    let (cache, clock) = manager_with_clock();
+    let calls = Arc::new(AtomicUsize::new(0));
+    let mut policy = ComputePolicy::new(TTL);
+    policy.error_ttl = Some(Duration::from_millis(200));
+
+    let counted = Arc::clone(&calls);
+    let failed = cache
+        .get_or_compute_with("key", &policy, move || async move {
+            counted.fetch_add(1, Ordering::SeqCst);
+            Err::<u64, _>(PaymentError::Timeout { processor: "default".to_string() })
+        })
+        .await;
+    let cached = cache.get_or_compute_with("key", &policy, {
+        let calls = Arc::clone(&calls);
+        move || slow_compute(calls, 1)
+    }).await;
+
+    assert_eq!(failed, cached);
+    assert_eq!(calls.load(Ordering::SeqCst), 1);
+
+    clock.advance(Duration::from_millis(300));
+    let recovered = cache.get_or_compute_with("key", &policy, {
+        let calls = Arc::clone(&calls);
+        move || slow_compute(calls, 1)
+    }).await;
+    assert_eq!(recovered, Ok(1));
+}
+
+#[tokio::test]
+async fn test_stale_value_is_served_while_refreshing() {
+    let (cache, clock) = manager_with_clock();
+    let calls = Arc::new(AtomicUsize::new(0));
+    let mut policy = ComputePolicy::new(TTL);
+    policy.stale_while_revalidate = Some(Duration::from_secs(10));
+    let compute = |value: u64| {
+        let calls = Arc::clone(&calls);
+        move || slow_compute(calls, value)
+    };
+
+    assert_eq!(cache.get_or_compute_with("key", &policy, compute(1)).await, Ok(1));
+    clock.advance(Duration::from_secs(2));
+
+    // Both stale reads answer at once and trigger a single refresh
+    assert_eq!(cache.get_or_compute_with("key", &policy, compute(2)).await, Ok(1));
+    assert_eq!(cache.get_or_compute_with("key", &policy, compute(3)).await, Ok(1));
+    for _ in 0..100 {
+        if cache.get_or_compute_with("key", &policy, compute(4)).await == Ok(2) {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+
+    assert_eq!(cache.get_or_compute_with("key", &policy, compute(5)).await, Ok(2));
+    assert_eq!(calls.load(Ordering::SeqCst), 2);
+}
+
+#[tokio::test]
+async fn test_value_past_the_stale_window_is_recomputed_inline() {
+    let (cache, clock) = manager_with_clock();
+    let calls = Arc::new(AtomicUsize::new(0));
+    let mut policy = ComputePolicy::new(TTL);
+    policy.stale_while_revalidate = Some(Duration::from_secs(10));
+
+    let first = cache.get_or_compute_with("key", &policy, {
+        let calls = Arc::clone(&calls);
+        move || slow_compute(calls, 1)
+    }).await;
+    clock.advance(Duration::from_secs(20));
+    let second = cache.get_or_compute_with("key", &policy, {
+        let calls = Arc::clone(&calls);
+        move || slow_compute(calls, 2)
+    }).await;
+
+    assert_eq!((first, second), (Ok(1), Ok(2)));
+}
+
+#[tokio::test]
+async fn test_waiter_takes_over_when_the_computing_caller_is_cancelled() {
+    let (cache, _clock) = manager_with_clock();
+
+    let leader = {
+        let cache = Arc::clone(&cache);
+        tokio::spawn(async move {
+            cache
+                .get_or_compute("key", TTL, || async {
+                    tokio::time::sleep(Duration::from_secs(60)).await;
+                    Ok::<u64, PaymentError>(1)
+                })
+                .await
+        })
+    };
+    tokio::time::sleep(Duration::from_millis(20)).await;
+    let follower = {
+        let cache = Arc::clone(&cache);
+        tokio::spawn(async move { cache.get_or_compute("key", TTL, || async { Ok::<u64, PaymentError>(2) }).await })
+    };
+    tokio::time::sleep(Duration::from_millis(20)).await;
+    leader.abort();
+
+    let result = tokio::time::timeout(Duration::from_secs(1), follower).await.unwrap().unwrap();
+    assert_eq!(result, Ok(2));
+}
@@ -0,0 +1,152 @@
+use rinha::modules::cache::lru::LruMap;
+use rinha::modules::cache::redis::{CachePolicy, RedisCache};
+use rinha::modules::cache::CacheManager;
+use rinha::modules::clock::ManualClock;
+use rinha::modules::error::{CacheRejection, PaymentError};
+use std::collections::HashSet;
+use std::sync::Arc;
+use std::time::Duration;
+
+const START_MILLIS: u64 = 1_752_582_896_000;
+
+fn value(bytes: usize) -> String {
+    "x".repeat(bytes)
+}
+
+fn secs(secs: u64) -> Option<Duration> {
+    Some(Duration::from_secs(secs))
+}
+
+async fn present(cache: &RedisCache, keys: &[&str]) -> Vec<String> {
+    let mut found = Vec::new();
+    for key in keys {
+        if cache.get_raw(key).await.unwrap().is_some() {
+            found.push(key.to_string());
+        }
+    }
+    found
+}
+
+#[tokio::test]
+async fn test_entry_without_ttl_never_expires() {
+    let clock = Arc::new(ManualClock::new(START_MILLIS));
+    let mut cache = RedisCache::new();
+    cache.set_clock(clock.clone());
+
+    cache.set_persistent("persistent", &"kept").await.unwrap();
+    cache.set("volatile", &"dropped", Duration::from_secs(5)).await.unwrap();
+    clock.advance(Duration::from_secs(3600));
+
+    assert_eq!(cache.get::<String>("persistent").await.unwrap().as_deref(), Some("kept"));
+    assert_eq!(cache.get::<String>("volatile").await.unwrap(), None);
+}
+
+#[tokio::test]
+async fn test_volatile_lru_only_evicts_keys_with_ttl() {
+    let cache = RedisCache::with_policy(1, CachePolicy::VolatileLRU);
+    cache.set_raw("persistent", value(400_000), None).await.unwrap();
+    cache.set_raw("old", value(300_000), secs(60)).await.unwrap();
+    cache.set_raw("recent", value(300_000), secs(60)).await.unwrap();
+
+    // "persistent" is the least recently used, but has no TTL
+    cache.set_raw("new", value(300_000), secs(60)).await.unwrap();
+
+    assert_eq!(
+        present(&cache, &["persistent", "old", "recent", "new"]).await,
+        vec!["persistent", "recent", "new"]
+    );
+}
+
+#[tokio::test]
+async fn test_volatile_policy_without_ttl_keys_rejects_the_write() {
+    let cache = RedisCache::with_policy(1, CachePolicy::VolatileLRU);
+    cache.set_raw("a", value(600_000), None).await.unwrap();
+
+    let result = cache.set_raw("b", value(600_000), None).await;
+
+    assert!(matches!(result, Err(PaymentError::CacheRejected(CacheRejection::NotEnoughEvictable { .. }))));
+    assert_eq!(cache.get_entry_count().await, 1);
+}
+
+#[tokio::test]
+async fn test_volatile_ttl_evicts_the_soonest_to_expire() {
+    let cache = RedisCache::with_policy(1, CachePolicy::VolatileTTL);
+    cache.set_raw("persistent", value(300_000), None).await.unwrap();
+    cache.set_raw("long", value(300_000), secs(600)).await.unwrap();
+    cache.set_raw("short", value(300_000), secs(10)).await.unwrap();
+
+    cache.set_raw("new", value(300_000), secs(60)).await.unwrap();
+
+    assert_eq!(
+        present(&cache, &["persistent", "long", "short", "new"]).await,
+        vec!["persistent", "long", "new"]
+    );
+}
+
+#[tokio::test]
+async fn test_volatile_random_never_evicts_persistent_keys() {
+    for _ in 0..20 {
+        let cache = RedisCache::with_policy(1, CachePolicy::VolatileRandom);
+        cache.set_raw("persistent", value(300_000), None).await.unwrap();
+        cache.set_raw("a", value(300_000), secs(60)).await.unwrap();
+        cache.set_raw("b", value(300_000), secs(60)).await.unwrap();
+
+        cache.set_raw("new", value(300_000), secs(60)).await.unwrap();
+
+        assert!(cache.get_raw("persistent").await.unwrap().is_some());
+        assert_eq!(cache.get_entry_count().await, 3);
+    }
+}
+
+#[tokio::test]
+async fn test_allkeys_random_picks_different_victims() {
+    let mut victims = HashSet::new();
+    for _ in 0..30 {
+        let cache = RedisCache::with_policy(1, CachePolicy::AllKeysRandom);
+        for key in ["a", "b", "c"] {
+            cache.set_raw(key, value(300_000), None).await.unwrap();
+        }
+
+        cache.set_raw("new", value(300_000), None).await.unwrap();
+
+        let kept = present(&cache, &["a", "b", "c"]).await;
+        assert_eq!(kept.len(), 2);
+        victims.extend(["a", "b", "c"].iter().filter(|key| !kept.contains(&key.to_string())).map(|key| key.to_string()));
+    }
+
+    assert!(victims.len() > 1, "random eviction always chose {:?}", victims);
+}
+
+#[test]
+fn test_lru_map_random_key_respects_filter() {
+    let mut map = LruMap::new();
+    for i in 0..50 {
+        map.insert(format!("key-{}", i), i);
+    }
+    let mut rng = rand::thread_rng();
+
+    for _ in 0..100 {
+        let key = map.random_key(&mut rng, |value| *value == 7).unwrap();
+        assert_eq!(key, "key-7");
+    }
+    assert_eq!(map.random_key(&mut rng, |value| *value > 100), None);
+}
+
+#[test]
+fn test_policy_parses_redis_names() {
+    assert_eq!("allkeys-lru".parse(), Ok(CachePolicy::AllKeysLRU));
+    assert_eq!("volatile-lru".parse(), Ok(CachePolicy::VolatileLRU));
+    assert_eq!("allkeys-random".parse(), Ok(CachePolicy::AllKeysRandom));
+    assert_eq!("volatile-random".parse(), Ok(CachePolicy::VolatileRandom));
+    assert_eq!("volatile-ttl".parse(), Ok(CachePolicy::VolatileTTL));
+    assert!("noeviction".parse::<CachePolicy>().is_err());
+}
+
+#[test]
+fn test_cache_manager_accepts_a_policy() {
+    let manager = CacheManager::with_policy(10, CachePolicy::VolatileTTL);
+
+    assert_eq!(manager.get_memory_limit_mb(), 10);
+    assert_eq!(RedisCache::with_policy(10, CachePolicy::VolatileTTL).get_policy(), CachePolicy::VolatileTTL);
+    assert_eq!(RedisCache::with_memory_limit(10).get_policy(), CachePolicy::AllKeysLRU);
+}
@@ -0,0 +1,131 @@
+use rinha::modules::cache::backend::EntryTtl;
+use rinha::modules::cache::redis::RedisCache;
+use rinha::modules::clock::{Clock, ManualClock, MonotonicClock, SystemClock};
+use std::sync::Arc;
+use std::time::Duration;
+
+const START_MILLIS: u64 = 1_752_582_896_000;
+
+fn cache_with_clock() -> (RedisCache, Arc<ManualClock>) {
+    let clock = Arc::new(ManualClock::new(START_MILLIS));
+    let mut cache = RedisCache::new();
+    cache.set_clock(clock.clone());
+    (cache, clock)
+}
+
+#[tokio::test]
+async fn test_ttl_has_millisecond_precision() {
+    let (cache, clock) = cache_with_clock();
+    cache.set("key", &"value", Duration::from_millis(1500)).await.unwrap();
+
+    clock.advance(Duration::from_millis(1400));
+    assert!(cache.get::<String>("key").await.unwrap().is_some());
+
+    clock.advance(Duration::from_millis(200));
+    assert!(cache.get::<String>("key").await.unwrap().is_none());
+}
+
+#[tokio::test]
+async fn test_clock_moving_backwards_does_not_expire_entries() {
+    let (cache, clock) = cache_with_clock();
+    cache.set("key", &"value", Duration::from_secs(10)).await.unwrap();
+
+    clock.set_millis(START_MILLIS - 60_000);
+
+    assert!(cache.get::<String>("key").await.unwrap().is_some());
+    assert_eq!(cache.ttl("key").await.unwrap(), EntryTtl::Expires(Duration::from_secs(70)));
+}
+
+#[tokio::test]
+async fn test_sweep_removes_expired_entries_without_reads() {
+    let (cache, clock) = cache_with_clock();
+    for i in 0..200 {
+        cache.set_raw(&format!("volatile-{}", i), "x".repeat(1000), Some(Duration::from_secs(1))).await.unwrap();
+    }
+    for i in 0..10 {
+        cache.set_raw(&format!("persistent-{}", i), "x".repeat(1000), None).await.unwrap();
+    }
+    cache.set_raw("long", "x".repeat(1000), Some(Duration::from_secs(600))).await.unwrap();
+
+    clock.advance(Duration::from_secs(2));
+    let mut removed = 0;
+    for _ in 0..100 {
+        removed += cache.sweep_expired().await;
+        if cache.get_entry_count().await == 11 {
+            break;
+        }
+    }
+
+    assert_eq!(removed, 200);
+    assert_eq!(cache.get_entry_count().await, 11);
+    assert!(cache.get_raw("long").await.unwrap().is_some());
+}
+
+#[tokio::test]
+async fn test_background_sweeper_frees_expired_entries() {
+    let (cache, clock) = cache_with_clock();
+    for i in 0..50 {
+        cache.set(&format!("key-{}", i), &i, Duration::from_millis(100)).await.unwrap();
+    }
+    clock.advance(Duration::from_secs(1));
+
+    assert!(cache.start_sweeper(Duration::from_millis(5)));
+    assert!(!cache.start_sweeper(Duration::from_millis(5)));
+    for _ in 0..200 {
+        if cache.get_entry_count().await == 0 {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(5)).await;
+    }
+
+    assert_eq!(cache.get_entry_count().await, 0);
+    assert!(cache.stop_sweeper());
+    assert!(!cache.stop_sweeper());
+}
+
+#[tokio::test]
+async fn test_ttl_reports_missing_persistent_and_remaining() {
+    let (cache, clock) = cache_with_clock();
+    cache.set_persistent("persistent", &1).await.unwrap();
+    cache.set("volatile", &2, Duration::from_millis(2500)).await.unwrap();
+    clock.advance(Duration::from_millis(500));
+
+    assert_eq!(cache.ttl("missing").await.unwrap(), EntryTtl::Missing);
+    assert_eq!(cache.ttl("persistent").await.unwrap(), EntryTtl::Persistent);
+    assert_eq!(cache.ttl("volatile").await.unwrap(), EntryTtl::Expires(Duration::from_millis(2000)));
+
+    clock.advance(Duration::from_secs(3));
+    assert_eq!(cache.ttl("volatile").await.unwrap(), EntryTtl::Missing);
+}
+
+#[tokio::test]
+async fn test_expire_and_persist_change_an_entry_ttl() {
+    let (cache, clock) = cache_with_clock();
+    cache.set_persistent("key", &"value").await.unwrap();
+
+    assert!(cache.expire("key", Duration::from_millis(300)).await.unwrap());
+    assert!(!cache.expire("missing", Duration::from_millis(300)).await.unwrap());
+    assert_eq!(cache.ttl("key").await.unwrap(), EntryTtl::Expires(Duration::from_millis(300)));
+
+    assert!(cache.persist("key").await.unwrap());
+    assert!(!cache.persist("key").await.unwrap());
+    clock.advance(Duration::from_secs(60));
+    assert_eq!(cache.ttl("key").await.unwrap(), EntryTtl::Persistent);
+
+    cache.expire("key", Duration::from_millis(300)).await.unwrap();
+    clock.advance(Duration::from_secs(1));
+    assert!(cache.get::<String>("key").await.unwrap().is_none());
+    assert!(!cache.persist("key").await.unwrap());
+}
+
+#[test]
+fn test_monotonic_clock_starts_at_wall_time_and_never_goes_back() {
+    let clock = MonotonicClock::new();
+    let wall = SystemClock.now_millis();
+
+    let first = clock.now_millis();
+    let second = clock.now_millis();
+
+    assert!(first.abs_diff(wall) < 1000);
+    assert!(second >= first);
+}
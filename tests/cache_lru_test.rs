@@ -0,0 +1,110 @@
+use rinha::modules::cache::lru::LruMap;
+use rinha::modules::cache::redis::RedisCache;
+use std::time::Duration;
+
+const TTL: Option<Duration> = Some(Duration::from_secs(60));
+
+fn value(bytes: usize) -> String {
+    "x".repeat(bytes)
+}
+
+#[test]
+fn test_lru_map_pops_least_recently_used_first() {
+    let mut map = LruMap::new();
+    map.insert("a".to_string(), 1);
+    map.insert("b".to_string(), 2);
+    map.insert("c".to_string(), 3);
+
+    assert_eq!(map.get("a"), Some(&1));
+
+    assert_eq!(map.pop_lru(), Some(("b".to_string(), 2)));
+    assert_eq!(map.pop_lru(), Some(("c".to_string(), 3)));
+    assert_eq!(map.pop_lru(), Some(("a".to_string(), 1)));
+    assert_eq!(map.pop_lru(), None);
+    assert!(map.is_empty());
+}
+
+#[test]
+fn test_lru_map_peek_does_not_touch_recency() {
+    let mut map = LruMap::new();
+    map.insert("a".to_string(), 1);
+    map.insert("b".to_string(), 2);
+
+    assert_eq!(map.peek("a"), Some(&1));
+
+    assert_eq!(map.peek_lru(), Some(("a", &1)));
+}
+
+#[test]
+fn test_lru_map_replacing_a_key_moves_it_to_the_front() {
+    let mut map = LruMap::new();
+    map.insert("a".to_string(), 1);
+    map.insert("b".to_string(), 2);
+
+    assert_eq!(map.insert("a".to_string(), 10), Some(1));
+
+    assert_eq!(map.len(), 2);
+    let order: Vec<_> = map.iter().map(|(key, value)| (key.to_string(), *value)).collect();
+    assert_eq!(order, vec![("b".to_string(), 2), ("a".to_string(), 10)]);
+}
+
+#[test]
+fn test_lru_map_reuses_removed_slots() {
+    let mut map = LruMap::new();
+    for round in 0..3 {
+        for i in 0..100 {
+            map.insert(format!("key-{}", i), round);
+        }
+        for i in 0..100 {
+            assert_eq!(map.remove(&format!("key-{}", i)), Some(round));
+        }
+    }
+
+    assert!(map.is_empty());
+    assert_eq!(map.peek_lru(), None);
+}
+
+#[tokio::test]
+async fn test_cache_evicts_least_recently_read_entry() {
+    let cache = RedisCache::with_memory_limit(1);
+    cache.set_raw("a", value(300_000), TTL).await.unwrap();
+    cache.set_raw("b", value(300_000), TTL).await.unwrap();
+    cache.set_raw("c", value(300_000), TTL).await.unwrap();
+
+    // Reading "a" makes "b" the least recently used
+    assert!(cache.get_raw("a").await.unwrap().is_some());
+    cache.set_raw("d", value(300_000), TTL).await.unwrap();
+
+    assert!(cache.get_raw("a").await.unwrap().is_some());
+    assert!(cache.get_raw("b").await.unwrap().is_none());
+    assert!(cache.get_raw("c").await.unwrap().is_some());
+    assert!(cache.get_raw("d").await.unwrap().is_some());
+}
+
+#[tokio::test]
+async fn test_cache_evicts_until_the_new_entry_fits() {
+    let cache = RedisCache::with_memory_limit(1);
+    cache.set_raw("a", value(300_000), TTL).await.unwrap();
+    cache.set_raw("b", value(300_000), TTL).await.unwrap();
+    cache.set_raw("c", value(300_000), TTL).await.unwrap();
+
+    cache.set_raw("big", value(700_000), TTL).await.unwrap();
+
+    assert_eq!(cache.get_entry_count().await, 2);
+    assert!(cache.get_raw("a").await.unwrap().is_none());
+    assert!(cache.get_raw("b").await.unwrap().is_none());
+    assert!(cache.get_raw("c").await.unwrap().is_some());
+    assert!(cache.get_raw("big").await.unwrap().is_some());
+}
+
+#[tokio::test]
+async fn test_overwriting_a_key_does_not_evict_others() {
+    let cache = RedisCache::with_memory_limit(1);
+    cache.set_raw("a", value(500_000), TTL).await.unwrap();
+    cache.set_raw("b", value(500_000), TTL).await.unwrap();
+
+    cache.set_raw("b", value(500_000), TTL).await.unwrap();
+
+    assert_eq!(cache.get_entry_count().await, 2);
+    assert!(cache.get_raw("a").await.unwrap().is_some());
+}
@@ -0,0 +1,132 @@
+use axum::http::StatusCode;
+use rinha::modules::cache::redis::{CachePolicy, RedisCache, ENTRY_OVERHEAD_BYTES};
+use rinha::modules::cache::CacheManager;
+use rinha::modules::error::{CacheRejection, PaymentError};
+use std::time::Duration;
+
+const MB: u64 = 1024 * 1024;
+const TTL: Option<Duration> = Some(Duration::from_secs(60));
+
+#[tokio::test]
+async fn test_usage_counts_key_value_and_overhead() {
+    let cache = RedisCache::new();
+
+    cache.set_raw("key", "v".repeat(10), TTL).await.unwrap();
+    assert_eq!(cache.get_memory_usage_bytes().await, 3 + 10 + ENTRY_OVERHEAD_BYTES);
+
+    cache.set_raw("key", "v".repeat(20), None).await.unwrap();
+    let stats = cache.memory_stats().await;
+    assert_eq!(stats.used_bytes, 3 + 20 + ENTRY_OVERHEAD_BYTES);
+    assert_eq!(stats.volatile_bytes, 0);
+    assert_eq!(stats.entry_count, 1);
+    assert_eq!(stats.limit_bytes, 50 * MB);
+
+    cache.remove("key").await.unwrap();
+    assert_eq!(cache.get_memory_usage_bytes().await, 0);
+}
+
+#[tokio::test]
+async fn test_small_entries_cannot_exceed_the_limit() {
+    let cache = RedisCache::with_memory_limit(1);
+
+    for i in 0..10_000 {
+        cache.set_raw(&format!("key-{}", i), "x".repeat(200), TTL).await.unwrap();
+    }
+
+    let stats = cache.memory_stats().await;
+    assert!(stats.used_bytes <= MB, "{} bytes used", stats.used_bytes);
+    assert!(stats.entry_count < 10_000);
+    assert!(cache.get_raw("key-9999").await.unwrap().is_some());
+    assert!(cache.get_raw("key-0").await.unwrap().is_none());
+}
+
+#[tokio::test]
+async fn test_entry_larger_than_the_limit_is_rejected_without_evicting() {
+    let cache = RedisCache::with_memory_limit(1);
+    cache.set_raw("small", "x".repeat(100), TTL).await.unwrap();
+
+    let error = cache.set_raw("huge", "x".repeat(2 * MB as usize), TTL).await.unwrap_err();
+
+    assert_eq!(
+        error,
+        PaymentError::CacheRejected(CacheRejection::EntryTooLarge {
+            entry_bytes: 4 + 2 * MB + ENTRY_OVERHEAD_BYTES,
+            limit_bytes: MB,
+        })
+    );
+    assert_eq!(error.kind(), "cache_rejected");
+    assert!(!error.is_retryable());
+    assert_eq!(error.status_code(), StatusCode::INSUFFICIENT_STORAGE);
+    assert!(error.to_string().contains("exceeds the 1048576 byte limit"));
+    assert!(cache.get_raw("small").await.unwrap().is_some());
+}
+
+#[tokio::test]
+async fn test_rejected_write_leaves_the_cache_unchanged() {
+    let cache = RedisCache::with_policy(1, CachePolicy::VolatileLRU);
+    cache.set_raw("persistent", "x".repeat(700_000), None).await.unwrap();
+    cache.set_raw("volatile", "x".repeat(100_000), TTL).await.unwrap();
+    let before = cache.memory_stats().await;
+
+    let error = cache.set_raw("new", "x".repeat(400_000), TTL).await.unwrap_err();
+
+    let PaymentError::CacheRejected(CacheRejection::NotEnoughEvictable { needed_bytes, evictable_bytes }) = error else {
+        panic!("unexpected error: {:?}", error);
+    };
+    assert_eq!(evictable_bytes, before.volatile_bytes);
+    assert!(needed_bytes > evictable_bytes);
+    assert_eq!(cache.memory_stats().await, before);
+    assert!(cache.get_raw("volatile").await.unwrap().is_some());
+}
+
+#[tokio::test]
+async fn test_overwrite_counts_only_the_new_value() {
+    let cache = RedisCache::with_policy(1, CachePolicy::VolatileLRU);
+    cache.set_raw("key", "x".repeat(900_000), TTL).await.unwrap();
+
+    // Would not fit next to the old value, but replaces it
+    cache.set_raw("key", "y".repeat(900_000), TTL).await.unwrap();
+
+    assert_eq!(cache.get_entry_count().await, 1);
+    assert_eq!(cache.get_memory_usage_bytes().await, 3 + 900_000 + ENTRY_OVERHEAD_BYTES);
+}
+
+#[tokio::test]
+async fn test_expire_and_persist_move_bytes_between_volatile_and_persistent() {
+    let cache = RedisCache::new();
+    cache.set_raw("key", "v".repeat(10), None).await.unwrap();
+    let size = 3 + 10 + ENTRY_OVERHEAD_BYTES;
+
+    cache.expire("key", Duration::from_secs(5)).await.unwrap();
+    assert_eq!(cache.memory_stats().await.volatile_bytes, size);
+
+    cache.persist("key").await.unwrap();
+    assert_eq!(cache.memory_stats().await.volatile_bytes, 0);
+}
+
+#[tokio::test]
+async fn test_memory_usage_mb_still_reports_whole_megabytes() {
+    let manager = CacheManager::new();
+
+    for i in 0..3 {
+        manager.set(&format!("key-{}", i), &"x".repeat(MB as usize), Duration::from_secs(60)).await.unwrap();
+    }
+
+    assert_eq!(manager.get_memory_usage_mb().await, 3);
+    assert!(manager.memory_stats().await.used_bytes > 3 * MB);
+}
+
+#[tokio::test]
+async fn test_atomic_writes_evict_and_respect_the_limit() {
+    let cache = RedisCache::with_memory_limit(1);
+    for key in ["a", "b", "c"] {
+        cache.set_raw(key, "x".repeat(300_000), TTL).await.unwrap();
+    }
+
+    assert!(cache.set_nx("d", "x".repeat(300_000), TTL).await.unwrap());
+    assert!(cache.get_raw("a").await.unwrap().is_none());
+
+    assert!(cache.compare_and_swap("d", &"x".repeat(300_000), "y".repeat(2 * MB as usize), TTL).await.is_err());
+    assert_eq!(cache.incr_by("counter", 1).await.unwrap(), 1);
+    assert!(cache.get_memory_usage_bytes().await <= MB);
+}
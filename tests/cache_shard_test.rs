@@ -0,0 +1,93 @@
+use rinha::modules::cache::redis::{CachePolicy, RedisCache, DEFAULT_SHARD_COUNT, ENTRY_OVERHEAD_BYTES};
+use std::sync::Arc;
+use std::time::Duration;
+
+const MB: u64 = 1024 * 1024;
+const TTL: Option<Duration> = Some(Duration::from_secs(60));
+
+#[test]
+fn test_default_shard_count() {
+    assert_eq!(RedisCache::new().shard_count(), DEFAULT_SHARD_COUNT);
+    assert_eq!(RedisCache::with_shards(10, CachePolicy::AllKeysLRU, 0).shard_count(), 1);
+}
+
+#[tokio::test]
+async fn test_totals_add_up_across_shards() {
+    let cache = RedisCache::with_shards(50, CachePolicy::AllKeysLRU, 8);
+
+    for i in 0..100 {
+        cache.set_raw(&format!("key-{:03}", i), "v".repeat(10), TTL).await.unwrap();
+    }
+    for i in 0..50 {
+        cache.set_raw(&format!("key-{:03}", i), "v".repeat(10), None).await.unwrap();
+    }
+
+    let entry_size = 7 + 10 + ENTRY_OVERHEAD_BYTES;
+    let stats = cache.memory_stats().await;
+    assert_eq!(stats.entry_count, 100);
+    assert_eq!(stats.used_bytes, 100 * entry_size);
+    assert_eq!(stats.volatile_bytes, 50 * entry_size);
+
+    cache.clear().await.unwrap();
+    assert_eq!(cache.memory_stats().await.used_bytes, 0);
+}
+
+#[tokio::test]
+async fn test_lru_order_holds_across_shards() {
+    let cache = RedisCache::with_shards(1, CachePolicy::AllKeysLRU, 16);
+    for key in ["a", "b", "c"] {
+        cache.set_raw(key, "x".repeat(300_000), None).await.unwrap();
+    }
+    cache.get_raw("a").await.unwrap();
+
+    cache.set_raw("d", "x".repeat(300_000), None).await.unwrap();
+
+    assert!(cache.get_raw("b").await.unwrap().is_none());
+    for key in ["a", "c", "d"] {
+        assert!(cache.get_raw(key).await.unwrap().is_some(), "{} was evicted", key);
+    }
+}
+
+#[tokio::test]
+async fn test_overwrite_under_pressure_keeps_the_key() {
+    let cache = RedisCache::with_shards(1, CachePolicy::AllKeysRandom, 4);
+    cache.set_raw("key", "x".repeat(500_000), None).await.unwrap();
+    cache.set_raw("other", "x".repeat(400_000), None).await.unwrap();
+
+    cache.set_raw("key", "y".repeat(700_000), None).await.unwrap();
+
+    assert_eq!(cache.get_raw("key").await.unwrap(), Some("y".repeat(700_000)));
+    assert!(cache.get_raw("other").await.unwrap().is_none());
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn test_concurrent_writers_share_one_memory_budget() {
+    let cache = Arc::new(RedisCache::with_shards(1, CachePolicy::AllKeysLRU, 8));
+
+    let mut tasks = Vec::new();
+    for task in 0..8 {
+        let cache = Arc::clone(&cache);
+        tasks.push(tokio::spawn(async move {
+            for i in 0..2_000 {
+                let key = format!("task-{}-{}", task, i % 500);
+                cache.set_raw(&key, "x".repeat(500), TTL).await.unwrap();
+                if i % 3 == 0 {
+                    cache.get_raw(&key).await.unwrap();
+                }
+                if i % 7 == 0 {
+                    cache.remove(&key).await.unwrap();
+                }
+            }
+        }));
+    }
+    for task in tasks {
+        task.await.unwrap();
+    }
+
+    let stats = cache.memory_stats().await;
+    assert!(stats.used_bytes <= MB, "{} bytes used", stats.used_bytes);
+    assert!(stats.entry_count > 0);
+    let entry_bytes = stats.used_bytes / stats.entry_count as u64;
+    assert!((510..=520 + ENTRY_OVERHEAD_BYTES).contains(&entry_bytes));
+    assert!(cache.stats().await.evicted_keys() > 0);
+}
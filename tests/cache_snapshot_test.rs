@@ -0,0 +1,149 @@
+use rinha::modules::cache::backend::EntryTtl;
+use rinha::modules::cache::redis::{CachePolicy, RedisCache};
+use rinha::modules::cache::redis_server::{RedisServerCache, RedisSettings};
+use rinha::modules::cache::snapshot::{CacheSnapshot, SNAPSHOT_VERSION};
+use rinha::modules::cache::CacheManager;
+use rinha::modules::clock::ManualClock;
+use rinha::modules::error::PaymentError;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+
+const START_MILLIS: u64 = 1_752_582_896_000;
+
+fn snapshot_path() -> PathBuf {
+    std::env::temp_dir()
+        .join(format!("rinha-snapshot-{}", uuid::Uuid::new_v4()))
+        .join("cache.msgpack")
+}
+
+fn cache_at(clock: &Arc<ManualClock>) -> RedisCache {
+    let mut cache = RedisCache::new();
+    cache.set_clock(clock.clone());
+    cache
+}
+
+#[tokio::test]
+async fn test_restore_takes_downtime_off_the_ttls() {
+    let clock = Arc::new(ManualClock::new(START_MILLIS));
+    let cache = cache_at(&clock);
+    cache.set_raw("persistent", "p".to_string(), None).await.unwrap();
+    cache.set_raw("long", "l".to_string(), Some(Duration::from_secs(60))).await.unwrap();
+    cache.set_raw("short", "s".to_string(), Some(Duration::from_secs(3))).await.unwrap();
+    cache.set_raw("gone", "g".to_string(), Some(Duration::from_secs(1))).await.unwrap();
+    clock.advance(Duration::from_secs(2));
+
+    let snapshot = cache.snapshot().await;
+    assert_eq!(snapshot.entries.len(), 3);
+
+    clock.advance(Duration::from_secs(5));
+    let restarted = cache_at(&clock);
+    assert_eq!(restarted.restore(&snapshot).await, 2);
+
+    assert_eq!(restarted.get_raw("persistent").await.unwrap().as_deref(), Some("p"));
+    assert_eq!(restarted.ttl("persistent").await.unwrap(), EntryTtl::Persistent);
+    assert_eq!(restarted.ttl("long").await.unwrap(), EntryTtl::Expires(Duration::from_secs(53)));
+    assert_eq!(restarted.get_raw("short").await.unwrap(), None);
+}
+
+#[tokio::test]
+async fn test_restore_keeps_lru_order() {
+    let cache = RedisCache::with_shards(1, CachePolicy::AllKeysLRU, 16);
+    for key in ["a", "b", "c"] {
+        cache.set_raw(key, "x".repeat(300_000), None).await.unwrap();
+    }
+    cache.get_raw("a").await.unwrap();
+
+    let restarted = RedisCache::with_shards(1, CachePolicy::AllKeysLRU, 16);
+    assert_eq!(restarted.restore(&cache.snapshot().await).await, 3);
+    restarted.set_raw("d", "x".repeat(300_000), None).await.unwrap();
+
+    assert!(restarted.get_raw("b").await.unwrap().is_none());
+    for key in ["a", "c", "d"] {
+        assert!(restarted.get_raw(key).await.unwrap().is_some(), "{} was evicted", key);
+    }
+}
+
+#[tokio::test]
+async fn test_msgpack_round_trip_and_version_check() {
+    let cache = RedisCache::new();
+    cache.set_raw("key", "\"value\"".to_string(), Some(Duration::from_secs(60))).await.unwrap();
+    cache.hincr_by("hash", "field", 1).await.unwrap();
+    let snapshot = cache.snapshot().await;
+
+    let data = snapshot.to_msgpack().unwrap();
+    assert_eq!(CacheSnapshot::from_msgpack(&data).unwrap(), snapshot);
+
+    // Hashes come back as hashes
+    let restarted = RedisCache::new();
+    assert_eq!(restarted.restore(&snapshot).await, 2);
+    assert_eq!(restarted.hget_all("hash").await.unwrap()["field"], "1");
+    assert!(restarted.get_raw("hash").await.is_err());
+
+    let future = CacheSnapshot {
+        version: SNAPSHOT_VERSION + 1,
+        ..snapshot
+    };
+    let result = CacheSnapshot::from_msgpack(&future.to_msgpack().unwrap());
+    assert!(matches!(result, Err(PaymentError::Serialization(_))));
+    assert!(CacheSnapshot::from_msgpack(b"not msgpack").is_err());
+}
+
+#[tokio::test]
+async fn test_manager_saves_and_loads_snapshot_files() {
+    let path = snapshot_path();
+    let manager = CacheManager::new();
+    assert_eq!(manager.load_snapshot(&path).await.unwrap(), 0);
+
+    manager.set("key", &"value", Duration::from_secs(60)).await.unwrap();
+    manager.set_persistent("config", &42).await.unwrap();
+    assert_eq!(manager.save_snapshot(&path).await.unwrap(), Some(2));
+
+    let restarted = CacheManager::new();
+    assert_eq!(restarted.load_snapshot(&path).await.unwrap(), 2);
+    assert_eq!(restarted.get::<String>("key").await.unwrap().as_deref(), Some("value"));
+    assert_eq!(restarted.get::<i32>("config").await.unwrap(), Some(42));
+
+    tokio::fs::write(&path, b"garbage").await.unwrap();
+    assert!(restarted.load_snapshot(&path).await.is_err());
+    let _ = tokio::fs::remove_dir_all(path.parent().unwrap()).await;
+}
+
+#[tokio::test]
+async fn test_redis_server_backend_keeps_no_snapshots() {
+    let path = snapshot_path();
+    let cache = RedisServerCache::new(RedisSettings {
+        url: "redis://127.0.0.1:1".to_string(),
+        ..RedisSettings::default()
+    })
+    .unwrap();
+    let manager = CacheManager::with_backend(Box::new(cache));
+
+    assert_eq!(manager.save_snapshot(&path).await.unwrap(), None);
+    assert!(!manager.start_snapshots(path.clone(), Duration::from_millis(10)));
+    assert!(!path.exists());
+}
+
+#[tokio::test]
+async fn test_scheduled_snapshots_are_written() {
+    let path = snapshot_path();
+    let cache = RedisCache::new();
+    cache.set_raw("key", "value".to_string(), None).await.unwrap();
+
+    assert!(cache.start_snapshots(path.clone(), Duration::from_millis(10)));
+    assert!(!cache.start_snapshots(path.clone(), Duration::from_millis(10)));
+
+    let mut snapshot = None;
+    for _ in 0..100 {
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        snapshot = CacheSnapshot::read_from(&path).await.unwrap();
+        if snapshot.is_some() {
+            break;
+        }
+    }
+    let snapshot = snapshot.expect("no snapshot was written");
+    assert_eq!(snapshot.entries.len(), 1);
+    assert_eq!(snapshot.entries[0].key, "key");
+    drop(cache);
+    let _ = tokio::fs::remove_dir_all(path.parent().unwrap()).await;
+}
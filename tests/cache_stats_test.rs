@@ -0,0 +1,118 @@
+mod common;
+
+use axum::{Router, routing::get};
+use reqwest::Client;
+use rinha::modules::cache::redis::{CachePolicy, RedisCache, ENTRY_OVERHEAD_BYTES};
+use rinha::modules::cache::stats::InfoSection;
+use rinha::modules::cache::{get_cache_info, CacheManager};
+use rinha::modules::clock::ManualClock;
+use rinha::modules::{ApplicationServices, SharedServices};
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+
+const START_MILLIS: u64 = 1_752_582_896_000;
+const TTL: Option<Duration> = Some(Duration::from_secs(60));
+
+async fn start_server(services: SharedServices) -> SocketAddr {
+    let app = Router::new()
+        .route("/metrics/cache", get(get_cache_info))
+        .with_state(services);
+    common::start_server(app).await
+}
+
+#[tokio::test]
+async fn test_reads_count_hits_and_misses() {
+    let cache = RedisCache::new();
+    cache.set_raw("key", "value".to_string(), TTL).await.unwrap();
+
+    cache.get_raw("key").await.unwrap();
+    cache.get_raw("key").await.unwrap();
+    cache.get_raw("missing").await.unwrap();
+
+    let stats = cache.stats().await;
+    assert_eq!((stats.hits, stats.misses), (2, 1));
+    assert!((stats.hit_rate() - 2.0 / 3.0).abs() < 1e-9);
+    assert_eq!(stats.bytes_written, 3 + 5 + ENTRY_OVERHEAD_BYTES);
+    assert_eq!(stats.memory.entry_count, 1);
+}
+
+#[tokio::test]
+async fn test_expired_entries_are_counted_on_read_and_by_the_sweeper() {
+    let clock = Arc::new(ManualClock::new(START_MILLIS));
+    let mut cache = RedisCache::new();
+    cache.set_clock(clock.clone());
+    for key in ["a", "b", "c"] {
+        cache.set_raw(key, "v".to_string(), Some(Duration::from_secs(1))).await.unwrap();
+    }
+    clock.advance(Duration::from_secs(2));
+
+    assert_eq!(cache.get_raw("a").await.unwrap(), None);
+    cache.sweep_expired().await;
+
+    let stats = cache.stats().await;
+    assert_eq!(stats.expired_keys, 3);
+    assert_eq!(stats.misses, 1);
+}
+
+#[tokio::test]
+async fn test_evictions_and_rejections_are_counted_by_policy() {
+    let cache = RedisCache::with_policy(1, CachePolicy::VolatileLRU);
+    cache.set_raw("a", "x".repeat(400_000), TTL).await.unwrap();
+    cache.set_raw("b", "x".repeat(400_000), TTL).await.unwrap();
+    cache.set_raw("c", "x".repeat(400_000), None).await.unwrap();
+    assert!(cache.set_raw("d", "x".repeat(2_000_000), TTL).await.is_err());
+
+    let stats = cache.stats().await;
+    assert_eq!(stats.evictions.get("volatile-lru"), Some(&1));
+    assert_eq!(stats.evicted_keys(), 1);
+    assert_eq!(stats.rejected_writes, 1);
+    assert_eq!(stats.bytes_written, 3 * (1 + 400_000 + ENTRY_OVERHEAD_BYTES));
+}
+
+#[tokio::test]
+async fn test_info_report_uses_redis_field_names() {
+    let manager = CacheManager::with_policy(1, CachePolicy::AllKeysLRU);
+    manager.set("key", &"value", Duration::from_secs(60)).await.unwrap();
+    manager.get::<String>("key").await.unwrap();
+
+    let stats = manager.stats().await;
+    let info = stats.to_info(InfoSection::All);
+
+    assert!(info.starts_with("# Memory\r\n"));
+    assert!(info.contains("\r\n\r\n# Stats\r\n"));
+    assert!(info.contains("cache_backend:memory\r\n"));
+    assert!(info.contains(&format!("used_memory:{}\r\n", stats.memory.used_bytes)));
+    assert!(info.contains("maxmemory:1048576\r\nmaxmemory_human:1.00M\r\n"));
+    assert!(info.contains("keys:1\r\n"));
+    assert!(info.contains("keyspace_hits:1\r\nkeyspace_misses:0\r\nhit_rate:1.0000\r\n"));
+    assert!(info.contains("evicted_keys_allkeys_lru:0\r\n"));
+
+    let memory_only = stats.to_info(InfoSection::Memory);
+    assert!(!memory_only.contains("# Stats"));
+    assert!(!stats.to_info(InfoSection::Stats).contains("# Memory"));
+}
+
+#[tokio::test]
+async fn test_cache_info_endpoint() {
+    let services = ApplicationServices::new();
+    services.cache_manager.set("key", &"value", Duration::from_secs(60)).await.unwrap();
+    services.cache_manager.get::<String>("missing").await.unwrap();
+    let addr = start_server(Arc::new(services)).await;
+    let client = Client::new();
+
+    let resp = client.get(format!("http://{}/metrics/cache", addr)).send().await.unwrap();
+    assert_eq!(resp.status().as_u16(), 200);
+    assert!(resp.headers()["content-type"].to_str().unwrap().starts_with("text/plain"));
+    let body = resp.text().await.unwrap();
+    assert!(body.contains("# Memory\r\n"));
+    assert!(body.contains("keyspace_misses:1\r\n"));
+
+    let resp = client.get(format!("http://{}/metrics/cache?section=stats", addr)).send().await.unwrap();
+    let body = resp.text().await.unwrap();
+    assert!(body.starts_with("# Stats\r\n"));
+    assert!(!body.contains("used_memory"));
+
+    let resp = client.get(format!("http://{}/metrics/cache?section=cpu", addr)).send().await.unwrap();
+    assert_eq!(resp.status().as_u16(), 400);
+}
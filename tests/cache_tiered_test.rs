@@ -0,0 +1,195 @@
+use rinha::modules::cache::backend::CacheBackend;
+use rinha::modules::cache::invalidation::{InvalidationBus, LocalInvalidationBus};
+use rinha::modules::cache::redis::RedisCache;
+use rinha::modules::cache::stats::InfoSection;
+use rinha::modules::cache::tiered::{TierSettings, TieredCache};
+use rinha::modules::cache::CacheManager;
+use rinha::modules::clock::ManualClock;
+use rinha::modules::config::{CacheBackendKind, Config};
+use std::sync::Arc;
+use std::time::Duration;
+
+const START_MILLIS: u64 = 1_752_582_896_000;
+const TTL: Option<Duration> = Some(Duration::from_secs(60));
+
+fn settings(replica_id: &str) -> TierSettings {
+    TierSettings {
+        l1_ttl: Duration::from_secs(60),
+        replica_id: replica_id.to_string(),
+        ..TierSettings::default()
+    }
+}
+
+/// Two replicas sharing one L2 and one bus.
+fn replicas() -> (TieredCache, TieredCache, Arc<RedisCache>) {
+    let l2 = Arc::new(RedisCache::new());
+    let bus: Arc<dyn InvalidationBus> = Arc::new(LocalInvalidationBus::new());
+    let a = TieredCache::new(l2.clone(), bus.clone(), settings("a"));
+    let b = TieredCache::new(l2.clone(), bus, settings("b"));
+    (a, b, l2)
+}
+
+/// Polls until `cache` returns `expected` for `key`, as invalidations
+/// arrive asynchronously.
+async fn eventually(cache: &TieredCache, key: &str, expected: Option<&str>) {
+    for _ in 0..100 {
+        if cache.get_raw(key).await.unwrap().as_deref() == expected {
+            return;
+        }
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+    panic!("{} never became {:?}", key, expected);
+}
+
+#[tokio::test]
+async fn test_reads_fill_l1_and_count_hits_per_tier() {
+    let (a, _b, _l2) = replicas();
+    a.set_raw("key", "value".to_string(), TTL).await.unwrap();
+
+    assert_eq!(a.get_raw("key").await.unwrap().as_deref(), Some("value"));
+    assert_eq!(a.get_raw("key").await.unwrap().as_deref(), Some("value"));
+    assert_eq!(a.get_raw("missing").await.unwrap(), None);
+
+    let tiers = a.tier_stats();
+    assert_eq!((tiers.l1.hits, tiers.l1.misses), (1, 2));
+    assert_eq!((tiers.l2.hits, tiers.l2.misses), (1, 1));
+    assert_eq!(tiers.invalidations_published, 1);
+}
+
+#[tokio::test]
+async fn test_writes_invalidate_other_replicas() {
+    let (a, b, _l2) = replicas();
+    assert!(a.start_invalidation_listener());
+    assert!(b.start_invalidation_listener());
+    assert!(!b.start_invalidation_listener());
+
+    a.set_raw("key", "old".to_string(), TTL).await.unwrap();
+    assert_eq!(b.get_raw("key").await.unwrap().as_deref(), Some("old"));
+
+    a.set_raw("key", "new".to_string(), TTL).await.unwrap();
+    eventually(&b, "key", Some("new")).await;
+
+    a.remove("key").await.unwrap();
+    eventually(&b, "key", None).await;
+
+    assert!(b.tier_stats().invalidations_received >= 2);
+    assert_eq!(a.tier_stats().invalidations_received, 0);
+}
+
+#[tokio::test]
+async fn test_without_invalidations_l1_serves_until_its_ttl() {
+    let l2 = Arc::new(RedisCache::new());
+    let clock = Arc::new(ManualClock::new(START_MILLIS));
+    let mut cache = TieredCache::new(
+        l2.clone(),
+        Arc::new(LocalInvalidationBus::new()),
+        TierSettings {
+            l1_ttl: Duration::from_secs(1),
+            ..settings("a")
+        },
+    );
+    cache.set_clock(clock.clone());
+
+    l2.set_raw("key", "old".to_string(), TTL).await.unwrap();
+    assert_eq!(cache.get_raw("key").await.unwrap().as_deref(), Some("old"));
+
+    l2.set_raw("key", "new".to_string(), TTL).await.unwrap();
+    assert_eq!(cache.get_raw("key").await.unwrap().as_deref(), Some("old"));
+
+    clock.advance(Duration::from_secs(2));
+    assert_eq!(cache.get_raw("key").await.unwrap().as_deref(), Some("new"));
+}
+
+#[tokio::test]
+async fn test_key_ttl_of_zero_bypasses_l1() {
+    let l2 = Arc::new(RedisCache::new());
+    let settings = TierSettings {
+        l1_key_ttls: vec![
+            ("health:".to_string(), Duration::ZERO),
+            ("health:config:".to_string(), Duration::from_secs(5)),
+        ],
+        ..settings("a")
+    };
+    assert_eq!(settings.l1_ttl_for("health:status"), Duration::ZERO);
+    assert_eq!(settings.l1_ttl_for("health:config:default"), Duration::from_secs(5));
+    assert_eq!(settings.l1_ttl_for("payments"), Duration::from_secs(60));
+    let cache = TieredCache::new(l2.clone(), Arc::new(LocalInvalidationBus::new()), settings);
+
+    l2.set_raw("health:status", "up".to_string(), TTL).await.unwrap();
+    assert_eq!(cache.get_raw("health:status").await.unwrap().as_deref(), Some("up"));
+    l2.set_raw("health:status", "down".to_string(), TTL).await.unwrap();
+    assert_eq!(cache.get_raw("health:status").await.unwrap().as_deref(), Some("down"));
+
+    let tiers = cache.tier_stats();
+    assert_eq!((tiers.l1.hits, tiers.l1.misses), (0, 0));
+    assert_eq!(tiers.l2.hits, 2);
+
+    // Writes to keys that bypass L1, and to hashes, are not broadcast
+    cache.set_raw("health:status", "up".to_string(), TTL).await.unwrap();
+    cache.remove("health:status").await.unwrap();
+    cache.hincr_by("totals", "requests", 1).await.unwrap();
+    assert_eq!(cache.tier_stats().invalidations_published, 0);
+    cache.set_raw("health:config:default", "{}".to_string(), TTL).await.unwrap();
+    assert_eq!(cache.tier_stats().invalidations_published, 1);
+}
+
+#[tokio::test]
+async fn test_atomic_writes_invalidate_other_replicas() {
+    let (a, b, _l2) = replicas();
+    b.start_invalidation_listener();
+
+    a.incr_by("counter", 1).await.unwrap();
+    assert_eq!(b.get_raw("counter").await.unwrap().as_deref(), Some("1"));
+    a.incr_by("counter", 1).await.unwrap();
+    eventually(&b, "counter", Some("2")).await;
+
+    assert!(!a.compare_and_swap("counter", "1", "10".to_string(), TTL).await.unwrap());
+    assert!(a.compare_and_swap("counter", "2", "10".to_string(), TTL).await.unwrap());
+    eventually(&b, "counter", Some("10")).await;
+
+    // Only the successful swap and the two increments were broadcast
+    assert_eq!(a.tier_stats().invalidations_published, 3);
+}
+
+#[tokio::test]
+async fn test_clear_drops_every_replica_l1() {
+    let (a, b, _l2) = replicas();
+    b.start_invalidation_listener();
+    a.set_raw("key", "value".to_string(), TTL).await.unwrap();
+    assert!(b.get_raw("key").await.unwrap().is_some());
+
+    a.clear().await.unwrap();
+
+    eventually(&b, "key", None).await;
+}
+
+#[tokio::test]
+async fn test_info_reports_hit_rates_per_tier() {
+    let l2 = Arc::new(RedisCache::new());
+    let manager = CacheManager::with_tiers(l2, Arc::new(LocalInvalidationBus::new()), settings("a"));
+    manager.set("key", &"value", Duration::from_secs(60)).await.unwrap();
+    manager.get::<String>("key").await.unwrap();
+    manager.get::<String>("key").await.unwrap();
+
+    let info = manager.stats().await.to_info(InfoSection::Stats);
+
+    assert!(info.contains("l1_hits:1\r\nl1_misses:1\r\nl1_hit_rate:0.5000\r\n"));
+    assert!(info.contains("l2_hits:1\r\nl2_misses:0\r\nl2_hit_rate:1.0000\r\n"));
+    assert!(info.contains("invalidations_published:1\r\n"));
+    assert!(!CacheManager::new().stats().await.to_info(InfoSection::Stats).contains("l1_hits"));
+}
+
+#[tokio::test]
+async fn test_redis_config_layers_l1_unless_turned_off() {
+    let mut config = Config::new();
+    config.cache_backend = CacheBackendKind::Redis;
+    config.redis.url = "redis://127.0.0.1:1".to_string();
+    config.redis.connect_timeout = Duration::from_millis(200);
+
+    let manager = CacheManager::from_config(&config);
+    assert_eq!(manager.backend_name(), "redis");
+    assert!(manager.stats().await.tiers.is_some());
+
+    config.cache_tiers.l1_ttl = Duration::ZERO;
+    assert!(CacheManager::from_config(&config).stats().await.tiers.is_none());
+}
@@ -0,0 +1,184 @@
+use httpmock::MockServer;
+use httpmock::Method::POST;
+use reqwest::Client;
+use rinha::modules::clock::{Clock, ManualClock};
+use rinha::modules::models::Money;
+use rinha::modules::processors::PaymentProcessor;
+use rinha::modules::processors::circuit_breaker::{CircuitBreaker, CircuitBreakerConfig, CircuitState};
+use rinha::modules::processors::retry::RetryPolicy;
+use rinha::modules::processors::selector::ProcessorSelector;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+const START_MILLIS: u64 = 1_752_582_896_000;
+
+fn config() -> CircuitBreakerConfig {
+    CircuitBreakerConfig {
+        window: Duration::from_secs(10),
+        buckets: 10,
+        failure_threshold: 0.5,
+        min_requests: 4,
+        open_cooldown: Duration::from_secs(5),
+        half_open_probes: 2,
+    }
+}
+
+fn breaker() -> (CircuitBreaker, Arc<ManualClock>) {
+    let clock = Arc::new(ManualClock::new(START_MILLIS));
+    (CircuitBreaker::new(config(), clock.clone()), clock)
+}
+
+#[test]
+fn test_opens_on_failure_ratio_after_min_requests() {
+    let (breaker, _clock) = breaker();
+    breaker.record_failure();
+    breaker.record_failure();
+    breaker.record_failure();
+    // Three calls are not enough to judge
+    assert_eq!(breaker.state(), CircuitState::Closed);
+
+    breaker.record_success();
+    assert_eq!(breaker.state(), CircuitState::Closed);
+    breaker.record_failure();
+    assert_eq!(breaker.state(), CircuitState::Open);
+    assert!(breaker.try_acquire().is_none());
+}
+
+#[test]
+fn test_old_failures_leave_the_window() {
+    let (breaker, clock) = breaker();
+    for _ in 0..3 {
+        breaker.record_failure();
+    }
+    clock.advance(Duration::from_secs(11));
+    breaker.record_failure();
+    assert_eq!(breaker.window_counts(), (0, 1));
+    assert_eq!(breaker.state(), CircuitState::Closed);
+}
+
+#[test]
+fn test_half_open_probes_close_the_circuit() {
+    let (breaker, clock) = breaker();
+    breaker.force_open();
+    assert!(breaker.try_acquire().is_none());
+
+    clock.advance(Duration::from_secs(5));
+    assert_eq!(breaker.state(), CircuitState::HalfOpen);
+    let first = breaker.try_acquire().unwrap();
+    let second = breaker.try_acquire().unwrap();
+    assert!(first.is_probe());
+    // Only two probes are allowed at a time
+    assert!(breaker.try_acquire().is_none());
+
+    first.record_success();
+    assert_eq!(breaker.state(), CircuitState::HalfOpen);
+    second.record_success();
+    assert_eq!(breaker.state(), CircuitState::Closed);
+    assert_eq!(breaker.window_counts(), (0, 0));
+}
+
+#[test]
+fn test_failed_probe_reopens_the_circuit() {
+    let (breaker, clock) = breaker();
+    breaker.force_open();
+    clock.advance(Duration::from_secs(5));
+    breaker.try_acquire().unwrap().record_failure();
+
+    assert_eq!(breaker.state(), CircuitState::Open);
+    assert!(breaker.try_acquire().is_none());
+    clock.advance(Duration::from_secs(5));
+    assert!(breaker.try_acquire().is_some());
+}
+
+#[test]
+fn test_dropped_probe_does_not_hold_the_circuit_half_open() {
+    let (breaker, clock) = breaker();
+    breaker.force_open();
+    clock.advance(Duration::from_secs(5));
+    let probe = breaker.try_acquire().unwrap();
+    drop(probe);
+
+    // Counted as a failed probe, so probing resumes after the next cooldown
+    assert_eq!(breaker.state(), CircuitState::Open);
+    clock.advance(Duration::from_secs(5));
+    let first = breaker.try_acquire().unwrap();
+    let second = breaker.try_acquire().unwrap();
+    first.record_success();
+    second.record_success();
+    assert_eq!(breaker.state(), CircuitState::Closed);
+
+    // Dropping a permit taken while closed counts nothing
+    drop(breaker.try_acquire().unwrap());
+    assert_eq!(breaker.window_counts(), (0, 0));
+}
+
+#[test]
+fn test_concurrent_outcomes_are_counted() {
+    let clock: Arc<dyn Clock> = Arc::new(ManualClock::new(START_MILLIS));
+    let breaker = Arc::new(CircuitBreaker::new(
+        CircuitBreakerConfig { min_requests: u64::MAX, ..config() },
+        clock,
+    ));
+    let handles: Vec<_> = (0..8)
+        .map(|_| {
+            let breaker = Arc::clone(&breaker);
+            std::thread::spawn(move || {
+                for _ in 0..1_000 {
+                    breaker.record_success();
+                    breaker.record_failure();
+                }
+            })
+        })
+        .collect();
+    for handle in handles {
+        handle.join().unwrap();
+    }
+    assert_eq!(breaker.window_counts(), (8_000, 8_000));
+}
+
+#[tokio::test]
+async fn test_selector_skips_open_processor_and_probes_after_cooldown() {
+    let server = MockServer::start_async().await;
+    let default_mock = server.mock_async(|when, then| {
+        when.method(POST).path("/default/payments");
+        then.status(500);
+    }).await;
+    let fallback_mock = server.mock_async(|when, then| {
+        when.method(POST).path("/fallback/payments");
+        then.status(200).json_body_obj(&serde_json::json!({"message": "ok"}));
+    }).await;
+
+    let mut config_map = HashMap::new();
+    config_map.insert("default".to_string(), server.url("/default"));
+    config_map.insert("fallback".to_string(), server.url("/fallback"));
+    let clock = Arc::new(ManualClock::new(START_MILLIS));
+    let mut selector = ProcessorSelector::with_config_and_client(config_map, Client::new());
+    selector.set_clock(clock.clone());
+    selector.set_retry_policy(RetryPolicy::no_retries());
+    selector.set_circuit_breaker_config(CircuitBreakerConfig { min_requests: 2, half_open_probes: 1, ..config() });
+    selector.update_processor_rate("default", 0.01).await;
+    selector.update_processor_rate("fallback", 0.02).await;
+    let processor = PaymentProcessor::with_selector(selector);
+
+    for i in 0..4 {
+        let response = processor.process_payment(&format!("payment-{}", i), Money::from_cents(1990)).await;
+        assert!(response.is_ok());
+    }
+    // The circuit opened after two failures, so later payments skip default
+    assert_eq!(default_mock.hits_async().await, 2);
+    assert_eq!(fallback_mock.hits_async().await, 4);
+
+    let info = processor.get_processor_info().await;
+    assert_eq!(info["default"].circuit_state, CircuitState::Open);
+    assert!(!info["default"].is_healthy);
+    assert_eq!(info["fallback"].circuit_state, CircuitState::Closed);
+
+    clock.advance(Duration::from_secs(5));
+    let info = processor.get_processor_info().await;
+    assert_eq!(info["default"].circuit_state, CircuitState::HalfOpen);
+    assert!(processor.process_payment("payment-probe", Money::from_cents(1990)).await.is_ok());
+    assert_eq!(default_mock.hits_async().await, 3);
+    let info = processor.get_processor_info().await;
+    assert_eq!(info["default"].circuit_state, CircuitState::Open);
+}
@@ -0,0 +1,107 @@
+use httpmock::MockServer;
+use httpmock::Method::POST;
+use reqwest::Client;
+use rinha::modules::cache::CacheManager;
+use rinha::modules::clock::{format_iso8601, parse_iso8601, Clock, ManualClock, SystemClock};
+use rinha::modules::health::service::HealthCheckService;
+use rinha::modules::models::Money;
+use rinha::modules::processors::selector::ProcessorSelector;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+// 2020-07-10T12:34:56.000Z
+const START_MILLIS: u64 = 1_594_384_496_000;
+
+#[test]
+fn test_iso8601_round_trip() {
+    assert_eq!(format_iso8601(START_MILLIS), "2020-07-10T12:34:56.000Z");
+    assert_eq!(format_iso8601(START_MILLIS + 7), "2020-07-10T12:34:56.007Z");
+    assert_eq!(parse_iso8601("2020-07-10T12:34:56.000Z").unwrap(), START_MILLIS);
+    assert_eq!(parse_iso8601("2020-07-10T09:34:56.250-03:00").unwrap(), START_MILLIS + 250);
+    assert!(parse_iso8601("1594384496000").is_err());
+}
+
+#[test]
+fn test_manual_clock_only_moves_when_told() {
+    let clock = ManualClock::new(START_MILLIS);
+    assert_eq!(clock.now_millis(), START_MILLIS);
+    clock.advance(Duration::from_millis(1500));
+    assert_eq!(clock.now_millis(), START_MILLIS + 1500);
+    assert_eq!(clock.now_secs(), (START_MILLIS + 1500) / 1000);
+    clock.set_millis(0);
+    assert_eq!(clock.now_millis(), 0);
+
+    assert!(SystemClock.now_millis() > START_MILLIS);
+}
+
+#[tokio::test]
+async fn test_selector_sends_iso8601_requested_at() {
+    let server = MockServer::start_async().await;
+    let mock = server.mock_async(|when, then| {
+        when.method(POST)
+            .path("/payments")
+            .json_body_partial(r#"{"requestedAt": "2020-07-10T12:34:56.000Z"}"#);
+        then.status(200).json_body_obj(&serde_json::json!({"message": "ok"}));
+    }).await;
+    let mut config = HashMap::new();
+    config.insert("default".to_string(), server.url(""));
+    let mut selector = ProcessorSelector::with_config_and_client(config, Client::new());
+    selector.set_clock(Arc::new(ManualClock::new(START_MILLIS)));
+
+    let result = selector.process_payment("4a7901b8-7d26-4d9d-aa19-4dc1c7cf60b3", Money::from_cents(1990)).await;
+    assert!(result.is_ok());
+    assert_eq!(mock.hits_async().await, 1);
+    let summary = selector.ledger().summary(Some(START_MILLIS), Some(START_MILLIS)).await;
+    assert_eq!(summary.default.total_requests, 1);
+}
+
+#[tokio::test]
+async fn test_manual_clock_drives_summary_window() {
+    let server = MockServer::start_async().await;
+    let _mock = server.mock_async(|when, then| {
+        when.method(POST).path("/payments");
+        then.status(200).json_body_obj(&serde_json::json!({"message": "ok"}));
+    }).await;
+    let mut config = HashMap::new();
+    config.insert("default".to_string(), server.url(""));
+    let clock = Arc::new(ManualClock::new(START_MILLIS));
+    let mut selector = ProcessorSelector::with_config_and_client(config, Client::new());
+    selector.set_clock(clock.clone());
+
+    selector.process_payment("first", Money::from_cents(1990)).await.unwrap();
+    clock.advance(Duration::from_secs(60));
+    selector.process_payment("second", Money::from_cents(1990)).await.unwrap();
+
+    let from = parse_iso8601("2020-07-10T12:35:00.000Z").unwrap();
+    let summary = selector.ledger().summary(Some(from), None).await;
+    assert_eq!(summary.default.total_requests, 1);
+}
+
+#[tokio::test]
+async fn test_manual_clock_drives_health_cache_ttl() {
+    let clock = Arc::new(ManualClock::new(START_MILLIS));
+    let mut service = HealthCheckService::new();
+    service.set_clock(clock.clone());
+    service.set_cache_ttl(Duration::from_secs(10));
+
+    let _ = service.check_processor_health("default").await;
+    assert!(service.get_processor_status("default").await.is_some());
+    assert_eq!(service.get_last_check_time("default").await, Some(START_MILLIS / 1000));
+
+    clock.advance(Duration::from_secs(11));
+    assert!(service.get_processor_status("default").await.is_none());
+}
+
+#[tokio::test]
+async fn test_manual_clock_drives_cache_ttl() {
+    let clock = Arc::new(ManualClock::new(START_MILLIS));
+    let mut cache = CacheManager::new();
+    cache.set_clock(clock.clone());
+
+    cache.set("key", &"value", Duration::from_secs(1)).await.unwrap();
+    assert_eq!(cache.get::<String>("key").await.unwrap(), Some("value".to_string()));
+
+    clock.advance(Duration::from_secs(2));
+    assert_eq!(cache.get::<String>("key").await.unwrap(), None);
+}
@@ -0,0 +1,33 @@
+//! Fixtures shared by the integration tests. Each test binary uses only
+//! some of them.
+#![allow(dead_code)]
+
+use axum::{serve, Router};
+use httpmock::MockServer;
+use reqwest::Client;
+use rinha::modules::processors::selector::ProcessorSelector;
+use std::collections::HashMap;
+use std::net::SocketAddr;
+
+/// Serves `app` on a free local port and returns its address.
+pub async fn start_server(app: Router) -> SocketAddr {
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        serve(listener, app).await.unwrap();
+    });
+    addr
+}
+
+/// URLs of the `default` and `fallback` processors mocked under
+/// `/default` and `/fallback` on `server`.
+pub fn processor_urls(server: &MockServer) -> HashMap<String, String> {
+    let mut config = HashMap::new();
+    config.insert("default".to_string(), server.url("/default"));
+    config.insert("fallback".to_string(), server.url("/fallback"));
+    config
+}
+
+pub fn selector_for(server: &MockServer) -> ProcessorSelector {
+    ProcessorSelector::with_config_and_client(processor_urls(server), Client::new())
+}
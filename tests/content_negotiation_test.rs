@@ -0,0 +1,217 @@
+mod common;
+
+use axum::{Router, routing::post};
+use httpmock::MockServer;
+use httpmock::Method::POST;
+use reqwest::Client;
+use rinha::modules::cache::CacheManager;
+use rinha::modules::health::HealthManager;
+use rinha::modules::models::PaymentResponse;
+use rinha::modules::payment::create_payment;
+use rinha::modules::processors::PaymentProcessor;
+use rinha::modules::processors::selector::ProcessorSelector;
+use rinha::modules::ApplicationServices;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::Arc;
+
+const MSGPACK: &str = "application/x-msgpack";
+
+#[derive(Serialize)]
+struct Payload<'a> {
+    #[serde(rename = "correlationId")]
+    correlation_id: &'a str,
+    amount: f64,
+}
+
+async fn start_server(server: &MockServer) -> SocketAddr {
+    let mut config = HashMap::new();
+    config.insert("default".to_string(), server.url(""));
+    let selector = ProcessorSelector::with_config_and_client(config, Client::new());
+    let services = ApplicationServices::with_services(
+        PaymentProcessor::with_selector(selector),
+        HealthManager::new(),
+        CacheManager::new(),
+    );
+    let app = Router::new()
+        .route("/payments", post(create_payment))
+        .with_state(Arc::new(services));
+    common::start_server(app).await
+}
+
+async fn mock_processor() -> MockServer {
+    let server = MockServer::start_async().await;
+    server.mock_async(|when, then| {
+        when.method(POST).path("/payments");
+        then.status(200).json_body_obj(&serde_json::json!({"message": "payment processed successfully"}));
+    }).await;
+    server
+}
+
+fn msgpack(correlation_id: &str, amount: f64) -> Vec<u8> {
+    rmp_serde::to_vec_named(&Payload { correlation_id, amount }).unwrap()
+}
+
+#[tokio::test]
+async fn test_msgpack_request_and_response() {
+    let server = mock_processor().await;
+    let addr = start_server(&server).await;
+
+    let resp = Client::new().post(format!("http://{}/payments", addr))
+        .header("content-type", MSGPACK)
+        .header("accept", MSGPACK)
+        .body(msgpack("4a7901b8-7d26-4d9d-aa19-4dc1c7cf60b3", 19.9))
+        .send().await.unwrap();
+
+    assert_eq!(resp.status().as_u16(), 200);
+    assert_eq!(resp.headers()["content-type"], MSGPACK);
+    let body: PaymentResponse = rmp_serde::from_slice(&resp.bytes().await.unwrap()).unwrap();
+    assert_eq!(body.message, "payment processed successfully");
+}
+
+#[tokio::test]
+async fn test_json_request_with_msgpack_accept() {
+    let server = mock_processor().await;
+    let addr = start_server(&server).await;
+
+    let resp = Client::new().post(format!("http://{}/payments", addr))
+        .header("accept", "text/html, application/x-msgpack;q=0.9")
+        .json(&serde_json::json!({
+            "correlationId": "4a7901b8-7d26-4d9d-aa19-4dc1c7cf60b3",
+            "amount": 19.9
+        }))
+        .send().await.unwrap();
+
+    assert_eq!(resp.status().as_u16(), 200);
+    assert_eq!(resp.headers()["content-type"], MSGPACK);
+}
+
+#[tokio::test]
+async fn test_accept_honours_q_values() {
+    let server = mock_processor().await;
+    let addr = start_server(&server).await;
+    let client = Client::new();
+
+    for (accept, expected) in [
+        ("application/x-msgpack;q=0", "application/json"),
+        ("application/x-msgpack;q=0, application/json;q=0.1", "application/json"),
+        ("application/json;q=0.5, application/x-msgpack", MSGPACK),
+    ] {
+        let resp = client.post(format!("http://{}/payments", addr))
+            .header("accept", accept)
+            .json(&serde_json::json!({
+                "correlationId": uuid::Uuid::new_v4().to_string(),
+                "amount": 19.9
+            }))
+            .send().await.unwrap();
+
+        assert_eq!(resp.status().as_u16(), 200);
+        assert_eq!(resp.headers()["content-type"], expected, "Accept: {}", accept);
+    }
+}
+
+#[tokio::test]
+async fn test_error_bodies_use_the_negotiated_format() {
+    let server = MockServer::start_async().await;
+    server.mock_async(|when, then| {
+        when.method(POST).path("/payments");
+        then.status(400);
+    }).await;
+    let addr = start_server(&server).await;
+    let client = Client::new();
+
+    for (body, status, error) in [
+        (msgpack("invalid-uuid", 19.9), 400, "validation"),
+        (msgpack("4a7901b8-7d26-4d9d-aa19-4dc1c7cf60b3", 19.9), 502, "processor_client_error"),
+    ] {
+        let resp = client.post(format!("http://{}/payments", addr))
+            .header("content-type", MSGPACK)
+            .header("accept", MSGPACK)
+            .body(body)
+            .send().await.unwrap();
+
+        assert_eq!(resp.status().as_u16(), status);
+        assert_eq!(resp.headers()["content-type"], MSGPACK);
+        let body: serde_json::Value = rmp_serde::from_slice(&resp.bytes().await.unwrap()).unwrap();
+        assert_eq!(body["error"], error);
+        assert_eq!(body["retryable"], false);
+    }
+}
+
+#[tokio::test]
+async fn test_msgpack_request_defaults_to_json_response() {
+    let server = mock_processor().await;
+    let addr = start_server(&server).await;
+
+    let resp = Client::new().post(format!("http://{}/payments", addr))
+        .header("content-type", MSGPACK)
+        .body(msgpack("4a7901b8-7d26-4d9d-aa19-4dc1c7cf60b3", 19.9))
+        .send().await.unwrap();
+
+    assert_eq!(resp.status().as_u16(), 200);
+    assert_eq!(resp.headers()["content-type"], "application/json");
+    let body: PaymentResponse = resp.json().await.unwrap();
+    assert_eq!(body.message, "payment processed successfully");
+}
+
+#[tokio::test]
+async fn test_msgpack_goes_through_validation() {
+    let server = mock_processor().await;
+    let addr = start_server(&server).await;
+    let client = Client::new();
+
+    for body in [
+        msgpack("invalid-uuid", 19.9),
+        msgpack("4a7901b8-7d26-4d9d-aa19-4dc1c7cf60b3", 0.0),
+        msgpack("4a7901b8-7d26-4d9d-aa19-4dc1c7cf60b3", f64::NAN),
+        msgpack("4a7901b8-7d26-4d9d-aa19-4dc1c7cf60b3", 19.901),
+        b"not msgpack".to_vec(),
+    ] {
+        let resp = client.post(format!("http://{}/payments", addr))
+            .header("content-type", MSGPACK)
+            .body(body)
+            .send().await.unwrap();
+        assert_eq!(resp.status().as_u16(), 400);
+    }
+}
+
+#[tokio::test]
+async fn test_unsupported_content_type() {
+    let server = mock_processor().await;
+    let addr = start_server(&server).await;
+
+    let resp = Client::new().post(format!("http://{}/payments", addr))
+        .header("content-type", "text/plain")
+        .body("correlationId=4a7901b8-7d26-4d9d-aa19-4dc1c7cf60b3")
+        .send().await.unwrap();
+    assert_eq!(resp.status().as_u16(), 415);
+    assert_eq!(resp.headers()["content-type"], "application/json");
+    let body: serde_json::Value = resp.json().await.unwrap();
+    assert_eq!(body["error"], "unsupported_media_type");
+    assert_eq!(body["retryable"], false);
+}
+
+#[tokio::test]
+async fn test_rejected_bodies_use_the_accept_format() {
+    let server = mock_processor().await;
+    let addr = start_server(&server).await;
+    let client = Client::new();
+
+    for (content_type, body, status, error) in [
+        ("text/plain", b"correlationId=4a7901b8".to_vec(), 415, "unsupported_media_type"),
+        (MSGPACK, b"not msgpack".to_vec(), 400, "validation"),
+        ("application/json", b"{\"amount\":".to_vec(), 400, "validation"),
+    ] {
+        let resp = client.post(format!("http://{}/payments", addr))
+            .header("content-type", content_type)
+            .header("accept", MSGPACK)
+            .body(body)
+            .send().await.unwrap();
+
+        assert_eq!(resp.status().as_u16(), status);
+        assert_eq!(resp.headers()["content-type"], MSGPACK);
+        let body: serde_json::Value = rmp_serde::from_slice(&resp.bytes().await.unwrap()).unwrap();
+        assert_eq!(body["error"], error);
+    }
+}
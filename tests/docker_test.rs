@@ -40,7 +40,7 @@ fn test_docker_compose_has_resource_limits() {
 fn test_docker_build_succeeds() {
 
     let output = Command::new("docker")
-        .args(&["build", "-t", "rinha-backend:test", "."])
+        .args(["build", "-t", "rinha-backend:test", "."])
         .output();
     
     assert!(output.is_ok(), "Docker build command should be available");
@@ -109,7 +109,7 @@ fn test_t1_2_docker_compose_configuration() {
     
     // Verificar se Redis tem configuração de memória
     assert!(compose_content.contains("--maxmemory 50mb"), "Redis should have maxmemory config");
-    assert!(compose_content.contains("--maxmemory-policy allkeys-lru"), "Redis should have LRU policy");
+    assert!(compose_content.contains("--maxmemory-policy volatile-lru"), "Redis should evict only keys with a TTL");
     
     // Verificar se tem rede payment-processor
     assert!(compose_content.contains("payment-processor"), "Should have payment-processor network");
@@ -142,5 +142,5 @@ fn test_t1_2_docker_build_size() {
     }
     
     // Se chegou aqui, o Dockerfile está sintaticamente correto
-    assert!(true, "Dockerfile should be syntactically correct");
+    assert!(build_output.is_ok(), "Dockerfile should be syntactically correct");
 } 
\ No newline at end of file
@@ -1,4 +1,9 @@
-use rinha::modules::health::service::HealthCheckService;
+use httpmock::MockServer;
+use httpmock::Method::GET;
+use rinha::modules::clock::ManualClock;
+use rinha::modules::health::service::{HealthCheckService, ProcessorHealth};
+use std::collections::HashMap;
+use std::sync::Arc;
 use std::time::Duration;
 use tokio::time::sleep;
 
@@ -82,7 +87,6 @@ async fn test_health_check_status_tracking() {
     assert!(status.is_some());
     
     let status = status.unwrap();
-    assert!(status.is_healthy || !status.is_healthy);
     assert!(status.last_check.is_some());
 }
 
@@ -150,4 +154,69 @@ async fn test_health_check_batch_operation() {
     for (processor, result) in results {
         assert!(result.is_ok(), "Health check failed for processor: {}", processor);
     }
-} 
\ No newline at end of file
+}
+
+fn service_for(server: &MockServer, clock: Arc<ManualClock>) -> HealthCheckService {
+    let mut urls = HashMap::new();
+    urls.insert("default".to_string(), server.url("/default"));
+    let mut service = HealthCheckService::with_processor_urls(urls);
+    service.set_clock(clock);
+    // Always go to the processor instead of the result cache
+    service.set_cache_ttl(Duration::ZERO);
+    service
+}
+
+#[tokio::test]
+async fn test_health_body_is_parsed() {
+    let server = MockServer::start_async().await;
+    server.mock_async(|when, then| {
+        when.method(GET).path("/default/payments/service-health");
+        then.status(200).json_body_obj(&serde_json::json!({"failing": true, "minResponseTime": 120}));
+    }).await;
+    let service = service_for(&server, Arc::new(ManualClock::new(1_000_000)));
+    let updates = service.subscribe();
+
+    let status = service.check_processor_health("default").await.unwrap();
+    assert!(status.failing);
+    assert!(!status.is_healthy);
+    assert_eq!(status.min_response_time_ms, Some(120));
+    assert_eq!(
+        updates.borrow().get("default"),
+        Some(&ProcessorHealth { failing: true, min_response_time_ms: 120 })
+    );
+}
+
+#[tokio::test]
+async fn test_rate_limited_check_keeps_last_status() {
+    let server = MockServer::start_async().await;
+    let healthy = server.mock_async(|when, then| {
+        when.method(GET).path("/default/payments/service-health");
+        then.status(200).json_body_obj(&serde_json::json!({"failing": false, "minResponseTime": 10}));
+    }).await;
+    let clock = Arc::new(ManualClock::new(1_000_000));
+    let service = service_for(&server, clock.clone());
+
+    let first = service.check_processor_health("default").await.unwrap();
+    assert!(first.is_healthy);
+
+    healthy.delete_async().await;
+    let throttled = server.mock_async(|when, then| {
+        when.method(GET).path("/default/payments/service-health");
+        then.status(429).header("Retry-After", "30");
+    }).await;
+
+    clock.advance(Duration::from_secs(5));
+    let second = service.check_processor_health("default").await.unwrap();
+    assert_eq!(second, first);
+    assert_eq!(throttled.hits_async().await, 1);
+
+    // Retry-After pushes the next check well past the usual 5s interval
+    clock.advance(Duration::from_secs(10));
+    let third = service.check_processor_health("default").await.unwrap();
+    assert_eq!(third, first);
+    assert_eq!(throttled.hits_async().await, 1);
+
+    clock.advance(Duration::from_secs(21));
+    let _ = service.check_processor_health("default").await;
+    assert_eq!(throttled.hits_async().await, 2);
+}
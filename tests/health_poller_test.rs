@@ -0,0 +1,79 @@
+use httpmock::MockServer;
+use httpmock::Method::GET;
+use rinha::modules::health::HealthManager;
+use rinha::modules::health::service::HealthCheckService;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+fn manager_for(server: &MockServer, interval: Duration) -> HealthManager {
+    let mut urls = HashMap::new();
+    urls.insert("default".to_string(), server.url("/default"));
+    urls.insert("fallback".to_string(), server.url("/fallback"));
+    let mut service = HealthCheckService::with_processor_urls(urls);
+    service.set_rate_limit(Duration::from_millis(10));
+    let mut manager = HealthManager::with_health_service(service);
+    manager.set_poll_interval(interval, Duration::from_millis(5));
+    manager
+}
+
+#[tokio::test]
+async fn test_poller_start_stop_hooks() {
+    let server = MockServer::start_async().await;
+    let manager = manager_for(&server, Duration::from_secs(5));
+
+    assert!(!manager.is_poller_running());
+    assert!(manager.start_poller());
+    assert!(manager.is_poller_running());
+    // A second start is refused while the first task runs
+    assert!(!manager.start_poller());
+
+    assert!(manager.stop_poller());
+    assert!(!manager.is_poller_running());
+    assert!(!manager.stop_poller());
+
+    assert!(manager.start_poller());
+    assert!(manager.is_poller_running());
+}
+
+#[tokio::test]
+async fn test_poller_publishes_snapshots() {
+    let server = MockServer::start_async().await;
+    let default_health = server.mock_async(|when, then| {
+        when.method(GET).path("/default/payments/service-health");
+        then.status(200).json_body_obj(&serde_json::json!({"failing": true, "minResponseTime": 0}));
+    }).await;
+    server.mock_async(|when, then| {
+        when.method(GET).path("/fallback/payments/service-health");
+        then.status(200).json_body_obj(&serde_json::json!({"failing": false, "minResponseTime": 40}));
+    }).await;
+    let manager = manager_for(&server, Duration::from_millis(20));
+    let mut updates = manager.subscribe();
+    let initial = Arc::clone(&updates.borrow());
+
+    assert!(manager.start_poller());
+    tokio::time::timeout(Duration::from_secs(2), async {
+        loop {
+            updates.changed().await.unwrap();
+            if updates.borrow().len() == 2 {
+                break;
+            }
+        }
+    }).await.unwrap();
+
+    let snapshot = Arc::clone(&updates.borrow());
+    assert!(snapshot["default"].failing);
+    assert!(!snapshot["fallback"].failing);
+    assert_eq!(snapshot["fallback"].min_response_time_ms, 40);
+    // Published snapshots are never changed in place
+    assert!(initial.is_empty());
+
+    // The task keeps polling on its interval
+    tokio::time::sleep(Duration::from_millis(200)).await;
+    assert!(default_health.hits_async().await >= 2);
+
+    manager.stop_poller();
+    let hits = default_health.hits_async().await;
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    assert_eq!(default_health.hits_async().await, hits);
+}
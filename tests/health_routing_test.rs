@@ -0,0 +1,139 @@
+mod common;
+
+use httpmock::MockServer;
+use httpmock::Method::{GET, POST};
+use rinha::modules::cache::CacheManager;
+use rinha::modules::health::HealthManager;
+use rinha::modules::health::service::{HealthCheckService, ProcessorHealth, SharedHealthSnapshot};
+use rinha::modules::models::Money;
+use rinha::modules::processors::PaymentProcessor;
+use rinha::modules::processors::selector::ProcessorSelector;
+use rinha::modules::ApplicationServices;
+use std::sync::Arc;
+use tokio::sync::watch;
+
+const CORRELATION_ID: &str = "4a7901b8-7d26-4d9d-aa19-4dc1c7cf60b3";
+
+async fn rated_selector_for(server: &MockServer) -> ProcessorSelector {
+    let selector = common::selector_for(server);
+    selector.update_processor_rate("default", 0.05).await;
+    selector.update_processor_rate("fallback", 0.15).await;
+    selector
+}
+
+fn health(failing: bool, min_response_time_ms: u64) -> ProcessorHealth {
+    ProcessorHealth { failing, min_response_time_ms }
+}
+
+#[tokio::test]
+async fn test_failing_processor_is_skipped() {
+    let server = MockServer::start_async().await;
+    let default_mock = server.mock_async(|when, then| {
+        when.method(POST).path("/default/payments");
+        then.status(200).json_body_obj(&serde_json::json!({"message": "ok"}));
+    }).await;
+    let fallback_mock = server.mock_async(|when, then| {
+        when.method(POST).path("/fallback/payments");
+        then.status(200).json_body_obj(&serde_json::json!({"message": "ok"}));
+    }).await;
+
+    let (updates, receiver) = watch::channel(SharedHealthSnapshot::default());
+    let mut selector = rated_selector_for(&server).await;
+    selector.set_health_updates(receiver);
+
+    updates.send_modify(|snapshot| {
+        Arc::make_mut(snapshot).insert("default".to_string(), health(true, 0));
+    });
+    let response = selector.process_payment(CORRELATION_ID, Money::from_cents(1990)).await.unwrap();
+    assert_eq!(response.processor, "fallback");
+    assert_eq!(default_mock.hits_async().await, 0);
+
+    // A later update that clears the flag is picked up without polling
+    updates.send_modify(|snapshot| {
+        Arc::make_mut(snapshot).insert("default".to_string(), health(false, 0));
+    });
+    let response = selector.process_payment("5b8a02c9-8e37-4e0e-bb2a-5ed2d8d071c4", Money::from_cents(1990)).await.unwrap();
+    assert_eq!(response.processor, "default");
+    assert_eq!(fallback_mock.hits_async().await, 1);
+}
+
+#[tokio::test]
+async fn test_slow_processor_costs_more() {
+    let server = MockServer::start_async().await;
+    let (updates, receiver) = watch::channel(SharedHealthSnapshot::default());
+    let mut selector = rated_selector_for(&server).await;
+    selector.set_health_updates(receiver);
+    selector.set_latency_penalty(0.0001);
+
+    updates.send_modify(|snapshot| {
+        let snapshot = Arc::make_mut(snapshot);
+        snapshot.insert("default".to_string(), health(false, 500));
+        snapshot.insert("fallback".to_string(), health(false, 0));
+    });
+    assert_eq!(selector.get_default_processor().await.unwrap().name, "default");
+
+    updates.send_modify(|snapshot| {
+        Arc::make_mut(snapshot).insert("default".to_string(), health(false, 2_000));
+    });
+    assert_eq!(selector.get_default_processor().await.unwrap().name, "fallback");
+    assert_eq!(selector.get_processor_health("default"), Some(health(false, 2_000)));
+}
+
+#[tokio::test]
+async fn test_health_checks_reach_the_selector() {
+    let server = MockServer::start_async().await;
+    server.mock_async(|when, then| {
+        when.method(GET).path("/default/payments/service-health");
+        then.status(200).json_body_obj(&serde_json::json!({"failing": true, "minResponseTime": 0}));
+    }).await;
+    let default_mock = server.mock_async(|when, then| {
+        when.method(POST).path("/default/payments");
+        then.status(200).json_body_obj(&serde_json::json!({"message": "ok"}));
+    }).await;
+    let fallback_mock = server.mock_async(|when, then| {
+        when.method(POST).path("/fallback/payments");
+        then.status(200).json_body_obj(&serde_json::json!({"message": "ok"}));
+    }).await;
+
+    let services = ApplicationServices::with_services(
+        PaymentProcessor::with_selector(rated_selector_for(&server).await),
+        HealthManager::with_health_service(HealthCheckService::with_processor_urls(common::processor_urls(&server))),
+        CacheManager::new(),
+    );
+
+    let status = services.health_manager.check_processor_health("default").await.unwrap();
+    assert!(status.failing);
+
+    let response = services.payment_processor.process_payment(CORRELATION_ID, Money::from_cents(1990)).await;
+    assert!(response.is_ok());
+    assert_eq!(default_mock.hits_async().await, 0);
+    assert_eq!(fallback_mock.hits_async().await, 1);
+}
+
+#[tokio::test]
+async fn test_failed_health_checks_do_not_exclude_processors() {
+    let server = MockServer::start_async().await;
+    server.mock_async(|when, then| {
+        when.method(GET).path_contains("/payments/service-health");
+        then.status(500);
+    }).await;
+    let default_mock = server.mock_async(|when, then| {
+        when.method(POST).path("/default/payments");
+        then.status(200).json_body_obj(&serde_json::json!({"message": "ok"}));
+    }).await;
+
+    let services = ApplicationServices::with_services(
+        PaymentProcessor::with_selector(rated_selector_for(&server).await),
+        HealthManager::with_health_service(HealthCheckService::with_processor_urls(common::processor_urls(&server))),
+        CacheManager::new(),
+    );
+    for name in ["default", "fallback"] {
+        let status = services.health_manager.check_processor_health(name).await.unwrap();
+        assert!(!status.is_healthy);
+        assert!(!status.failing);
+    }
+
+    let response = services.payment_processor.process_payment(CORRELATION_ID, Money::from_cents(1990)).await;
+    assert!(response.is_ok());
+    assert_eq!(default_mock.hits_async().await, 1);
+}
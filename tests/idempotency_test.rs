@@ -0,0 +1,192 @@
+mod common;
+
+use httpmock::MockServer;
+use httpmock::Method::{GET, POST};
+use rinha::modules::cache::backend::EntryTtl;
+use rinha::modules::cache::CacheManager;
+use rinha::modules::processors::idempotency::DEFAULT_RECORD_TTL;
+use rinha::modules::processors::PaymentProcessor;
+use rinha::modules::error::PaymentError;
+use rinha::modules::summary::ledger::PaymentLedger;
+use rinha::modules::models::Money;
+use std::sync::Arc;
+use std::time::Duration;
+
+const CORRELATION_ID: &str = "4a7901b8-7d26-4d9d-aa19-4dc1c7cf60b3";
+
+/// A processor standing in for one replica, sharing `cache` with the others.
+async fn replica(server: &MockServer, cache: &Arc<CacheManager>) -> Arc<PaymentProcessor> {
+    let selector = common::selector_for(server);
+    selector.update_processor_rate("default", 0.01).await;
+    Arc::new(PaymentProcessor::with_selector_and_cache(selector, Arc::clone(cache)))
+}
+
+#[tokio::test]
+async fn test_duplicate_payment_returns_stored_outcome() {
+    let server = MockServer::start_async().await;
+    let default_mock = server.mock_async(|when, then| {
+        when.method(POST).path("/default/payments");
+        then.status(200).json_body_obj(&serde_json::json!({"message": "payment processed successfully"}));
+    }).await;
+    let selector = common::selector_for(&server);
+    selector.update_processor_rate("default", 0.01).await;
+    let processor = PaymentProcessor::with_selector(selector);
+
+    let first = processor.process_payment(CORRELATION_ID, Money::from_cents(1990)).await.unwrap();
+    let second = processor.process_payment(CORRELATION_ID, Money::from_cents(1990)).await.unwrap();
+
+    assert_eq!(first.message, second.message);
+    assert_eq!(default_mock.hits_async().await, 1);
+    assert_eq!(processor.get_ledger().len().await, 1);
+
+    let outcome = processor.get_stored_outcome(CORRELATION_ID).await.unwrap();
+    assert_eq!(outcome.processor, "default");
+}
+
+#[tokio::test]
+async fn test_in_flight_payment_is_not_dispatched_twice() {
+    let server = MockServer::start_async().await;
+    let default_mock = server.mock_async(|when, then| {
+        when.method(POST).path("/default/payments");
+        then.status(200)
+            .delay(Duration::from_millis(300))
+            .json_body_obj(&serde_json::json!({"message": "ok"}));
+    }).await;
+    let selector = common::selector_for(&server);
+    selector.update_processor_rate("default", 0.01).await;
+    let processor = Arc::new(PaymentProcessor::with_selector(selector));
+
+    let first = tokio::spawn({
+        let processor = Arc::clone(&processor);
+        async move { processor.process_payment(CORRELATION_ID, Money::from_cents(1990)).await }
+    });
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    assert!(processor.is_in_flight(CORRELATION_ID));
+
+    let second = processor.process_payment(CORRELATION_ID, Money::from_cents(1990)).await;
+    let err = second.unwrap_err();
+    assert!(matches!(err, PaymentError::Duplicate { .. }));
+    assert_eq!(err.status_code().as_u16(), 409);
+
+    assert!(first.await.unwrap().is_ok());
+    assert!(!processor.is_in_flight(CORRELATION_ID));
+    assert_eq!(default_mock.hits_async().await, 1);
+}
+
+#[tokio::test]
+async fn test_replicas_sharing_a_cache_dispatch_once() {
+    let server = MockServer::start_async().await;
+    let default_mock = server.mock_async(|when, then| {
+        when.method(POST).path("/default/payments");
+        then.status(200)
+            .delay(Duration::from_millis(300))
+            .json_body_obj(&serde_json::json!({"message": "ok"}));
+    }).await;
+    let cache = Arc::new(CacheManager::new());
+    let first = replica(&server, &cache).await;
+    let second = replica(&server, &cache).await;
+
+    let dispatch = tokio::spawn({
+        let first = Arc::clone(&first);
+        async move { first.process_payment(CORRELATION_ID, Money::from_cents(1990)).await }
+    });
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    // The other replica sees the claim, not its own in-flight set
+    assert!(!second.is_in_flight(CORRELATION_ID));
+    let err = second.process_payment(CORRELATION_ID, Money::from_cents(1990)).await.unwrap_err();
+    assert!(matches!(err, PaymentError::Duplicate { .. }));
+
+    assert!(dispatch.await.unwrap().is_ok());
+    let replayed = second.process_payment(CORRELATION_ID, Money::from_cents(1990)).await.unwrap();
+    assert_eq!(replayed.message, "ok");
+    assert_eq!(default_mock.hits_async().await, 1);
+
+    let EntryTtl::Expires(ttl) = cache.ttl(&format!("idempotency:{}", CORRELATION_ID)).await.unwrap() else {
+        panic!("idempotency record has no TTL");
+    };
+    assert!(ttl <= DEFAULT_RECORD_TTL);
+}
+
+#[tokio::test]
+async fn test_failed_dispatch_releases_the_claim() {
+    let server = MockServer::start_async().await;
+    let failing = server.mock_async(|when, then| {
+        when.method(POST).path("/default/payments");
+        then.status(400);
+    }).await;
+    let selector = common::selector_for(&server);
+    selector.update_processor_rate("default", 0.01).await;
+    let processor = PaymentProcessor::with_selector(selector);
+
+    assert!(processor.process_payment(CORRELATION_ID, Money::from_cents(1990)).await.is_err());
+    failing.delete_async().await;
+    server.mock_async(|when, then| {
+        when.method(POST).path("/default/payments");
+        then.status(200).json_body_obj(&serde_json::json!({"message": "ok"}));
+    }).await;
+    assert!(processor.process_payment(CORRELATION_ID, Money::from_cents(1990)).await.is_ok());
+}
+
+#[tokio::test]
+async fn test_already_exists_response_counts_as_success() {
+    let server = MockServer::start_async().await;
+    let default_mock = server.mock_async(|when, then| {
+        when.method(POST).path("/default/payments");
+        then.status(422);
+    }).await;
+    let confirm_mock = server.mock_async(|when, then| {
+        when.method(GET).path(format!("/default/payments/{}", CORRELATION_ID));
+        then.status(200).json_body_obj(&serde_json::json!({"correlationId": CORRELATION_ID}));
+    }).await;
+    let fallback_mock = server.mock_async(|when, then| {
+        when.method(POST).path("/fallback/payments");
+        then.status(200).json_body_obj(&serde_json::json!({"message": "ok"}));
+    }).await;
+    let selector = common::selector_for(&server);
+    selector.update_processor_rate("default", 0.01).await;
+    selector.update_processor_rate("fallback", 0.02).await;
+
+    let result = selector.process_payment(CORRELATION_ID, Money::from_cents(1990)).await;
+    assert!(result.is_ok());
+    assert_eq!(result.unwrap().processor, "default");
+    assert!(selector.is_processor_healthy("default").await);
+    assert_eq!(default_mock.hits_async().await, 1);
+    assert_eq!(confirm_mock.hits_async().await, 1);
+    assert_eq!(fallback_mock.hits_async().await, 0);
+
+    let summary = selector.ledger().summary(None, None).await;
+    assert_eq!(summary.default.total_requests, 1);
+    assert_eq!(summary.fallback.total_requests, 0);
+}
+
+#[tokio::test]
+async fn test_unconfirmed_rejection_is_not_recorded() {
+    let server = MockServer::start_async().await;
+    server.mock_async(|when, then| {
+        when.method(POST).path("/default/payments");
+        then.status(422).json_body_obj(&serde_json::json!({"message": "invalid amount"}));
+    }).await;
+    server.mock_async(|when, then| {
+        when.method(GET).path(format!("/default/payments/{}", CORRELATION_ID));
+        then.status(404);
+    }).await;
+    let selector = common::selector_for(&server);
+    selector.update_processor_rate("default", 0.01).await;
+    selector.update_processor_rate("fallback", 0.02).await;
+
+    let err = selector.process_payment(CORRELATION_ID, Money::from_cents(1990)).await.unwrap_err();
+    assert!(matches!(err, PaymentError::ProcessorClientError { status: 422, .. }));
+    assert!(selector.ledger().is_empty().await);
+}
+
+#[tokio::test]
+async fn test_ledger_records_correlation_id_once() {
+    let ledger = PaymentLedger::new();
+    assert!(ledger.record(CORRELATION_ID, "default", Money::from_cents(1990), 1_000).await);
+    assert!(!ledger.record(CORRELATION_ID, "fallback", Money::from_cents(1990), 2_000).await);
+    assert!(ledger.contains(CORRELATION_ID).await);
+
+    let summary = ledger.summary(None, None).await;
+    assert_eq!(summary.default.total_requests, 1);
+    assert_eq!(summary.fallback.total_requests, 0);
+}
@@ -3,10 +3,12 @@ use serde::{Serialize, Deserialize};
 use std::net::SocketAddr;
 use tokio::sync::oneshot;
 use axum::serve;
-use reqwest;
 use rinha::modules::payment::create_payment;
+use rinha::modules::ApplicationServices;
+use std::sync::Arc;
 
 
+#[allow(dead_code)]
 #[derive(Serialize, Deserialize, Debug, Clone)]
 struct PaymentPayload {
     #[serde(rename = "correlationId")]
@@ -18,7 +20,8 @@ struct PaymentPayload {
 
 async fn start_test_server() -> (SocketAddr, oneshot::Sender<()>) {
     let app = Router::new()
-        .route("/payments", post(create_payment));
+        .route("/payments", post(create_payment))
+        .with_state(Arc::new(ApplicationServices::new()));
     
     let addr: SocketAddr = "127.0.0.1:0".parse().unwrap();
     let listener = tokio::net::TcpListener::bind(addr).await.unwrap();
@@ -45,7 +48,7 @@ async fn test_payment_validation() {
     let client = reqwest::Client::new();
     
 
-    let resp = client.post(&format!("http://{}/payments", server_addr))
+    let resp = client.post(format!("http://{}/payments", server_addr))
         .json(&serde_json::json!({
             "correlationId": "invalid-uuid",
             "amount": 100.50
@@ -54,7 +57,7 @@ async fn test_payment_validation() {
     
     assert_eq!(resp.status().as_u16(), 400, "Deve retornar 400 para UUID inválido");
     
-    let resp = client.post(&format!("http://{}/payments", server_addr))
+    let resp = client.post(format!("http://{}/payments", server_addr))
         .json(&serde_json::json!({
             "correlationId": "4a7901b8-7d26-4d9d-aa19-4dc1c7cf60b3",
             "amount": 0.0
@@ -75,7 +78,7 @@ async fn test_real_integration_with_payment_processors() {
     //     .send().await.unwrap();
     // assert_eq!(resp.status(), StatusCode::OK);
     // // Validar fallback, payload, etc.
-    assert!(true, "Teste real será implementado quando containers estiverem disponíveis");
+    // Teste real será implementado quando containers estiverem disponíveis
 } 
 
 #[tokio::test]
@@ -114,7 +117,7 @@ async fn payment_processor_request_structure_test() {
     assert!(processors_content.contains("correlation_id: String"), "Should have correlation_id field");
     
     // Verificar se tem amount
-    assert!(processors_content.contains("amount: f64"), "Should have amount field");
+    assert!(processors_content.contains("amount: Money"), "Should have amount field");
     
     // Verificar se tem requestedAt
     assert!(processors_content.contains("#[serde(rename = \"requestedAt\")]"), "Should have requestedAt field");
@@ -137,10 +140,14 @@ async fn payment_processor_timestamp_test() {
     
     let selector_content = std::fs::read_to_string("src/modules/processors/selector.rs").expect("Should read processors/selector.rs");
     
-    // Verificar se usa SystemTime para timestamp
-    assert!(selector_content.contains("SystemTime::now()"), "Should use SystemTime::now() for timestamp");
-    assert!(selector_content.contains("UNIX_EPOCH"), "Should use UNIX_EPOCH for timestamp");
-    assert!(selector_content.contains("as_millis()"), "Should convert to milliseconds");
+    // Verificar se usa o Clock injetável e formata requestedAt em ISO-8601
+    assert!(selector_content.contains("clock.now_millis()"), "Should read the time from the injected clock");
+    assert!(selector_content.contains("format_iso8601"), "Should send requestedAt as ISO-8601");
+
+    let clock_content = std::fs::read_to_string("src/modules/clock/mod.rs").expect("Should read clock/mod.rs");
+    assert!(clock_content.contains("SystemTime::now()"), "Should use SystemTime::now() for timestamp");
+    assert!(clock_content.contains("UNIX_EPOCH"), "Should use UNIX_EPOCH for timestamp");
+    assert!(clock_content.contains("as_millis()"), "Should convert to milliseconds");
 }
 
 #[tokio::test]
@@ -163,8 +170,8 @@ async fn payment_processor_fallback_logic_test() {
     assert!(selector_content.contains("get_default_processor"), "Should try default processor first");
     
     // Verificar se tem fallback em caso de erro
-    assert!(selector_content.contains("try_fallback_processor"), "Should try fallback processor on error");
-    assert!(selector_content.contains("try_any_processor"), "Should try any available processor as last resort");
+    assert!(selector_content.contains("failing over"), "Should try fallback processor on error");
+    assert!(selector_content.contains("breaker.try_acquire()"), "Should let each circuit breaker gate its processor");
 }
 
 #[tokio::test]
@@ -7,7 +7,7 @@ mod tests {
     #[tokio::test]
     async fn test_server_integration() {
         let mut server = Command::new("cargo")
-            .args(&["run"])
+            .args(["run"])
             .env("RUST_LOG", "info")
             .stdout(Stdio::piped())
             .stderr(Stdio::piped())
@@ -17,13 +17,14 @@ mod tests {
         sleep(Duration::from_secs(3)).await;
 
         let output = Command::new("curl")
-            .args(&["-s", "-o", "/dev/null", "-w", "%{http_code}", "http://localhost:9999/health"])
+            .args(["-s", "-o", "/dev/null", "-w", "%{http_code}", "http://localhost:9999/health"])
             .output()
             .expect("Failed to execute curl");
 
         let status_code = String::from_utf8_lossy(&output.stdout);
         
         let _ = server.kill();
+        let _ = server.wait();
 
         assert!(status_code == "200" || status_code == "404" || status_code == "000", 
                 "Server should respond with HTTP status. Got: {}", status_code);
@@ -31,10 +32,8 @@ mod tests {
 
     #[tokio::test]
     async fn test_server_starts_successfully() {
-        let output = Command::new("cargo")
-            .args(&["run", "--", "--help"])
-            .output();
+        let binary = std::path::Path::new(env!("CARGO_BIN_EXE_rinha"));
 
-        assert!(output.is_ok(), "Server should compile and be executable");
+        assert!(binary.is_file(), "Server should compile and be executable");
     }
 } 
\ No newline at end of file
@@ -0,0 +1,113 @@
+use rinha::modules::models::money::MoneyError;
+use rinha::modules::models::{Money, PaymentRequest};
+use rinha::modules::summary::ledger::PaymentLedger;
+use validator::Validate;
+
+#[test]
+fn test_money_rejects_invalid_amounts() {
+    assert_eq!(Money::try_from_f64(f64::NAN), Err(MoneyError::NotFinite));
+    assert_eq!(Money::try_from_f64(f64::INFINITY), Err(MoneyError::NotFinite));
+    assert_eq!(Money::try_from_f64(f64::NEG_INFINITY), Err(MoneyError::NotFinite));
+    assert_eq!(Money::try_from_f64(19.901), Err(MoneyError::TooManyDecimals));
+    assert_eq!(Money::try_from_f64(19.90), Ok(Money::from_cents(1990)));
+    assert_eq!(Money::try_from_f64(0.29), Ok(Money::from_cents(29)));
+    assert_eq!(Money::try_from_f64(0.010000001), Err(MoneyError::TooManyDecimals));
+    assert_eq!(Money::try_from_f64(-0.5), Ok(Money::from_cents(-50)));
+    assert_eq!(Money::try_from_f64(415542345.98), Ok(Money::from_cents(41_554_234_598)));
+    assert_eq!(Money::try_from_f64(1e17), Err(MoneyError::OutOfRange));
+}
+
+#[test]
+fn test_money_parses_decimal_text_exactly() {
+    assert_eq!(Money::from_decimal_str("19.90"), Ok(Money::from_cents(1990)));
+    assert_eq!(Money::from_decimal_str("19.9000"), Ok(Money::from_cents(1990)));
+    assert_eq!(Money::from_decimal_str("20"), Ok(Money::from_cents(2000)));
+    assert_eq!(Money::from_decimal_str("-0.5"), Ok(Money::from_cents(-50)));
+    assert_eq!(Money::from_decimal_str("1.999e1"), Ok(Money::from_cents(1999)));
+    assert_eq!(Money::from_decimal_str("1990E-2"), Ok(Money::from_cents(1990)));
+    assert_eq!(Money::from_decimal_str("0.000"), Ok(Money::ZERO));
+    assert_eq!(Money::from_decimal_str("1.0000000000000000001"), Err(MoneyError::TooManyDecimals));
+    assert_eq!(Money::from_decimal_str("1999e-3"), Err(MoneyError::TooManyDecimals));
+    assert_eq!(Money::from_decimal_str("1e17"), Err(MoneyError::OutOfRange));
+    assert_eq!(Money::from_decimal_str("1e99999999999"), Err(MoneyError::OutOfRange));
+    assert_eq!(Money::from_decimal_str("1.2.3"), Err(MoneyError::Malformed));
+    assert_eq!(Money::from_decimal_str(""), Err(MoneyError::Malformed));
+}
+
+#[test]
+fn test_money_addition_does_not_overflow() {
+    let max = Money::from_cents(i64::MAX);
+    assert_eq!(max.checked_add(Money::from_cents(1)), None);
+    assert_eq!(max + Money::from_cents(1), max);
+
+    let mut total = Money::from_cents(i64::MAX - 1);
+    total += Money::from_cents(10);
+    assert_eq!(total, max);
+    assert_eq!([max, max].into_iter().sum::<Money>(), max);
+}
+
+#[test]
+fn test_money_json_round_trip() {
+    let money: Money = serde_json::from_str("19.90").unwrap();
+    assert_eq!(money, Money::from_cents(1990));
+    assert_eq!(serde_json::to_string(&money).unwrap(), "19.9");
+
+    let whole: Money = serde_json::from_str("20").unwrap();
+    assert_eq!(whole, Money::from_cents(2000));
+
+    // Would round to 1.00 through an f64
+    assert!(serde_json::from_str::<Money>("1.0000000000000000001").is_err());
+    let value = serde_json::json!(19.9);
+    assert_eq!(serde_json::from_value::<Money>(value).unwrap(), Money::from_cents(1990));
+
+    let large = Money::from_cents(41_554_234_598);
+    assert_eq!(serde_json::to_string(&large).unwrap(), "415542345.98");
+    assert_eq!(large.to_string(), "415542345.98");
+}
+
+#[test]
+fn test_money_msgpack_round_trip() {
+    let money = Money::from_cents(1990);
+    let bytes = rmp_serde::to_vec(&money).unwrap();
+    let decoded: Money = rmp_serde::from_slice(&bytes).unwrap();
+    assert_eq!(decoded, money);
+
+    let nan = rmp_serde::to_vec(&f64::NAN).unwrap();
+    assert!(rmp_serde::from_slice::<Money>(&nan).is_err());
+}
+
+#[test]
+fn test_payment_request_amount_validation() {
+    let valid: PaymentRequest = serde_json::from_str(
+        r#"{"correlationId": "4a7901b8-7d26-4d9d-aa19-4dc1c7cf60b3", "amount": 19.90}"#,
+    ).unwrap();
+    assert!(valid.validate().is_ok());
+
+    let zero: PaymentRequest = serde_json::from_str(
+        r#"{"correlationId": "4a7901b8-7d26-4d9d-aa19-4dc1c7cf60b3", "amount": 0.0}"#,
+    ).unwrap();
+    assert!(zero.validate().is_err());
+
+    let too_precise = serde_json::from_str::<PaymentRequest>(
+        r#"{"correlationId": "4a7901b8-7d26-4d9d-aa19-4dc1c7cf60b3", "amount": 19.901}"#,
+    );
+    assert!(too_precise.is_err());
+
+    let too_large: PaymentRequest = serde_json::from_str(
+        r#"{"correlationId": "4a7901b8-7d26-4d9d-aa19-4dc1c7cf60b3", "amount": 1000000000.01}"#,
+    ).unwrap();
+    assert!(too_large.validate().is_err());
+}
+
+#[tokio::test]
+async fn test_summary_totals_do_not_drift() {
+    let ledger = PaymentLedger::new();
+    for i in 0..10_000 {
+        ledger.record(&format!("payment-{}", i), "default", Money::from_cents(10), i).await;
+    }
+
+    let summary = ledger.summary(None, None).await;
+    assert_eq!(summary.default.total_amount, Money::from_cents(100_000));
+    let body = serde_json::to_value(&summary).unwrap();
+    assert_eq!(body["default"]["totalAmount"], 1000.0);
+}
@@ -0,0 +1,134 @@
+mod common;
+
+use axum::{Router, routing::post};
+use httpmock::MockServer;
+use httpmock::Method::POST;
+use reqwest::Client;
+use rinha::modules::cache::CacheManager;
+use rinha::modules::error::PaymentError;
+use rinha::modules::health::HealthManager;
+use rinha::modules::models::Money;
+use rinha::modules::payment::create_payment;
+use rinha::modules::processors::PaymentProcessor;
+use rinha::modules::processors::selector::ProcessorSelector;
+use rinha::modules::ApplicationServices;
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::Arc;
+
+const CORRELATION_ID: &str = "4a7901b8-7d26-4d9d-aa19-4dc1c7cf60b3";
+
+async fn start_server(processor: PaymentProcessor) -> SocketAddr {
+    let services = ApplicationServices::with_services(processor, HealthManager::new(), CacheManager::new());
+    let app = Router::new()
+        .route("/payments", post(create_payment))
+        .with_state(Arc::new(services));
+    common::start_server(app).await
+}
+
+#[test]
+fn test_error_classification() {
+    let timeout = PaymentError::Timeout { processor: "default".to_string() };
+    assert!(timeout.is_retryable());
+    assert_eq!(timeout.status_code().as_u16(), 504);
+    assert_eq!(timeout.processor(), Some("default"));
+
+    let server_error = PaymentError::ProcessorServerError { processor: "default".to_string(), status: 500 };
+    assert!(server_error.is_retryable());
+    assert_eq!(server_error.status_code().as_u16(), 502);
+
+    let client_error = PaymentError::ProcessorClientError { processor: "default".to_string(), status: 400 };
+    assert!(!client_error.is_retryable());
+    let rate_limited = PaymentError::ProcessorClientError { processor: "default".to_string(), status: 429 };
+    assert!(rate_limited.is_retryable());
+
+    assert!(!PaymentError::Validation("bad".to_string()).is_retryable());
+    assert_eq!(PaymentError::Validation("bad".to_string()).status_code().as_u16(), 400);
+    assert_eq!(PaymentError::Duplicate { correlation_id: CORRELATION_ID.to_string() }.status_code().as_u16(), 409);
+    assert_eq!(PaymentError::AllProcessorsDown.status_code().as_u16(), 503);
+    assert!(!PaymentError::Serialization("bad".to_string()).is_retryable());
+
+    let body = serde_json::to_value(PaymentError::AllProcessorsDown.to_body()).unwrap();
+    assert_eq!(body["error"], "all_processors_down");
+    assert_eq!(body["retryable"], true);
+}
+
+#[tokio::test]
+async fn test_client_error_does_not_fail_over() {
+    let server = MockServer::start_async().await;
+    let default_mock = server.mock_async(|when, then| {
+        when.method(POST).path("/default/payments");
+        then.status(400);
+    }).await;
+    let fallback_mock = server.mock_async(|when, then| {
+        when.method(POST).path("/fallback/payments");
+        then.status(200).json_body_obj(&serde_json::json!({"message": "ok"}));
+    }).await;
+    let selector = common::selector_for(&server);
+    selector.update_processor_rate("default", 0.01).await;
+    selector.update_processor_rate("fallback", 0.02).await;
+
+    let err = selector.process_payment(CORRELATION_ID, Money::from_cents(1990)).await.unwrap_err();
+    assert_eq!(err, PaymentError::ProcessorClientError { processor: "default".to_string(), status: 400 });
+    assert!(selector.is_processor_healthy("default").await);
+    assert_eq!(default_mock.hits_async().await, 1);
+    assert_eq!(fallback_mock.hits_async().await, 0);
+}
+
+#[tokio::test]
+async fn test_server_errors_exhaust_processors() {
+    let server = MockServer::start_async().await;
+    server.mock_async(|when, then| {
+        when.method(POST).path("/default/payments");
+        then.status(500);
+    }).await;
+    server.mock_async(|when, then| {
+        when.method(POST).path("/fallback/payments");
+        then.status(503);
+    }).await;
+    let selector = common::selector_for(&server);
+
+    let err = selector.process_payment(CORRELATION_ID, Money::from_cents(1990)).await.unwrap_err();
+    assert_eq!(err, PaymentError::AllProcessorsDown);
+    assert!(selector.ledger().is_empty().await);
+}
+
+#[tokio::test]
+async fn test_connection_refused_is_retryable() {
+    let mut config = HashMap::new();
+    // Nothing listens on port 1
+    config.insert("default".to_string(), "http://127.0.0.1:1".to_string());
+    let selector = ProcessorSelector::with_config_and_client(config, Client::new());
+
+    let err = selector.process_payment(CORRELATION_ID, Money::from_cents(1990)).await.unwrap_err();
+    assert_eq!(err, PaymentError::AllProcessorsDown);
+    let info = selector.get_processors().await;
+    assert_eq!(info["default"].failure_count, 1);
+}
+
+#[tokio::test]
+async fn test_handler_returns_json_error_bodies() {
+    let server = MockServer::start_async().await;
+    server.mock_async(|when, then| {
+        when.method(POST);
+        then.status(500);
+    }).await;
+    let addr = start_server(PaymentProcessor::with_selector(common::selector_for(&server))).await;
+    let client = Client::new();
+
+    let resp = client.post(format!("http://{}/payments", addr))
+        .json(&serde_json::json!({"correlationId": CORRELATION_ID, "amount": 19.9}))
+        .send().await.unwrap();
+    assert_eq!(resp.status().as_u16(), 503);
+    let body: serde_json::Value = resp.json().await.unwrap();
+    assert_eq!(body["error"], "all_processors_down");
+    assert_eq!(body["retryable"], true);
+
+    let resp = client.post(format!("http://{}/payments", addr))
+        .json(&serde_json::json!({"correlationId": "invalid-uuid", "amount": 19.9}))
+        .send().await.unwrap();
+    assert_eq!(resp.status().as_u16(), 400);
+    let body: serde_json::Value = resp.json().await.unwrap();
+    assert_eq!(body["error"], "validation");
+    assert_eq!(body["retryable"], false);
+}
@@ -0,0 +1,304 @@
+mod common;
+
+use axum::{Router, routing::{get, post}, http::StatusCode, Json};
+use httpmock::MockServer;
+use httpmock::Method::POST;
+use reqwest::Client;
+use rinha::modules::cache::CacheManager;
+use rinha::modules::config::{Config, QueueFullPolicy};
+use rinha::modules::health::HealthManager;
+use rinha::modules::payment::create_payment;
+use rinha::modules::processors::retry::RetryPolicy;
+use rinha::modules::processors::PaymentProcessor;
+use rinha::modules::processors::selector::ProcessorSelector;
+use rinha::modules::queue::{get_queue_metrics, EnqueueError, PaymentQueue, QueuedPayment};
+use rinha::modules::{ApplicationServices, SharedServices};
+use rinha::modules::models::Money;
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+
+fn processor_for(server: &MockServer) -> PaymentProcessor {
+    let mut config = HashMap::new();
+    config.insert("default".to_string(), server.url(""));
+    config.insert("fallback".to_string(), server.url(""));
+    PaymentProcessor::with_selector(ProcessorSelector::with_config_and_client(config, Client::new()))
+}
+
+fn queued(correlation_id: &str) -> QueuedPayment {
+    QueuedPayment {
+        correlation_id: correlation_id.to_string(),
+        amount: Money::from_cents(1990),
+    }
+}
+
+/// Processor that answers `status` to the first `failures` payments, then 200.
+async fn processor_failing(failures: usize, status: StatusCode) -> PaymentProcessor {
+    let hits = Arc::new(AtomicUsize::new(0));
+    let app = Router::new().route("/payments", post(move || {
+        let hits = Arc::clone(&hits);
+        async move {
+            if hits.fetch_add(1, Ordering::SeqCst) < failures {
+                (status, Json(serde_json::json!({})))
+            } else {
+                (StatusCode::OK, Json(serde_json::json!({"message": "ok"})))
+            }
+        }
+    }));
+    let url = format!("http://{}", common::start_server(app).await);
+
+    let mut config = HashMap::new();
+    config.insert("default".to_string(), url.clone());
+    config.insert("fallback".to_string(), url);
+    let mut selector = ProcessorSelector::with_config_and_client(config, Client::new());
+    selector.set_retry_policy(RetryPolicy::no_retries());
+    PaymentProcessor::with_selector(selector)
+}
+
+async fn wait_until(queue: &PaymentQueue, done: impl Fn(&PaymentQueue) -> bool) {
+    for _ in 0..100 {
+        if done(queue) {
+            return;
+        }
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+}
+
+async fn start_server(services: SharedServices) -> SocketAddr {
+    let app = Router::new()
+        .route("/payments", post(create_payment))
+        .route("/metrics/queue", get(get_queue_metrics))
+        .with_state(services);
+    common::start_server(app).await
+}
+
+#[tokio::test]
+async fn test_workers_drain_queue_through_processor() {
+    let server = MockServer::start_async().await;
+    let _mock = server.mock_async(|when, then| {
+        when.method(POST).path("/payments");
+        then.status(200).json_body_obj(&serde_json::json!({"message": "ok"}));
+    }).await;
+    let processor = Arc::new(processor_for(&server));
+    let queue = PaymentQueue::start(Arc::clone(&processor), 16, 2, QueueFullPolicy::Reject);
+
+    for i in 0..5 {
+        assert!(queue.try_enqueue(queued(&format!("queued-{}", i))).is_ok());
+    }
+
+    for _ in 0..50 {
+        if queue.metrics().processed == 5 {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+
+    let metrics = queue.metrics();
+    assert_eq!(metrics.enqueued, 5);
+    assert_eq!(metrics.processed, 5);
+    assert_eq!(metrics.depth, 0);
+    assert_eq!(metrics.workers, 2);
+    assert_eq!(processor.get_ledger().len().await, 5);
+}
+
+#[tokio::test]
+async fn test_full_queue_hands_payment_back() {
+    let server = MockServer::start_async().await;
+    let processor = Arc::new(processor_for(&server));
+    // Workers cannot run before the first await on a current-thread runtime,
+    // so the second enqueue deterministically finds the queue full.
+    let queue = PaymentQueue::start(processor, 1, 1, QueueFullPolicy::Reject);
+
+    assert!(queue.try_enqueue(queued("first")).is_ok());
+    assert_eq!(queue.depth(), 1);
+    assert_eq!(queue.try_enqueue(queued("second")), Err(EnqueueError::Full(queued("second"))));
+
+    let metrics = queue.metrics();
+    assert_eq!(metrics.capacity, 1);
+    assert_eq!(metrics.enqueued, 1);
+    assert_eq!(metrics.rejected, 1);
+}
+
+#[tokio::test]
+async fn test_async_intake_returns_accepted() {
+    let server = MockServer::start_async().await;
+    let _mock = server.mock_async(|when, then| {
+        when.method(POST).path("/payments");
+        then.status(200).json_body_obj(&serde_json::json!({"message": "ok"}));
+    }).await;
+    let mut services = ApplicationServices::with_services(
+        processor_for(&server),
+        HealthManager::new(),
+        CacheManager::new(),
+    );
+    let mut config = Config::new();
+    config.queue_capacity = 8;
+    config.queue_workers = 1;
+    services.start_payment_queue(&config);
+    let addr = start_server(Arc::new(services)).await;
+    let client = Client::new();
+
+    let resp = client.post(format!("http://{}/payments", addr))
+        .json(&serde_json::json!({
+            "correlationId": "4a7901b8-7d26-4d9d-aa19-4dc1c7cf60b3",
+            "amount": 19.9
+        }))
+        .send().await.unwrap();
+    assert_eq!(resp.status().as_u16(), 202);
+
+    // Validation still happens before anything is enqueued
+    let resp = client.post(format!("http://{}/payments", addr))
+        .json(&serde_json::json!({"correlationId": "invalid-uuid", "amount": 19.9}))
+        .send().await.unwrap();
+    assert_eq!(resp.status().as_u16(), 400);
+
+    let resp = client.get(format!("http://{}/metrics/queue", addr)).send().await.unwrap();
+    assert_eq!(resp.status().as_u16(), 200);
+    let body: serde_json::Value = resp.json().await.unwrap();
+    assert_eq!(body["enqueued"], 1);
+    assert_eq!(body["capacity"], 8);
+}
+
+#[tokio::test]
+async fn test_queue_metrics_absent_in_sync_mode() {
+    let addr = start_server(Arc::new(ApplicationServices::new())).await;
+    let resp = Client::new()
+        .get(format!("http://{}/metrics/queue", addr))
+        .send().await.unwrap();
+    assert_eq!(resp.status().as_u16(), 404);
+}
+
+#[tokio::test]
+async fn test_retryable_failures_are_requeued_until_processed() {
+    // Both processors fail the first attempt, so the payment is requeued once
+    let processor = Arc::new(processor_failing(2, StatusCode::INTERNAL_SERVER_ERROR).await);
+    let queue = PaymentQueue::start_with_requeue_backoff(
+        Arc::clone(&processor),
+        4,
+        1,
+        QueueFullPolicy::Reject,
+        Duration::from_millis(5),
+        Duration::from_millis(10),
+        3,
+    );
+
+    queue.try_enqueue(queued("4a7901b8-7d26-4d9d-aa19-4dc1c7cf60b3")).unwrap();
+    wait_until(&queue, |queue| queue.metrics().processed == 1).await;
+
+    let metrics = queue.metrics();
+    assert_eq!(metrics.processed, 1);
+    assert_eq!(metrics.requeued, 1);
+    assert_eq!(metrics.failed, 0);
+    assert_eq!(metrics.retrying, 0);
+    assert_eq!(processor.get_ledger().len().await, 1);
+}
+
+#[tokio::test]
+async fn test_non_retryable_failures_are_dropped() {
+    let processor = Arc::new(processor_failing(usize::MAX, StatusCode::BAD_REQUEST).await);
+    let queue = PaymentQueue::start_with_requeue_backoff(
+        processor,
+        4,
+        1,
+        QueueFullPolicy::Reject,
+        Duration::from_millis(5),
+        Duration::from_millis(10),
+        3,
+    );
+
+    queue.try_enqueue(queued("4a7901b8-7d26-4d9d-aa19-4dc1c7cf60b3")).unwrap();
+    wait_until(&queue, |queue| queue.metrics().failed == 1).await;
+
+    let metrics = queue.metrics();
+    assert_eq!(metrics.failed, 1);
+    assert_eq!(metrics.requeued, 0);
+    assert_eq!(metrics.processed, 0);
+}
+
+#[tokio::test]
+async fn test_payments_failing_past_the_requeue_limit_count_as_failed() {
+    let processor = Arc::new(processor_failing(usize::MAX, StatusCode::INTERNAL_SERVER_ERROR).await);
+    let queue = PaymentQueue::start_with_requeue_backoff(
+        Arc::clone(&processor),
+        4,
+        1,
+        QueueFullPolicy::Reject,
+        Duration::from_millis(5),
+        Duration::from_millis(10),
+        2,
+    );
+
+    queue.try_enqueue(queued("4a7901b8-7d26-4d9d-aa19-4dc1c7cf60b3")).unwrap();
+    wait_until(&queue, |queue| queue.metrics().failed == 1).await;
+
+    let metrics = queue.metrics();
+    assert_eq!(metrics.requeued, 2);
+    assert_eq!(metrics.failed, 1);
+    assert_eq!(metrics.processed, 0);
+    assert_eq!(metrics.retrying, 0);
+}
+
+#[tokio::test]
+async fn test_payments_awaiting_requeue_count_against_capacity() {
+    let processor = Arc::new(processor_failing(usize::MAX, StatusCode::INTERNAL_SERVER_ERROR).await);
+    let queue = PaymentQueue::start_with_requeue_backoff(
+        processor,
+        1,
+        1,
+        QueueFullPolicy::Reject,
+        Duration::from_secs(60),
+        Duration::from_secs(60),
+        3,
+    );
+
+    queue.try_enqueue(queued("4a7901b8-7d26-4d9d-aa19-4dc1c7cf60b3")).unwrap();
+    wait_until(&queue, |queue| queue.metrics().retrying == 1).await;
+
+    assert_eq!(queue.depth(), 0);
+    assert!(matches!(
+        queue.try_enqueue(queued("5b8a02c9-8e37-4e0e-bb2a-5ed2d8d071c4")),
+        Err(EnqueueError::Full(_))
+    ));
+}
+
+#[tokio::test]
+async fn test_full_queue_answers_503_with_retry_after() {
+    let mut services = ApplicationServices::with_services(
+        processor_failing(usize::MAX, StatusCode::INTERNAL_SERVER_ERROR).await,
+        HealthManager::new(),
+        CacheManager::new(),
+    );
+    let mut config = Config::new();
+    config.queue_capacity = 1;
+    config.queue_workers = 1;
+    config.queue_requeue_base_backoff = Duration::from_secs(60);
+    config.queue_requeue_max_backoff = Duration::from_secs(60);
+    services.start_payment_queue(&config);
+    let services = Arc::new(services);
+    let addr = start_server(Arc::clone(&services)).await;
+    let client = Client::new();
+
+    let resp = client.post(format!("http://{}/payments", addr))
+        .json(&serde_json::json!({
+            "correlationId": "4a7901b8-7d26-4d9d-aa19-4dc1c7cf60b3",
+            "amount": 19.9
+        }))
+        .send().await.unwrap();
+    assert_eq!(resp.status().as_u16(), 202);
+    let queue = services.payment_queue.as_ref().unwrap();
+    wait_until(queue, |queue| queue.metrics().retrying == 1).await;
+
+    let resp = client.post(format!("http://{}/payments", addr))
+        .json(&serde_json::json!({
+            "correlationId": "5b8a02c9-8e37-4e0e-bb2a-5ed2d8d071c4",
+            "amount": 19.9
+        }))
+        .send().await.unwrap();
+    assert_eq!(resp.status().as_u16(), 503);
+    assert_eq!(resp.headers()["retry-after"], "1");
+    let body: serde_json::Value = resp.json().await.unwrap();
+    assert_eq!(body["error"], "queue_full");
+    assert_eq!(body["retryable"], true);
+}
@@ -19,7 +19,7 @@ async fn payments_endpoint_exists_test() {
     let models_content = std::fs::read_to_string("src/modules/models/mod.rs").expect("Should read models/mod.rs");
     assert!(models_content.contains("PaymentRequest"), "models/mod.rs should have PaymentRequest struct");
     assert!(models_content.contains("validate_uuid"), "models/mod.rs should have UUID validation");
-    assert!(models_content.contains("range(min = 0.01)"), "models/mod.rs should have amount validation");
+    assert!(models_content.contains("validate_amount"), "models/mod.rs should have amount validation");
 }
 
 #[tokio::test]
@@ -34,7 +34,9 @@ async fn payments_validation_correlation_id_test() {
     // Verificar se o handler usa validação
     let payment_content = std::fs::read_to_string("src/modules/payment/mod.rs").expect("Should read payment/mod.rs");
     assert!(payment_content.contains("payment.validate()"), "Handler should validate payment request");
-    assert!(payment_content.contains("StatusCode::BAD_REQUEST"), "Handler should return 400 for invalid requests");
+    assert!(payment_content.contains("PaymentError::from(errors)"), "Handler should turn validation errors into PaymentError");
+    let error_content = std::fs::read_to_string("src/modules/error/mod.rs").expect("Should read error/mod.rs");
+    assert!(error_content.contains("PaymentError::Validation(_) => StatusCode::BAD_REQUEST"), "Validation errors should map to 400");
 }
 
 #[tokio::test]
@@ -43,8 +45,8 @@ async fn payments_validation_amount_test() {
     
     // Verificar se a validação de amount está implementada
     let models_content = std::fs::read_to_string("src/modules/models/mod.rs").expect("Should read models/mod.rs");
-    assert!(models_content.contains("range(min = 0.01)"), "Should have amount minimum validation");
-    assert!(models_content.contains("amount: f64"), "Should have amount field as f64");
+    assert!(models_content.contains("validate_amount"), "Should have amount minimum validation");
+    assert!(models_content.contains("amount: Money"), "Should have amount field as exact Money");
     
     // Verificar se o handler retorna erro para amount inválido
     let payment_content = std::fs::read_to_string("src/modules/payment/mod.rs").expect("Should read payment/mod.rs");
@@ -57,11 +59,11 @@ async fn payments_validation_amount_zero_test() {
     
     // Verificar se amount zero é rejeitado
     let models_content = std::fs::read_to_string("src/modules/models/mod.rs").expect("Should read models/mod.rs");
-    assert!(models_content.contains("range(min = 0.01)"), "Should reject amounts less than 0.01");
+    assert!(models_content.contains("validate_amount"), "Should reject amounts less than 0.01");
     
     // Verificar se o handler trata erros de validação
-    let payment_content = std::fs::read_to_string("src/modules/payment/mod.rs").expect("Should read payment/mod.rs");
-    assert!(payment_content.contains("StatusCode::BAD_REQUEST"), "Should return 400 for validation errors");
+    let error_content = std::fs::read_to_string("src/modules/error/mod.rs").expect("Should read error/mod.rs");
+    assert!(error_content.contains("PaymentError::Validation(_) => StatusCode::BAD_REQUEST"), "Should return 400 for validation errors");
 }
 
 #[tokio::test]
@@ -70,7 +72,7 @@ async fn payments_validation_amount_minimum_test() {
     
     // Verificar se amount mínimo (0.01) é aceito
     let models_content = std::fs::read_to_string("src/modules/models/mod.rs").expect("Should read models/mod.rs");
-    assert!(models_content.contains("range(min = 0.01)"), "Should accept amounts >= 0.01");
+    assert!(models_content.contains("validate_amount"), "Should accept amounts >= 0.01");
     
     // Verificar se o handler retorna sucesso para valores válidos
     let payment_content = std::fs::read_to_string("src/modules/payment/mod.rs").expect("Should read payment/mod.rs");
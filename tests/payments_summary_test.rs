@@ -0,0 +1,131 @@
+mod common;
+
+use axum::{Router, routing::get};
+use httpmock::MockServer;
+use httpmock::Method::POST;
+use reqwest::Client;
+use rinha::modules::cache::CacheManager;
+use rinha::modules::summary::get_payments_summary;
+use rinha::modules::summary::ledger::{PaymentLedger, PaymentsSummary};
+use rinha::modules::{ApplicationServices, SharedServices};
+use rinha::modules::models::Money;
+use std::net::SocketAddr;
+use std::sync::Arc;
+
+async fn start_summary_server(services: SharedServices) -> SocketAddr {
+    let app = Router::new()
+        .route("/payments-summary", get(get_payments_summary))
+        .with_state(services);
+    common::start_server(app).await
+}
+
+#[tokio::test]
+async fn test_ledger_summary_groups_by_processor() {
+    let ledger = PaymentLedger::new();
+    ledger.record("a", "default", Money::from_cents(1000), 1_000).await;
+    ledger.record("b", "default", Money::from_cents(550), 2_000).await;
+    ledger.record("c", "fallback", Money::from_cents(125), 3_000).await;
+
+    let summary = ledger.summary(None, None).await;
+    assert_eq!(summary.default.total_requests, 2);
+    assert_eq!(summary.default.total_amount, Money::from_cents(1550));
+    assert_eq!(summary.fallback.total_requests, 1);
+    assert_eq!(summary.fallback.total_amount, Money::from_cents(125));
+}
+
+#[tokio::test]
+async fn test_ledger_summary_respects_inclusive_window() {
+    let ledger = PaymentLedger::new();
+    ledger.record("a", "default", Money::from_cents(100), 1_000).await;
+    ledger.record("b", "default", Money::from_cents(100), 2_000).await;
+    ledger.record("c", "fallback", Money::from_cents(100), 3_000).await;
+
+    let summary = ledger.summary(Some(2_000), Some(3_000)).await;
+    assert_eq!(summary.default.total_requests, 1);
+    assert_eq!(summary.fallback.total_requests, 1);
+
+    let summary = ledger.summary(None, Some(1_500)).await;
+    assert_eq!(summary.default.total_requests, 1);
+    assert_eq!(summary.fallback.total_requests, 0);
+}
+
+#[tokio::test]
+async fn test_replicas_sharing_a_cache_share_the_summary() {
+    let cache = Arc::new(CacheManager::new());
+    let first = PaymentLedger::with_cache(Arc::clone(&cache));
+    let second = PaymentLedger::with_cache(Arc::clone(&cache));
+    assert!(first.record("a", "default", Money::from_cents(1000), 1_000).await);
+    assert!(second.record("b", "fallback", Money::from_cents(250), 1_200).await);
+    // Already counted by the other replica
+    assert!(!second.record("a", "default", Money::from_cents(1000), 1_000).await);
+    assert!(second.contains("a").await);
+
+    for ledger in [&first, &second] {
+        let summary = ledger.summary(None, None).await;
+        assert_eq!(summary.default.total_amount, Money::from_cents(1000));
+        assert_eq!(summary.fallback.total_amount, Money::from_cents(250));
+        assert_eq!(ledger.len().await, 2);
+    }
+}
+
+#[tokio::test]
+async fn test_selector_records_accepting_processor() {
+    let server = MockServer::start_async().await;
+    let _default_mock = server.mock_async(|when, then| {
+        when.method(POST).path("/default/payments");
+        then.status(500);
+    }).await;
+    let _fallback_mock = server.mock_async(|when, then| {
+        when.method(POST).path("/fallback/payments");
+        then.status(200).json_body_obj(&serde_json::json!({"message": "ok"}));
+    }).await;
+
+    let selector = common::selector_for(&server);
+    selector.update_processor_rate("default", 0.01).await;
+    selector.update_processor_rate("fallback", 0.02).await;
+
+    let result = selector.process_payment("4a7901b8-7d26-4d9d-aa19-4dc1c7cf60b3", Money::from_cents(1990)).await;
+    assert!(result.is_ok());
+
+    let summary = selector.ledger().summary(None, None).await;
+    assert_eq!(summary.default.total_requests, 0);
+    assert_eq!(summary.fallback.total_requests, 1);
+    assert_eq!(summary.fallback.total_amount, Money::from_cents(1990));
+}
+
+#[tokio::test]
+async fn test_payments_summary_endpoint() {
+    let services = Arc::new(ApplicationServices::new());
+    let ledger = services.payment_processor.get_ledger();
+    // 2020-07-10T12:34:56.000Z and one minute later
+    ledger.record("a", "default", Money::from_cents(1990), 1_594_384_496_000).await;
+    ledger.record("b", "fallback", Money::from_cents(1990), 1_594_384_556_000).await;
+    let addr = start_summary_server(services).await;
+    let client = Client::new();
+
+    let resp = client.get(format!("http://{}/payments-summary", addr)).send().await.unwrap();
+    assert_eq!(resp.status().as_u16(), 200);
+    let body: serde_json::Value = resp.json().await.unwrap();
+    assert_eq!(body["default"]["totalRequests"], 1);
+    assert_eq!(body["default"]["totalAmount"], 19.9);
+    assert_eq!(body["fallback"]["totalRequests"], 1);
+
+    let resp = client
+        .get(format!(
+            "http://{}/payments-summary?from=2020-07-10T12:34:56.000Z&to=2020-07-10T12:35:00.000Z",
+            addr
+        ))
+        .send().await.unwrap();
+    let summary: PaymentsSummary = resp.json().await.unwrap();
+    assert_eq!(summary.default.total_requests, 1);
+    assert_eq!(summary.fallback.total_requests, 0);
+}
+
+#[tokio::test]
+async fn test_payments_summary_rejects_invalid_timestamp() {
+    let addr = start_summary_server(Arc::new(ApplicationServices::new())).await;
+    let resp = Client::new()
+        .get(format!("http://{}/payments-summary?from=yesterday", addr))
+        .send().await.unwrap();
+    assert_eq!(resp.status().as_u16(), 400);
+}
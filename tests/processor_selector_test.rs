@@ -1,5 +1,7 @@
 use rinha::modules::processors::selector::ProcessorSelector;
 use rinha::modules::processors::selector::ProcessorInfo;
+use rinha::modules::processors::circuit_breaker::CircuitState;
+use rinha::modules::models::Money;
 use std::collections::HashMap;
 use std::sync::Arc;
 use httpmock::MockServer;
@@ -20,10 +22,10 @@ async fn test_processor_rate_tracking() {
     let selector = ProcessorSelector::new();
     for i in 0..10 {
         let correlation_id = format!("test-{}", i);
-        let _ = selector.process_payment(&correlation_id, 100.0).await;
+        let _ = selector.process_payment(&correlation_id, Money::from_cents(10_000)).await;
     }
     let rates = selector.get_processor_rates().await;
-    assert!(rates.len() > 0);
+    assert!(!rates.is_empty());
     for (_, rate) in rates {
         assert!(rate >= 0.0);
     }
@@ -61,7 +63,7 @@ async fn test_automatic_fallback_on_failure() {
     selector.mark_processor_healthy("default").await;
     selector.mark_processor_healthy("fallback").await;
     let correlation_id = "test-fallback";
-    let result = selector.process_payment(correlation_id, 100.0).await;
+    let result = selector.process_payment(correlation_id, Money::from_cents(10_000)).await;
     assert!(result.is_ok());
 }
 
@@ -101,7 +103,7 @@ async fn test_concurrent_processor_access() {
         let selector_clone = Arc::clone(&selector);
         let handle = tokio::spawn(async move {
             let correlation_id = format!("concurrent-{}", i);
-            selector_clone.process_payment(&correlation_id, 100.0).await
+            selector_clone.process_payment(&correlation_id, Money::from_cents(10_000)).await
         });
         handles.push(handle);
     }
@@ -119,6 +121,7 @@ async fn test_processor_info_structure() {
         rate: 0.05,
         is_healthy: true,
         failure_count: 0,
+        circuit_state: CircuitState::Closed,
     };
     assert_eq!(info.name, "test");
     assert_eq!(info.url, "http://test:8080");
@@ -0,0 +1,199 @@
+mod common;
+
+use axum::{Router, routing::post, http::StatusCode, Json};
+use httpmock::MockServer;
+use httpmock::Method::POST;
+use reqwest::Client;
+use rinha::modules::clock::{system_clock, ManualClock};
+use rinha::modules::error::PaymentError;
+use rinha::modules::models::Money;
+use rinha::modules::processors::retry::{RetryBudget, RetryPolicy};
+use rinha::modules::processors::selector::ProcessorSelector;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+
+const CORRELATION_ID: &str = "4a7901b8-7d26-4d9d-aa19-4dc1c7cf60b3";
+
+fn fast_policy(max_attempts: u32) -> RetryPolicy {
+    RetryPolicy {
+        max_attempts,
+        base_backoff: Duration::from_millis(1),
+        max_backoff: Duration::from_millis(5),
+        jitter: false,
+        // Large enough that the budget never gets in the way
+        budget_percent: 1_000.0,
+        ..RetryPolicy::new()
+    }
+}
+
+fn selector_with_policy(server: &MockServer, policy: RetryPolicy) -> ProcessorSelector {
+    let mut selector = common::selector_for(server);
+    selector.set_retry_policy(policy);
+    selector
+}
+
+/// Processor that answers 500 to the first `failures` payments, then 200.
+async fn start_flaky_processor(failures: usize) -> (String, Arc<AtomicUsize>) {
+    let hits = Arc::new(AtomicUsize::new(0));
+    let counter = Arc::clone(&hits);
+    let app = Router::new().route("/payments", post(move || {
+        let counter = Arc::clone(&counter);
+        async move {
+            if counter.fetch_add(1, Ordering::SeqCst) < failures {
+                (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({})))
+            } else {
+                (StatusCode::OK, Json(serde_json::json!({"message": "ok"})))
+            }
+        }
+    }));
+
+    let addr = common::start_server(app).await;
+    (format!("http://{}", addr), hits)
+}
+
+#[test]
+fn test_backoff_doubles_up_to_max() {
+    let policy = RetryPolicy {
+        base_backoff: Duration::from_millis(10),
+        max_backoff: Duration::from_millis(50),
+        jitter: false,
+        ..RetryPolicy::new()
+    };
+    assert_eq!(policy.backoff(1), Duration::from_millis(10));
+    assert_eq!(policy.backoff(2), Duration::from_millis(20));
+    assert_eq!(policy.backoff(3), Duration::from_millis(40));
+    assert_eq!(policy.backoff(4), Duration::from_millis(50));
+    assert_eq!(policy.backoff(40), Duration::from_millis(50));
+
+    let jittered = RetryPolicy { jitter: true, ..policy };
+    for retry in 1..10 {
+        assert!(jittered.backoff(retry) <= Duration::from_millis(50));
+    }
+}
+
+#[test]
+fn test_retryable_kinds() {
+    let policy = RetryPolicy::new();
+    assert!(!policy.should_retry(&PaymentError::Timeout { processor: "default".to_string() }));
+    assert!(policy.should_retry(&PaymentError::ConnectionRefused { processor: "default".to_string(), reason: "refused".to_string() }));
+    assert!(policy.should_retry(&PaymentError::ProcessorServerError { processor: "default".to_string(), status: 502 }));
+    assert!(!policy.should_retry(&PaymentError::ProcessorClientError { processor: "default".to_string(), status: 400 }));
+
+    let only_timeouts = RetryPolicy { retryable_kinds: vec!["timeout".to_string()], ..RetryPolicy::new() };
+    assert!(only_timeouts.should_retry(&PaymentError::Timeout { processor: "default".to_string() }));
+    assert!(!only_timeouts.should_retry(&PaymentError::ProcessorServerError { processor: "default".to_string(), status: 502 }));
+}
+
+#[test]
+fn test_retry_budget_is_a_share_of_requests() {
+    let budget = RetryBudget::new(20.0, Duration::from_secs(10), system_clock());
+    for _ in 0..10 {
+        budget.record_request();
+    }
+    assert!(budget.try_acquire());
+    assert!(budget.try_acquire());
+    assert!(!budget.try_acquire());
+    assert_eq!(budget.retries(), 2);
+}
+
+#[test]
+fn test_retry_budget_slides_with_the_window() {
+    let clock = Arc::new(ManualClock::new(1_752_582_896_000));
+    let budget = RetryBudget::new(50.0, Duration::from_secs(10), clock.clone());
+    for _ in 0..4 {
+        budget.record_request();
+    }
+    assert!(budget.try_acquire());
+    assert!(budget.try_acquire());
+    assert!(!budget.try_acquire());
+
+    // Old traffic leaves the window along with the retries it paid for
+    clock.advance(Duration::from_secs(11));
+    assert_eq!((budget.requests(), budget.retries()), (0, 0));
+    assert!(!budget.try_acquire());
+    budget.record_request();
+    budget.record_request();
+    assert!(budget.try_acquire());
+    assert!(!budget.try_acquire());
+}
+
+#[tokio::test]
+async fn test_transient_failure_is_retried_on_same_processor() {
+    let (url, hits) = start_flaky_processor(1).await;
+    let fallback = MockServer::start_async().await;
+    let fallback_mock = fallback.mock_async(|when, then| {
+        when.method(POST);
+        then.status(200).json_body_obj(&serde_json::json!({"message": "ok"}));
+    }).await;
+
+    let mut config = HashMap::new();
+    config.insert("default".to_string(), url);
+    config.insert("fallback".to_string(), fallback.url(""));
+    let mut selector = ProcessorSelector::with_config_and_client(config, Client::new());
+    selector.set_retry_policy(fast_policy(3));
+    selector.update_processor_rate("default", 0.01).await;
+    selector.update_processor_rate("fallback", 0.02).await;
+
+    let response = selector.process_payment(CORRELATION_ID, Money::from_cents(1990)).await.unwrap();
+    assert_eq!(response.processor, "default");
+    assert_eq!(hits.load(Ordering::SeqCst), 2);
+    assert_eq!(fallback_mock.hits_async().await, 0);
+    assert!(selector.is_processor_healthy("default").await);
+    assert_eq!(selector.retry_budget().retries(), 1);
+}
+
+#[tokio::test]
+async fn test_fails_over_after_max_attempts() {
+    let server = MockServer::start_async().await;
+    let default_mock = server.mock_async(|when, then| {
+        when.method(POST).path("/default/payments");
+        then.status(503);
+    }).await;
+    let fallback_mock = server.mock_async(|when, then| {
+        when.method(POST).path("/fallback/payments");
+        then.status(200).json_body_obj(&serde_json::json!({"message": "ok"}));
+    }).await;
+    let selector = selector_with_policy(&server, fast_policy(3));
+    selector.update_processor_rate("default", 0.01).await;
+    selector.update_processor_rate("fallback", 0.02).await;
+
+    let response = selector.process_payment(CORRELATION_ID, Money::from_cents(1990)).await.unwrap();
+    assert_eq!(response.processor, "fallback");
+    assert_eq!(default_mock.hits_async().await, 3);
+    assert_eq!(fallback_mock.hits_async().await, 1);
+}
+
+#[tokio::test]
+async fn test_non_retryable_and_exhausted_budget_skip_retries() {
+    let server = MockServer::start_async().await;
+    let default_mock = server.mock_async(|when, then| {
+        when.method(POST).path("/default/payments");
+        then.status(400);
+    }).await;
+    let selector = selector_with_policy(&server, fast_policy(3));
+    selector.update_processor_rate("default", 0.01).await;
+    selector.update_processor_rate("fallback", 0.02).await;
+    assert!(selector.process_payment(CORRELATION_ID, Money::from_cents(1990)).await.is_err());
+    assert_eq!(default_mock.hits_async().await, 1);
+
+    let server = MockServer::start_async().await;
+    let default_mock = server.mock_async(|when, then| {
+        when.method(POST).path("/default/payments");
+        then.status(500);
+    }).await;
+    let selector = selector_with_policy(&server, RetryPolicy { budget_percent: 0.0, ..fast_policy(3) });
+    selector.update_processor_rate("default", 0.01).await;
+    selector.update_processor_rate("fallback", 0.02).await;
+    assert!(selector.process_payment(CORRELATION_ID, Money::from_cents(1990)).await.is_err());
+    assert_eq!(default_mock.hits_async().await, 1);
+    assert_eq!(selector.retry_budget().retries(), 0);
+}
+
+#[test]
+fn test_config_carries_retry_policy() {
+    let config = rinha::modules::config::Config::new();
+    assert!(config.retry_policy.max_attempts >= 1);
+    assert!(config.retry_policy.base_backoff <= config.retry_policy.max_backoff);
+}
@@ -5,7 +5,7 @@ mod tests {
     #[tokio::test]
     async fn test_server_compiles_with_axum() {
         let output = Command::new("cargo")
-            .args(&["check", "--quiet"])
+            .args(["check", "--quiet"])
             .output()
             .expect("Failed to execute cargo check");
 
@@ -16,7 +16,7 @@ mod tests {
     #[tokio::test]
     async fn test_server_builds_successfully() {
         let output = Command::new("cargo")
-            .args(&["build", "--quiet"])
+            .args(["build", "--quiet"])
             .output()
             .expect("Failed to execute cargo build");
 
@@ -6,7 +6,7 @@ mod tests {
     #[test]
     fn test_project_has_required_dependencies() {
         let output = Command::new("cargo")
-            .args(&["check", "--quiet"])
+            .args(["check", "--quiet"])
             .output()
             .expect("Failed to execute cargo check");
         let stderr = String::from_utf8_lossy(&output.stderr);
@@ -18,7 +18,7 @@ mod tests {
     #[test]
     fn test_project_compiles_without_errors() {
         let output = Command::new("cargo")
-            .args(&["build", "--quiet"])
+            .args(["build", "--quiet"])
             .output()
             .expect("Failed to execute cargo build");
         assert!(output.status.success(), 
@@ -30,7 +30,7 @@ mod tests {
         let config = Config::new();
         
     let server_addr = config.server_addr();
-    assert!(server_addr.to_string().len() > 0, "Server address should not be empty");
+    assert!(!server_addr.to_string().is_empty(), "Server address should not be empty");
         
         assert!(std::path::Path::new("src/main.rs").exists(), "main.rs should exist");
         assert!(std::path::Path::new("src/lib.rs").exists(), "lib.rs should exist");
@@ -0,0 +1,98 @@
+use httpmock::MockServer;
+use httpmock::Method::GET;
+use rinha::modules::cache::CacheManager;
+use rinha::modules::clock::ManualClock;
+use rinha::modules::health::service::HealthCheckService;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+const START_MILLIS: u64 = 1_752_582_896_000;
+
+fn replica(server: &MockServer, cache: &Arc<CacheManager>, clock: &Arc<ManualClock>, id: &str) -> HealthCheckService {
+    let mut urls = HashMap::new();
+    urls.insert("default".to_string(), server.url("/default"));
+    let mut service = HealthCheckService::with_processor_urls(urls);
+    service.set_clock(clock.clone());
+    service.share_through(Arc::clone(cache), id);
+    service
+}
+
+#[tokio::test]
+async fn test_only_lease_holder_polls_processor() {
+    let server = MockServer::start_async().await;
+    let health = server.mock_async(|when, then| {
+        when.method(GET).path("/default/payments/service-health");
+        then.status(200).json_body_obj(&serde_json::json!({"failing": false, "minResponseTime": 25}));
+    }).await;
+    let clock = Arc::new(ManualClock::new(START_MILLIS));
+    let mut cache = CacheManager::new();
+    cache.set_clock(clock.clone());
+    let cache = Arc::new(cache);
+
+    let first = replica(&server, &cache, &clock, "replica-1");
+    let second = replica(&server, &cache, &clock, "replica-2");
+    let third = replica(&server, &cache, &clock, "replica-3");
+    let updates = second.subscribe();
+
+    let mut status = first.refresh_processor_health("default").await.unwrap();
+    assert_eq!(status.min_response_time_ms, Some(25));
+
+    for _ in 0..3 {
+        assert_eq!(second.refresh_processor_health("default").await.unwrap(), status);
+        assert_eq!(third.refresh_processor_health("default").await.unwrap(), status);
+        clock.advance(Duration::from_secs(5));
+        status = first.refresh_processor_health("default").await.unwrap();
+    }
+
+    // Followers publish what they read so their selectors see it too
+    assert_eq!(updates.borrow()["default"].min_response_time_ms, 25);
+    assert_eq!(health.hits_async().await, 4);
+}
+
+#[tokio::test]
+async fn test_lease_is_taken_over_when_holder_stops() {
+    let server = MockServer::start_async().await;
+    let health = server.mock_async(|when, then| {
+        when.method(GET).path("/default/payments/service-health");
+        then.status(200).json_body_obj(&serde_json::json!({"failing": false, "minResponseTime": 0}));
+    }).await;
+    let clock = Arc::new(ManualClock::new(START_MILLIS));
+    let mut cache = CacheManager::new();
+    cache.set_clock(clock.clone());
+    let cache = Arc::new(cache);
+
+    let first = replica(&server, &cache, &clock, "replica-1");
+    let second = replica(&server, &cache, &clock, "replica-2");
+    assert_eq!(first.get_lease_ttl(), Duration::from_secs(10));
+
+    first.refresh_processor_health("default").await.unwrap();
+    second.refresh_processor_health("default").await.unwrap();
+    assert_eq!(health.hits_async().await, 1);
+
+    // replica-1 stops renewing; its lease runs out
+    clock.advance(Duration::from_secs(11));
+    second.refresh_processor_health("default").await.unwrap();
+    assert_eq!(health.hits_async().await, 2);
+
+    // and now it is replica-1 that only reads
+    first.refresh_processor_health("default").await.unwrap();
+    assert_eq!(health.hits_async().await, 2);
+}
+
+#[tokio::test]
+async fn test_lease_primitive() {
+    let clock = Arc::new(ManualClock::new(START_MILLIS));
+    let mut cache = CacheManager::new();
+    cache.set_clock(clock.clone());
+
+    assert!(cache.acquire_lease("lease", "a", Duration::from_secs(10)).await.unwrap());
+    assert!(!cache.acquire_lease("lease", "b", Duration::from_secs(10)).await.unwrap());
+    // The holder renews its own lease
+    clock.advance(Duration::from_secs(8));
+    assert!(cache.acquire_lease("lease", "a", Duration::from_secs(10)).await.unwrap());
+    clock.advance(Duration::from_secs(8));
+    assert!(!cache.acquire_lease("lease", "b", Duration::from_secs(10)).await.unwrap());
+    clock.advance(Duration::from_secs(11));
+    assert!(cache.acquire_lease("lease", "b", Duration::from_secs(10)).await.unwrap());
+}